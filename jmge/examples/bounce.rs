@@ -0,0 +1,77 @@
+
+use jmge::*;
+use std::rc::Rc;
+
+
+struct Bounce
+{
+	ball: Option<Entity>,
+	pos: (f32, f32),
+	old_pos: (f32, f32),
+	vel: (f32, f32),
+}
+
+impl App for Bounce
+{
+	fn init(&mut self, ctx: &mut AppContext) -> Result<(), Error>
+	{
+		// A single ball, moved at a fixed rate and drawn interpolated
+		ctx.clear_color = Color::rgb(0.1, 0.1, 0.2);
+		ctx.window.set_fps_in_title(true);
+
+		let tex = Rc::new(Texture::from_canvas(&Canvas::new(32, 32, Color::rgb(1.0, 0.5, 0.2)), false));
+		let ball = ctx.world.new_entity();
+		ctx.world.set(&ball, Renderable::new(&tex, self.pos.0, self.pos.1));
+
+		self.ball = Some(ball);
+
+		Ok(())
+	}
+
+	fn on_event(&mut self, ctx: &mut AppContext, ev: &Event)
+	{
+		if let Event::FocusLost = ev
+			{ ctx.set_fixed_step(1.0 / 10.0); }
+		else if let Event::FocusGained = ev
+			{ ctx.set_fixed_step(1.0 / 60.0); }
+	}
+
+	fn fixed_update(&mut self, ctx: &mut AppContext, dt: f32)
+	{
+		self.old_pos = self.pos;
+		self.pos.0 += self.vel.0 * dt;
+		self.pos.1 += self.vel.1 * dt;
+
+		let (w, h) = ctx.window.size();
+
+		if self.pos.0<0.0 || self.pos.0>(w-32) as f32
+			{ self.vel.0 = -self.vel.0; }
+
+		if self.pos.1<0.0 || self.pos.1>(h-32) as f32
+			{ self.vel.1 = -self.vel.1; }
+	}
+
+	fn update(&mut self, ctx: &mut AppContext, _dt: f32)
+	{
+		if ctx.window.keyboard().key_pressed(Key::Escape)
+			{ ctx.quit(); }
+
+		// Draw between the last two fixed positions
+		let a = ctx.alpha();
+		let mut r = ctx.world.get_mut::<Renderable>(self.ball.as_ref().unwrap());
+		r.x = self.old_pos.0 + (self.pos.0-self.old_pos.0)*a;
+		r.y = self.old_pos.1 + (self.pos.1-self.old_pos.1)*a;
+	}
+}
+
+
+fn main()
+{
+	let app = Bounce { ball: None, pos: (100.0, 100.0), old_pos: (100.0, 100.0), vel: (300.0, 200.0) };
+
+	if let Err(e) = run(WindowBuilder::new().size(1280, 720).title("Bounce"), app)
+	{
+		println!("Fatal error: {}", e);
+		std::process::exit(1);
+	}
+}
@@ -0,0 +1,74 @@
+
+use jmge::*;
+use std::rc::Rc;
+use std::time::Instant;
+
+
+const SPRITES: usize = 100000;
+const FRAMES: u32 = 200;
+
+
+fn bench(wnd: &mut Window, rend: &mut Renderer, tex: &Rc<Texture>, instanced: bool) -> f64
+{
+	// Render the same sprites for a number of frames, and return the average frame time in ms
+	rend.set_instanced(instanced);
+
+	let start = Instant::now();
+
+	for f in 0..FRAMES
+	{
+		wnd.poll_events();
+
+		for i in 0..SPRITES
+		{
+			let x = (i*37 % 1920) as f32;
+			let y = (i*91 % 1080) as f32;
+
+			let quad = Quad::new(tex)
+				.with_pos(x, y)
+				.with_origin(8.0, 8.0)
+				.with_angle((f as f32 + i as f32) / 100.0);
+
+			rend.add_quad(quad);
+		}
+
+		wnd.clear(Color::rgb(0.0, 0.0, 0.0));
+		rend.render(wnd.projection_matrix());
+		wnd.swap();
+	}
+
+	start.elapsed().as_secs_f64() * 1000.0 / FRAMES as f64
+}
+
+
+fn run() -> Result<(), Error>
+{
+	let mut wnd = Window::new()?;
+	let mut rend = Renderer::new()?;
+	wnd.set_vsync(false);
+
+	let tex = Rc::new(Texture::from_canvas(&Canvas::new(16, 16, Color::rgb(1.0, 0.5, 0.2)), false));
+
+	// Before: 6 vertices of 48 bytes per quad
+	let before = bench(&mut wnd, &mut rend, &tex, false);
+
+	// After: one 56 bytes instance record per quad
+	let after = bench(&mut wnd, &mut rend, &tex, true);
+
+	println!("{} sprites, {} frames", SPRITES, FRAMES);
+	println!("per-vertex: {:.2} ms/frame, {} KiB uploaded/frame", before, SPRITES*6*48/1024);
+	println!("instanced:  {:.2} ms/frame, {} KiB uploaded/frame", after, SPRITES*56/1024);
+
+	Ok(())
+}
+
+
+fn main()
+{
+	if let Err(e) = run()
+	{
+		println!("Fatal error: {}", e);
+		std::process::exit(1);
+	}
+}
+
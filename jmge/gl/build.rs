@@ -5,9 +5,9 @@ use std::path::Path;
 
 fn main() {
     let dest = env::var("OUT_DIR").unwrap();
-    let mut file = File::create(&Path::new(&dest).join("bindings.rs")).unwrap();
+    let mut file = File::create(Path::new(&dest).join("bindings.rs")).unwrap();
 
-    Registry::new(Api::Gl, (3, 2), Profile::Core, Fallbacks::All, [])
+    Registry::new(Api::Gl, (3, 3), Profile::Core, Fallbacks::All, [])
         .write_bindings(GlobalGenerator, &mut file)
         .unwrap();
 }
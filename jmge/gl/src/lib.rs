@@ -1 +1,4 @@
+// The bindings are generated, lints on them are up to the generator
+#![allow(clippy::all)]
+
 include!(concat!(env!("OUT_DIR"), "/bindings.rs"));
@@ -0,0 +1,43 @@
+
+#version 330 core
+
+in vec2 WorldPos;
+
+uniform vec2 LightPos;
+uniform float Radius;
+uniform float Falloff;
+uniform float Height;
+uniform vec3 LightColor;
+uniform vec2 SpotDir;
+uniform float SpotCos;
+uniform sampler2D Normals;
+uniform vec2 ScreenSize;
+
+out vec4 Color;
+
+void main()
+{
+	vec2 d = WorldPos - LightPos;
+
+	// Distance attenuation
+	float Att = pow(clamp(1.0 - length(d) / Radius, 0.0, 1.0), Falloff);
+
+	// Spot cone, point lights use a cosine of -1
+	if (SpotCos > -1.0)
+		Att *= smoothstep(SpotCos, SpotCos + 0.05, dot(normalize(d), SpotDir));
+
+	// Normal mapping, where something was drawn in the normal buffer
+	vec4 n = texture(Normals, gl_FragCoord.xy / ScreenSize);
+
+	if (n.a > 0.0)
+	{
+		// Normal maps have Y going up, the world has it going down
+		vec3 N = normalize(n.xyz * 2.0 - 1.0);
+		N.y = -N.y;
+
+		vec3 L = normalize(vec3(-d, Height));
+		Att *= max(dot(N, L), 0.0);
+	}
+
+	Color = vec4(LightColor * Att, 1.0);
+}
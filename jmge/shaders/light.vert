@@ -0,0 +1,17 @@
+
+#version 330 core
+
+out vec2 WorldPos;
+
+uniform mat4 Projection;
+uniform vec2 LightPos;
+uniform float Radius;
+
+void main()
+{
+	// Square around the light, from the vertex index within the triangle strip
+	vec2 Corner = vec2(gl_VertexID & 1, gl_VertexID >> 1) * 2.0 - 1.0;
+
+	WorldPos = LightPos + Corner * Radius;
+	gl_Position = Projection * vec4(WorldPos, 0.0, 1.0);
+}
@@ -0,0 +1,13 @@
+
+#version 330 core
+
+out vec2 TexCoord;
+
+void main()
+{
+	// Full screen triangle, from the vertex index
+	vec2 Pos = vec2((gl_VertexID << 1) & 2, gl_VertexID & 2);
+
+	gl_Position = vec4(Pos * 2.0 - 1.0, 0.0, 1.0);
+	TexCoord = Pos;
+}
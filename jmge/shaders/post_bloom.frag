@@ -0,0 +1,34 @@
+
+#version 330 core
+
+in vec2 TexCoord;
+
+uniform sampler2D tex;
+uniform vec2 TexelSize;
+uniform float Threshold;
+uniform float Intensity;
+
+out vec4 Color;
+
+void main()
+{
+	vec4 Base = texture(tex, TexCoord);
+
+	// Gather the bright parts around this pixel
+	vec3 Glow = vec3(0.0);
+	float Total = 0.0;
+
+	for (int y = -3; y <= 3; y++)
+	{
+		for (int x = -3; x <= 3; x++)
+		{
+			vec3 c = texture(tex, TexCoord + vec2(x, y) * TexelSize * 2.0).rgb;
+			float w = 1.0 / (1.0 + float(x*x + y*y));
+
+			Glow += max(c - vec3(Threshold), vec3(0.0)) * w;
+			Total += w;
+		}
+	}
+
+	Color = vec4(Base.rgb + Glow / Total * Intensity, Base.a);
+}
@@ -0,0 +1,20 @@
+
+#version 330 core
+
+in vec2 TexCoord;
+
+uniform sampler2D tex;
+uniform vec2 Direction;
+
+out vec4 Color;
+
+void main()
+{
+	// 9 tap gaussian along one direction, using linear filtering between texels
+	vec4 Sum = texture(tex, TexCoord) * 0.227027;
+
+	Sum += (texture(tex, TexCoord + Direction*1.384615) + texture(tex, TexCoord - Direction*1.384615)) * 0.316216;
+	Sum += (texture(tex, TexCoord + Direction*3.230769) + texture(tex, TexCoord - Direction*3.230769)) * 0.070270;
+
+	Color = Sum;
+}
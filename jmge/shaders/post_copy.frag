@@ -0,0 +1,13 @@
+
+#version 330 core
+
+in vec2 TexCoord;
+
+uniform sampler2D tex;
+
+out vec4 Color;
+
+void main()
+{
+	Color = texture(tex, TexCoord);
+}
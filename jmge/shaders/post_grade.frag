@@ -0,0 +1,25 @@
+
+#version 330 core
+
+in vec2 TexCoord;
+
+uniform sampler2D tex;
+uniform sampler2D Lut;
+
+out vec4 Color;
+
+void main()
+{
+	// The LUT is a 256x16 strip of 16 slices, red along x, green along y, one slice per blue step
+	vec4 c = texture(tex, TexCoord);
+
+	float b = c.b * 15.0;
+	float s0 = floor(b);
+	float s1 = min(s0 + 1.0, 15.0);
+
+	vec2 uv = vec2((c.r * 15.0 + 0.5) / 256.0, (c.g * 15.0 + 0.5) / 16.0);
+	vec3 c0 = texture(Lut, uv + vec2(s0 / 16.0, 0.0)).rgb;
+	vec3 c1 = texture(Lut, uv + vec2(s1 / 16.0, 0.0)).rgb;
+
+	Color = vec4(mix(c0, c1, b - s0), c.a);
+}
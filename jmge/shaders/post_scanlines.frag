@@ -0,0 +1,18 @@
+
+#version 330 core
+
+in vec2 TexCoord;
+
+uniform sampler2D tex;
+uniform float Intensity;
+uniform float Count;
+
+out vec4 Color;
+
+void main()
+{
+	vec4 c = texture(tex, TexCoord);
+	float Line = 0.5 + 0.5 * sin(TexCoord.y * Count * 6.2831853);
+
+	Color = vec4(c.rgb * (1.0 - Intensity * Line), c.a);
+}
@@ -0,0 +1,14 @@
+
+#version 330 core
+
+in vec2 TexCoord;
+
+uniform sampler2D tex;
+uniform vec2 Offset;
+
+out vec4 Color;
+
+void main()
+{
+	Color = texture(tex, TexCoord + Offset);
+}
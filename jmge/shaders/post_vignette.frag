@@ -0,0 +1,18 @@
+
+#version 330 core
+
+in vec2 TexCoord;
+
+uniform sampler2D tex;
+uniform float Radius;
+uniform float Softness;
+
+out vec4 Color;
+
+void main()
+{
+	vec4 c = texture(tex, TexCoord);
+	float v = smoothstep(Radius, Radius - Softness, distance(TexCoord, vec2(0.5)));
+
+	Color = vec4(c.rgb * v, c.a);
+}
@@ -7,6 +7,8 @@ in VS_OUTPUT {
 } IN;
 
 uniform sampler2D tex;
+uniform sampler2D normals;
+uniform int NormalPass;
 
 out vec4 Color;
 
@@ -14,5 +16,8 @@ void main()
 {
 	Color = texture2D(tex, IN.TexCoord) * IN.Color;
 	//Color = IN.Color;
-}
 
+	// The normal pass keeps the sprite's shape, but not its color
+	if (NormalPass != 0)
+		Color = vec4(texture2D(normals, IN.TexCoord).rgb, Color.a);
+}
@@ -0,0 +1,588 @@
+use super::{Error, Input, Key, GamepadButton, GamepadAxis, Gamepad};
+use super::input::{KEYS, NUM_BUTTONS, BUTTONS, AXES, MAX_GAMEPADS};
+use json::JsonValue;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+
+// Input level an action counts as down at, for analog inputs
+const THRESHOLD: f32 = 0.5;
+
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Binding
+{
+	Key (Key),
+	MouseButton (u8),
+
+	// Wheel movement in one direction
+	ScrollUp,
+	ScrollDown,
+	ScrollLeft,
+	ScrollRight,
+
+	PadButton (GamepadButton),
+
+	// One direction of a gamepad axis, true for the positive one
+	PadAxis (GamepadAxis, bool),
+}
+
+impl Binding
+{
+	fn value(&self, input: &Input, pad: Option<&Gamepad>) -> f32
+	{
+		// Current level, 0 or 1 for buttons
+		let flag = |b: bool| if b { 1.0 } else { 0.0 };
+		let (sx, sy) = input.mouse().scroll();
+
+		match *self
+		{
+			Binding::Key (key) => flag(input.keyboard().key_down(key)),
+			Binding::MouseButton (but) => flag(input.mouse().but_down(but)),
+			Binding::ScrollUp => sy.max(0.0),
+			Binding::ScrollDown => (-sy).max(0.0),
+			Binding::ScrollLeft => (-sx).max(0.0),
+			Binding::ScrollRight => sx.max(0.0),
+			Binding::PadButton (but) => flag(pad.is_some_and(|p| p.but_down(but))),
+			Binding::PadAxis (axis, positive) =>
+				{
+					let v = pad.map_or(0.0, |p| p.axis(axis));
+					if positive { v.max(0.0) } else { (-v).max(0.0) }
+				},
+		}
+	}
+
+	pub fn capture(input: &Input, pad: Option<&Gamepad>) -> Option<Binding>
+	{
+		// First input that started this frame, for "press a key" prompts in a controls menu
+		if let Some(key) = KEYS.iter().find(|k| input.keyboard().key_pressed(**k))
+			{ return Some(Binding::Key(*key)); }
+
+		if let Some(but) = (0..NUM_BUTTONS).find(|b| input.mouse().but_pressed(*b))
+			{ return Some(Binding::MouseButton(but)); }
+
+		let (sx, sy) = input.mouse().scroll();
+
+		if sy>0.0
+			{ return Some(Binding::ScrollUp); }
+
+		if sy<0.0
+			{ return Some(Binding::ScrollDown); }
+
+		if sx<0.0
+			{ return Some(Binding::ScrollLeft); }
+
+		if sx>0.0
+			{ return Some(Binding::ScrollRight); }
+
+		let pad = pad?;
+
+		if let Some(but) = BUTTONS.iter().find(|b| pad.but_pressed(**b))
+			{ return Some(Binding::PadButton(*but)); }
+
+		// Axes have no previous state, a deflection past the threshold counts
+		AXES.iter().map(|a| (*a, pad.axis(*a))).find(|(_, v)| v.abs()>THRESHOLD).map(|(a, v)| Binding::PadAxis(a, v>0.0))
+	}
+
+	pub fn parse(s: &str) -> Option<Binding>
+	{
+		// Parse the "Kind:Name" form used in the JSON files
+		let (kind, name) = s.split_once(':')?;
+
+		match kind
+		{
+			"Key" => KEYS.iter().find(|k| format!("{:?}", k)==name).map(|k| Binding::Key(*k)),
+			"Mouse" => name.parse().ok().filter(|b| *b<NUM_BUTTONS).map(Binding::MouseButton),
+			"Scroll" => match name
+				{
+					"Up" => Some(Binding::ScrollUp),
+					"Down" => Some(Binding::ScrollDown),
+					"Left" => Some(Binding::ScrollLeft),
+					"Right" => Some(Binding::ScrollRight),
+					_ => None,
+				},
+			"Pad" => BUTTONS.iter().find(|b| format!("{:?}", b)==name).map(|b| Binding::PadButton(*b)),
+			"PadAxis" =>
+				{
+					let positive = name.ends_with('+');
+
+					if !positive && !name.ends_with('-')
+						{ return None; }
+
+					let name = &name[..name.len()-1];
+					AXES.iter().find(|a| format!("{:?}", a)==name).map(|a| Binding::PadAxis(*a, positive))
+				},
+			_ => None,
+		}
+	}
+}
+
+impl fmt::Display for Binding
+{
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+	{
+		match self
+		{
+			Binding::Key (key)					=> write!(f, "Key:{:?}", key),
+			Binding::MouseButton (but)			=> write!(f, "Mouse:{}", but),
+			Binding::ScrollUp					=> write!(f, "Scroll:Up"),
+			Binding::ScrollDown					=> write!(f, "Scroll:Down"),
+			Binding::ScrollLeft					=> write!(f, "Scroll:Left"),
+			Binding::ScrollRight				=> write!(f, "Scroll:Right"),
+			Binding::PadButton (but)			=> write!(f, "Pad:{:?}", but),
+			Binding::PadAxis (axis, positive)	=> write!(f, "PadAxis:{:?}{}", axis, if *positive { "+" } else { "-" }),
+		}
+	}
+}
+
+//------------------------------------------------------------------------------------------------------------------------
+//------------------------------------------------------------------------------------------------------------------------
+//------------------------------------------------------------------------------------------------------------------------
+
+struct ActionContext
+{
+	// Contexts below a blocking one are inactive while it's on the stack
+	blocking: bool,
+
+	actions: BTreeMap<String, Vec<Binding>>,
+
+	// Bindings with the scale they contribute to the axis
+	axes: BTreeMap<String, Vec<(Binding, f32)>>,
+}
+
+impl ActionContext
+{
+	fn new(blocking: bool) -> ActionContext
+	{
+		ActionContext
+		{
+			blocking,
+			actions: BTreeMap::new(),
+			axes: BTreeMap::new(),
+		}
+	}
+}
+
+
+fn load_error(s: &str) -> Error
+{
+	Error::LoadActions(s.to_string())
+}
+
+fn parse_binding(j: &JsonValue) -> Result<Binding, Error>
+{
+	let s = j.as_str().ok_or_else(|| load_error("Bindings must be strings"))?;
+	Binding::parse(s).ok_or_else(|| load_error(&format!("Unknown binding '{}'", s)))
+}
+
+
+pub struct ActionMap
+{
+	contexts: BTreeMap<String, ActionContext>,
+	stack: Vec<String>,
+
+	// Gamepad index, or None for the first connected one
+	gamepad: Option<usize>,
+
+	// Current and previous down state of the actions, and the axis values
+	state: HashMap<String, (bool, bool)>,
+	values: HashMap<String, f32>,
+}
+
+impl ActionMap
+{
+	pub fn new() -> ActionMap
+	{
+		ActionMap
+		{
+			contexts: BTreeMap::new(),
+			stack: Vec::new(),
+			gamepad: None,
+			state: HashMap::new(),
+			values: HashMap::new(),
+		}
+	}
+
+	pub fn add_context(&mut self, name: &str, blocking: bool)
+	{
+		// Contexts are also created by binding to them, as blocking ones
+		self.contexts.entry(name.to_string()).or_insert_with(|| ActionContext::new(blocking)).blocking = blocking;
+	}
+
+	fn context_mut(&mut self, name: &str) -> &mut ActionContext
+	{
+		self.contexts.entry(name.to_string()).or_insert_with(|| ActionContext::new(true))
+	}
+
+	pub fn bind(&mut self, context: &str, action: &str, binding: Binding)
+	{
+		// Add a binding to an action, any of them triggers it
+		let bindings = self.context_mut(context).actions.entry(action.to_string()).or_default();
+
+		if !bindings.contains(&binding)
+			{ bindings.push(binding); }
+	}
+
+	pub fn bind_axis(&mut self, context: &str, axis: &str, binding: Binding, scale: f32)
+	{
+		// Add a binding to an axis, e.g. Key::A with -1.0 and Key::D with 1.0
+		self.context_mut(context).axes.entry(axis.to_string()).or_default().push((binding, scale));
+	}
+
+	pub fn set_bindings(&mut self, context: &str, action: &str, bindings: Vec<Binding>)
+	{
+		// Replace all the bindings of an action, for rebinding
+		self.context_mut(context).actions.insert(action.to_string(), bindings);
+	}
+
+	pub fn set_axis_bindings(&mut self, context: &str, axis: &str, bindings: Vec<(Binding, f32)>)
+	{
+		self.context_mut(context).axes.insert(axis.to_string(), bindings);
+	}
+
+	pub fn bindings(&self, context: &str, action: &str) -> &[Binding]
+	{
+		match self.contexts.get(context).and_then(|c| c.actions.get(action))
+		{
+			Some (b) => b,
+			None => &[],
+		}
+	}
+
+	pub fn axis_bindings(&self, context: &str, axis: &str) -> &[(Binding, f32)]
+	{
+		match self.contexts.get(context).and_then(|c| c.axes.get(axis))
+		{
+			Some (b) => b,
+			None => &[],
+		}
+	}
+
+	pub fn actions(&self, context: &str) -> Vec<String>
+	{
+		// Action names of a context, for listing them in a controls menu
+		self.contexts.get(context).map_or(Vec::new(), |c| c.actions.keys().cloned().collect())
+	}
+
+	pub fn push_context(&mut self, name: &str)
+	{
+		self.stack.push(name.to_string());
+	}
+
+	pub fn pop_context(&mut self) -> Option<String>
+	{
+		self.stack.pop()
+	}
+
+	pub fn top_context(&self) -> Option<&str>
+	{
+		self.stack.last().map(|s| s.as_str())
+	}
+
+	pub fn set_gamepad(&mut self, index: Option<usize>) -> Result<(), Error>
+	{
+		// Read a given joystick, between 0 and 15, or the first gamepad when None
+		if let Some(i) = index
+		{
+			if i>=MAX_GAMEPADS
+				{ return Err(Error::InvalidGamepad(i)); }
+		}
+
+		self.gamepad = index;
+		Ok(())
+	}
+
+	pub fn update(&mut self, input: &Input)
+	{
+		// Call once per frame, after polling the events
+		let pad = match self.gamepad
+			{
+				Some (i) => input.gamepads().get(i).filter(|p| p.is_connected()),
+				None => input.gamepads().first(),
+			};
+
+		let mut down = HashMap::new();
+		let mut values = HashMap::new();
+
+		// Go down the stack, the topmost context defining a name wins
+		for name in self.stack.iter().rev()
+		{
+			let ctx = match self.contexts.get(name)
+				{
+					Some (ctx) => ctx,
+					None => continue,
+				};
+
+			for (action, bindings) in ctx.actions.iter()
+			{
+				if !down.contains_key(action)
+					{ down.insert(action.clone(), bindings.iter().any(|b| b.value(input, pad)>THRESHOLD)); }
+			}
+
+			for (axis, bindings) in ctx.axes.iter()
+			{
+				if !values.contains_key(axis)
+				{
+					let v: f32 = bindings.iter().map(|(b, scale)| b.value(input, pad) * scale).sum();
+					values.insert(axis.clone(), v.clamp(-1.0, 1.0));
+				}
+			}
+
+			if ctx.blocking
+				{ break; }
+		}
+
+		// Actions that became inactive are released
+		for (action, state) in self.state.iter_mut()
+			{ *state = (down.remove(action).unwrap_or(false), state.0); }
+
+		for (action, d) in down.into_iter()
+			{ self.state.insert(action, (d, false)); }
+
+		self.values = values;
+	}
+
+	pub fn down(&self, action: &str) -> bool
+	{
+		self.state.get(action).is_some_and(|s| s.0)
+	}
+
+	pub fn pressed(&self, action: &str) -> bool
+	{
+		// Check if an action started on this frame
+		self.state.get(action).is_some_and(|s| s.0 && !s.1)
+	}
+
+	pub fn released(&self, action: &str) -> bool
+	{
+		// Check if an action ended on this frame
+		self.state.get(action).is_some_and(|s| !s.0 && s.1)
+	}
+
+	pub fn axis(&self, axis: &str) -> f32
+	{
+		// Between -1 and 1, 0 when the axis isn't active
+		self.values.get(axis).cloned().unwrap_or(0.0)
+	}
+
+	pub fn parse(s: &str) -> Result<ActionMap, Error>
+	{
+		// Parse the JSON data
+		let json = match json::parse(s)
+			{
+				Ok (json) => json,
+				Err (_) => return Err(load_error("Error parsing JSON data")),
+			};
+
+		if !json["contexts"].is_object()
+			{ return Err(load_error("Expected a 'contexts' object")); }
+
+		let mut map = ActionMap::new();
+
+		for (name, c) in json["contexts"].entries()
+		{
+			let blocking = match c["blocking"].is_null()
+				{
+					true => true,
+					false => c["blocking"].as_bool().ok_or_else(|| load_error("'blocking' must be a boolean"))?,
+				};
+
+			map.add_context(name, blocking);
+
+			for (action, bindings) in c["actions"].entries()
+			{
+				let bindings = bindings.members().map(parse_binding).collect::<Result<Vec<Binding>, Error>>()?;
+				map.set_bindings(name, action, bindings);
+			}
+
+			for (axis, bindings) in c["axes"].entries()
+			{
+				let mut list = Vec::new();
+
+				for b in bindings.members()
+				{
+					let scale = b["scale"].as_f32().ok_or_else(|| load_error("Axis 'scale' must be a number"))?;
+					list.push((parse_binding(&b["input"])?, scale));
+				}
+
+				map.set_axis_bindings(name, axis, list);
+			}
+		}
+
+		Ok(map)
+	}
+
+	pub fn from_file(fname: &str) -> Result<ActionMap, Error>
+	{
+		// Load the file
+		let s = match std::fs::read_to_string(fname)
+			{
+				Ok (s) => s,
+				Err (_) => return Err(load_error("Could not read the file")),
+			};
+
+		ActionMap::parse(&s)
+	}
+
+	pub fn to_json(&self) -> String
+	{
+		// Same format as parse(), the context stack isn't saved
+		let mut contexts = JsonValue::new_object();
+
+		for (name, ctx) in self.contexts.iter()
+		{
+			let mut actions = JsonValue::new_object();
+
+			for (action, bindings) in ctx.actions.iter()
+				{ actions[action.as_str()] = bindings.iter().map(|b| JsonValue::from(b.to_string())).collect::<Vec<JsonValue>>().into(); }
+
+			let mut axes = JsonValue::new_object();
+
+			for (axis, bindings) in ctx.axes.iter()
+			{
+				let mut list = JsonValue::new_array();
+
+				for (b, scale) in bindings.iter()
+				{
+					let mut entry = JsonValue::new_object();
+					entry["input"] = b.to_string().into();
+					entry["scale"] = (*scale).into();
+
+					let _ = list.push(entry);
+				}
+
+				axes[axis.as_str()] = list;
+			}
+
+			let mut c = JsonValue::new_object();
+			c["blocking"] = ctx.blocking.into();
+			c["actions"] = actions;
+			c["axes"] = axes;
+
+			contexts[name.as_str()] = c;
+		}
+
+		let mut json = JsonValue::new_object();
+		json["contexts"] = contexts;
+
+		json.pretty(4)
+	}
+
+	pub fn save(&self, fname: &str) -> Result<(), Error>
+	{
+		std::fs::write(fname, self.to_json()).map_err(|e| Error::SaveActions(e.to_string()))
+	}
+}
+
+impl Default for ActionMap
+{
+	fn default() -> ActionMap
+	{
+		ActionMap::new()
+	}
+}
+
+//------------------------------------------------------------------------------------------------------------------------
+//------------------------------------------------------------------------------------------------------------------------
+//------------------------------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+
+	#[test]
+	fn json_round_trip()
+	{
+		let mut map = ActionMap::new();
+		map.add_context("game", false);
+		map.add_context("menu", true);
+
+		map.bind("game", "jump", Binding::Key(Key::Space));
+		map.bind("game", "jump", Binding::PadButton(GamepadButton::ButtonA));
+		map.bind("game", "fire", Binding::MouseButton(1));
+		map.bind("game", "zoom", Binding::ScrollUp);
+		map.bind("menu", "back", Binding::Key(Key::Escape));
+		map.bind_axis("game", "move", Binding::Key(Key::A), -1.0);
+		map.bind_axis("game", "move", Binding::PadAxis(GamepadAxis::AxisLeftX, true), 1.0);
+
+		let json = map.to_json();
+		let loaded = ActionMap::parse(&json).unwrap();
+
+		assert_eq!(loaded.to_json(), json);
+		assert_eq!(loaded.bindings("game", "jump"), map.bindings("game", "jump"));
+		assert_eq!(loaded.axis_bindings("game", "move"), map.axis_bindings("game", "move"));
+	}
+
+	#[test]
+	fn binding_names()
+	{
+		for key in KEYS.iter()
+			{ assert_eq!(Binding::parse(&Binding::Key(*key).to_string()), Some(Binding::Key(*key))); }
+
+		assert_eq!(Binding::parse("Mouse:31"), Some(Binding::MouseButton(31)));
+		assert_eq!(Binding::parse("Mouse:32"), None);
+		assert_eq!(Binding::parse("Key:Unknown"), None);
+		assert_eq!(Binding::parse("PadAxis:AxisLeftX"), None);
+	}
+
+	#[test]
+	fn unknown_binding()
+	{
+		let json = r#"{ "contexts": { "game": { "actions": { "jump": [ "Key:Space", "Key:Spacebar" ] } } } }"#;
+		assert!(ActionMap::parse(json).is_err());
+	}
+
+	#[test]
+	fn blocking_context()
+	{
+		let mut map = ActionMap::new();
+		map.add_context("game", true);
+		map.add_context("menu", true);
+		map.add_context("hud", false);
+
+		map.bind("game", "jump", Binding::Key(Key::Space));
+		map.bind("menu", "select", Binding::Key(Key::Enter));
+		map.bind("hud", "toggle", Binding::Key(Key::Tab));
+
+		let mut input = Input::new();
+		input.keyboard_mut().set_key(Key::Space, true);
+		input.keyboard_mut().set_key(Key::Enter, true);
+
+		// Only the game context
+		map.push_context("game");
+		map.update(&input);
+		assert!(map.pressed("jump"));
+
+		// The menu hides the game below it
+		map.push_context("menu");
+		map.update(&input);
+		assert!(map.down("select"));
+		assert!(!map.down("jump"));
+		assert!(map.released("jump"));
+
+		// A non-blocking context on top leaves the menu active
+		map.push_context("hud");
+		map.update(&input);
+		assert!(map.down("select"));
+		assert!(!map.down("jump"));
+	}
+
+	#[test]
+	fn gamepad_index()
+	{
+		let mut map = ActionMap::new();
+		map.add_context("game", false);
+		map.bind("game", "jump", Binding::PadButton(GamepadButton::ButtonA));
+
+		assert!(map.set_gamepad(Some(15)).is_ok());
+		assert!(matches!(map.set_gamepad(Some(16)), Err(Error::InvalidGamepad(16))));
+		assert!(map.set_gamepad(None).is_ok());
+
+		// A rejected index leaves the previous one in place, and updating reads nothing
+		map.set_gamepad(Some(3)).unwrap();
+		let _ = map.set_gamepad(Some(100));
+		map.update(&Input::new());
+		assert!(!map.down("jump"));
+	}
+}
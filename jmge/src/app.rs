@@ -0,0 +1,160 @@
+use super::{Error, Window, WindowBuilder, Renderer, QuadRenderer, Renderable, Audio, World, Event, Color, ActionMap};
+
+
+pub struct AppContext
+{
+	pub window: Window,
+	pub renderer: Renderer,
+
+	// None when no audio device was found
+	pub audio: Option<Audio>,
+
+	pub world: World,
+
+	// Updated after polling the events, before on_event()
+	pub actions: ActionMap,
+
+	// Color the window is cleared to before drawing
+	pub clear_color: Color,
+
+	fixed_step: f32,
+	max_steps: u32,
+	acc: f32,
+	quit: bool,
+}
+
+impl AppContext
+{
+	pub fn set_fixed_step(&mut self, step: f32)
+	{
+		// Seconds per fixed_update() call
+		self.fixed_step = step.max(0.0001);
+	}
+
+	pub fn fixed_step(&self) -> f32
+	{
+		self.fixed_step
+	}
+
+	pub fn set_max_steps(&mut self, steps: u32)
+	{
+		// Fixed updates per frame at most, so a slow frame doesn't make the next ones slower
+		self.max_steps = steps.max(1);
+	}
+
+	pub fn alpha(&self) -> f32
+	{
+		// How far the time is between the last fixed update and the next, to interpolate drawing
+		self.acc / self.fixed_step
+	}
+
+	pub fn quit(&mut self)
+	{
+		// End the loop after this frame
+		self.quit = true;
+	}
+}
+
+//------------------------------------------------------------------------------------------------------------------------
+//------------------------------------------------------------------------------------------------------------------------
+//------------------------------------------------------------------------------------------------------------------------
+
+#[allow(unused_variables)]
+pub trait App
+{
+	// Called once, after the window, renderer and audio are set up
+	fn init(&mut self, ctx: &mut AppContext) -> Result<(), Error> { Ok(()) }
+
+	// Called for every window event, before the updates
+	fn on_event(&mut self, ctx: &mut AppContext, ev: &Event) {}
+
+	// Called at a fixed rate, zero or more times per frame
+	fn fixed_update(&mut self, ctx: &mut AppContext, dt: f32) {}
+
+	// Called once per frame with the time since the previous one, the world's systems run right after
+	fn update(&mut self, ctx: &mut AppContext, dt: f32) {}
+
+	// Called once per frame, after the world was added to the renderer and before it renders
+	fn draw(&mut self, ctx: &mut AppContext) {}
+
+	// Called once when the loop ends
+	fn shutdown(&mut self, ctx: &mut AppContext) {}
+}
+
+
+pub fn run<A: App>(builder: WindowBuilder, mut app: A) -> Result<(), Error>
+{
+	// Set everything up
+	let window = builder.build()?;
+	let renderer = Renderer::new()?;
+
+	let mut ctx = AppContext
+		{
+			window,
+			renderer,
+			audio: Audio::new().ok(),
+			world: World::new(),
+			actions: ActionMap::new(),
+			clear_color: Color::rgb(0.0, 0.0, 0.0),
+			fixed_step: 1.0 / 60.0,
+			max_steps: 5,
+			acc: 0.0,
+			quit: false,
+		};
+
+	// The renderer draws the world's renderables
+	ctx.world.register::<Renderable>();
+
+	app.init(&mut ctx)?;
+
+	loop
+	{
+		// Input and window events
+		ctx.window.poll_events();
+		ctx.actions.update(ctx.window.input());
+
+		let events = ctx.window.events().to_vec();
+
+		for ev in events.iter()
+			{ app.on_event(&mut ctx, ev); }
+
+		// A close request may have been vetoed by on_event()
+		if ctx.quit || ctx.window.should_close()
+			{ break; }
+
+		// Fixed steps for the time that passed, dropping what's left over after the maximum
+		let dt = ctx.window.delta();
+		ctx.acc += dt;
+
+		let mut steps = 0;
+
+		while ctx.acc>=ctx.fixed_step && steps<ctx.max_steps
+		{
+			let step = ctx.fixed_step;
+			app.fixed_update(&mut ctx, step);
+
+			ctx.acc -= step;
+			steps += 1;
+		}
+
+		if steps==ctx.max_steps
+			{ ctx.acc %= ctx.fixed_step; }
+
+		// Variable step
+		app.update(&mut ctx, dt);
+		ctx.world.run_all();
+
+		// Draw the frame
+		ctx.window.clear(ctx.clear_color);
+		ctx.renderer.add_world(&ctx.world);
+
+		app.draw(&mut ctx);
+
+		ctx.renderer.render(ctx.window.projection_matrix());
+		ctx.window.swap();
+	}
+
+	app.shutdown(&mut ctx);
+
+	Ok(())
+}
@@ -0,0 +1,128 @@
+
+use super::{Error, VertexAttrib, GlBackend};
+use std::cell::RefCell;
+use std::time::Duration;
+
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Primitive
+{
+	Triangles,
+	TriangleStrip,
+}
+
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Blend
+{
+	// The source replaces the target
+	Off,
+
+	// Regular alpha blending
+	Alpha,
+
+	// The source is added to the target, e.g. to accumulate lights
+	Add,
+
+	// The target is multiplied by the source, e.g. to apply a light map
+	Multiply,
+}
+
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum Uniform
+{
+	Int (i32),
+	Float (f32),
+	Vec2 (f32, f32),
+	Vec3 (f32, f32, f32),
+	Vec4 (f32, f32, f32, f32),
+
+	// Column major, like nalgebra
+	Matrix ([f32; 16]),
+}
+
+
+pub trait Backend
+{
+	// Textures, with RGBA pixels in Canvas layout
+	fn create_texture(&mut self, w: u32, h: u32, smooth: bool, data: Option<&[u32]>) -> u32;
+	fn replace_texture(&mut self, tex: u32, w: u32, h: u32, data: &[u32]);
+	fn update_texture(&mut self, tex: u32, x: i32, y: i32, w: u32, h: u32, data: &[u32]);
+	fn set_texture_repeat(&mut self, tex: u32, repeat: bool);
+	fn bind_texture(&mut self, unit: u32, tex: u32);
+	fn delete_texture(&mut self, tex: u32);
+
+	// Vertex or instance buffers, the attributes are in location order
+	fn create_buffer(&mut self, attribs: &[VertexAttrib], stride: usize, instanced: bool) -> u32;
+	fn upload_buffer(&mut self, buf: u32, data: &[u8]);
+	fn delete_buffer(&mut self, buf: u32);
+
+	// Pipelines, made of a vertex and a fragment shader
+	fn create_pipeline(&mut self, vert: &str, frag: &str) -> Result<u32, Error>;
+	fn bind_pipeline(&mut self, pipeline: u32);
+	fn set_uniform(&mut self, pipeline: u32, name: &str, value: Uniform);
+	fn delete_pipeline(&mut self, pipeline: u32);
+
+	// Draw submission, with the bound pipeline and textures
+	fn draw(&mut self, buf: u32, prim: Primitive, first: u32, count: u32);
+	fn draw_instanced(&mut self, buf: u32, prim: Primitive, first_instance: u32, instances: u32, vertices: u32);
+
+	// Render targets drawing into a texture, target 0 is the window
+	fn create_target(&mut self, tex: u32) -> Result<u32, Error>;
+	fn bind_target(&mut self, target: u32);
+	fn delete_target(&mut self, target: u32);
+
+	// State of the bound target, the viewport and clear rectangles are in pixels from the bottom left
+	fn set_viewport(&mut self, x: i32, y: i32, w: i32, h: i32);
+	fn clear(&mut self, col: [f32; 4], rect: Option<(i32, i32, i32, i32)>);
+	fn set_blend(&mut self, blend: Blend);
+
+	// Reading back a target, with the rows bottom-up
+	fn read_pixels(&mut self, target: u32, w: u32, h: u32) -> Vec<u32>;
+
+	// Asynchronous readbacks, into a buffer of the size given at creation
+	// finish_readback() returns None while the pixels aren't there, unless told to wait for them
+	// The pixels are empty when they couldn't be read
+	fn create_readback(&mut self, w: u32, h: u32) -> u32;
+	fn start_readback(&mut self, readback: u32, target: u32);
+	fn finish_readback(&mut self, readback: u32, wait: bool) -> Option<Vec<u32>>;
+	fn delete_readback(&mut self, readback: u32);
+
+	// GPU timers, None when the backend can't measure GPU time
+	// Only one timer runs at a time, the result is ready some frames after end_timer()
+	fn create_timer(&mut self) -> Option<u32>;
+	fn begin_timer(&mut self, timer: u32);
+	fn end_timer(&mut self, timer: u32);
+	fn timer_result(&mut self, timer: u32) -> Option<Duration>;
+	fn delete_timer(&mut self, timer: u32);
+}
+
+
+thread_local!
+{
+	static BACKEND: RefCell<Option<Box<dyn Backend>>> = RefCell::new(None);
+}
+
+
+pub fn set_backend(backend: Box<dyn Backend>) -> Option<Box<dyn Backend>>
+{
+	// Change the backend used by the current thread, returning the previous one
+	// Resources created with one backend can't be used with another
+	BACKEND.with(|b| b.borrow_mut().replace(backend))
+}
+
+pub(crate) fn with_backend<R, F>(f: F) -> R
+where
+	F: FnOnce(&mut dyn Backend) -> R
+{
+	// Run a call on the current backend, OpenGL unless another one was set
+	BACKEND.with(|b|
+		{
+			let mut b = b.borrow_mut();
+			let backend = b.get_or_insert_with(|| Box::new(GlBackend::new()));
+
+			f(backend.as_mut())
+		})
+}
+
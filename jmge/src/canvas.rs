@@ -24,12 +24,7 @@ impl Canvas
 		let height = height as usize;
 
 		// Allocate the data
-		let mut data = Vec::with_capacity(width*height);
-
-		unsafe
-		{
-			data.set_len(width*height);
-		}
+		let data = vec![0; width*height];
 
 		// Create the canvas
 		let mut cnv = Canvas
@@ -50,7 +45,7 @@ impl Canvas
 		// Create a canvas from an image
 
 		// Convert it to RGBA
-		let img = img.to_rgba();
+		let img = img.to_rgba8();
 
 		// Get the size
 		let w = img.width();
@@ -62,11 +57,10 @@ impl Canvas
 
 		// Copy the data into a vector
 		let size = (w*h) as usize;
-		let mut vec = Vec::with_capacity(size);
+		let mut vec = vec![0; size];
 
 		unsafe
 		{
-			vec.set_len(size);
 			std::ptr::copy_nonoverlapping(ptr, vec.as_mut_ptr(), size);
 		}
 
@@ -83,7 +77,7 @@ impl Canvas
 	pub fn from_file(fname: &str) -> Result<Canvas, Error>
 	{
 		// Load an image file
-		let img = match image::open(&std::path::Path::new(fname))
+		let img = match image::open(std::path::Path::new(fname))
 			{
 				Ok (i) => i,
 				Err (e) => return Err(Error::LoadImage(e.to_string())),
@@ -124,7 +118,7 @@ impl Canvas
 		unsafe
 		{
 			let sptr = self.data.as_ptr();
-			let mut dptr = self.data.as_mut_ptr() as *mut u32;
+			let mut dptr = self.data.as_mut_ptr();
 
 			for x in 0..self.w as isize
 				{ *(dptr.offset(x)) = col.0; }
@@ -143,6 +137,40 @@ impl Canvas
 		self.data
 	}
 
+	pub fn to_bytes(&self) -> Vec<u8>
+	{
+		// Copy the data as RGBA bytes
+		let mut bytes = Vec::with_capacity(self.data.len()*4);
+
+		for v in self.data.iter()
+			{ bytes.extend_from_slice(&v.to_le_bytes()); }
+
+		bytes
+	}
+
+	pub fn flip_vertical(&mut self)
+	{
+		// Swap the rows top to bottom, e.g. for OpenGL readbacks which are bottom-up
+		let w = self.w as usize;
+		let h = self.h as usize;
+
+		for y in 0..h/2
+		{
+			let (top, bottom) = self.data.split_at_mut((h-1-y)*w);
+			top[y*w..(y+1)*w].swap_with_slice(&mut bottom[..w]);
+		}
+	}
+
+	pub fn save_png(&self, fname: &str) -> Result<(), Error>
+	{
+		// Save the canvas as a PNG file
+		match image::save_buffer_with_format(fname, &self.to_bytes(), self.w, self.h, image::ColorType::Rgba8, image::ImageFormat::Png)
+		{
+			Ok (_) => Ok(()),
+			Err (e) => Err(Error::SaveImage(e.to_string())),
+		}
+	}
+
 	pub fn size(&self) -> (u32, u32)
 	{
 		(self.w, self.h)
@@ -173,8 +201,7 @@ impl Canvas
 			{ panic!("Canvas.sub(): invalid region"); }
 
 		// Copy the data into a new vector
-		let mut vec = Vec::with_capacity((w*h) as usize);
-		unsafe { vec.set_len((w*h) as usize); }
+		let mut vec = vec![0; (w*h) as usize];
 
 		let mut sptr = unsafe { self.data.as_ptr().offset((y*self.w+x) as isize) };
 		let mut dptr = vec.as_mut_ptr();
@@ -210,9 +237,31 @@ impl Canvas
 	pub fn get_pixel(&self, x: i32, y: i32) -> Color
 	{
 		if self.contains(x, y)
-			{ Color { 0:self.data[((y*self.w as i32)+x) as usize] } }
+			{ Color(self.data[((y*self.w as i32)+x) as usize]) }
 		else
-			{ Color { 0:0 } }
+			{ Color(0) }
+	}
+
+	pub fn max_difference(&self, o: &Canvas) -> Option<u8>
+	{
+		// Compare two canvases, returning the largest difference of any channel of any pixel
+		// Canvases of different sizes can't be compared
+		if self.size()!=o.size()
+			{ return None; }
+
+		let mut diff = 0;
+
+		for (a, b) in self.data.iter().zip(o.data.iter())
+		{
+			for shift in [0, 8, 16, 24].iter()
+			{
+				let ca = ((a>>shift) & 0xFF) as i32;
+				let cb = ((b>>shift) & 0xFF) as i32;
+				diff = diff.max((ca-cb).abs());
+			}
+		}
+
+		Some(diff as u8)
 	}
 
 	pub fn draw_char(&mut self, font: &Font, x: i32, y: i32, col: Color, ch: char) -> i32
@@ -232,7 +281,7 @@ impl Canvas
 					{ self.set_pixel(x+cx, y+cy, col.blend(self.get_pixel(x+cx, y+cy))); }
 			}
 
-			gw as i32
+			gw
 		}
 		else
 		{
@@ -249,6 +298,7 @@ impl Canvas
 		}
 	}
 
+	#[allow(clippy::too_many_arguments)]
 	pub fn blit(&mut self, mut dx: i32, mut dy: i32, o: &Canvas, mut sx: i32, mut sy: i32, w: u32, h: u32, alpha: bool)
 	{
 		// Clip the values
@@ -320,8 +370,8 @@ impl Canvas
 				{
 					unsafe
 					{
-						let scol = Color { 0:*sptr.offset(x as isize) };
-						let dcol = Color { 0:*dptr.offset(x as isize) };
+						let scol = Color(*sptr.offset(x as isize));
+						let dcol = Color(*dptr.offset(x as isize));
 						*dptr.offset(x as isize) = scol.blend(dcol).0;
 					}
 				}
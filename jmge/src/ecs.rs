@@ -154,7 +154,7 @@ impl CompVec
 		self.vec[i].is_some()
 	}
 
-	fn try_get(&self, i: usize) -> Option<Ref<dyn Component>>
+	fn try_get(&self, i: usize) -> Option<Ref<'_, dyn Component>>
 	{
 		if i>=self.vec.len()
 			{ return None; }
@@ -166,7 +166,7 @@ impl CompVec
 		}
 	}
 
-	fn try_get_mut(&self, i: usize) -> Option<RefMut<dyn Component>>
+	fn try_get_mut(&self, i: usize) -> Option<RefMut<'_, dyn Component>>
 	{
 		if i>=self.vec.len()
 			{ return None; }
@@ -178,22 +178,14 @@ impl CompVec
 		}
 	}
 
-	fn try_get_as<T:'static>(&self, i: usize) -> Option<Ref<T>>
+	fn try_get_as<T:'static>(&self, i: usize) -> Option<Ref<'_, T>>
 	{
-		match self.try_get(i)
-		{
-			Some(v) => Some(Ref::map(v, |v| v.as_any().downcast_ref::<T>().unwrap())),
-			None => None,
-		}
+		self.try_get(i).map(|v| Ref::map(v, |v| v.as_any().downcast_ref::<T>().unwrap()))
 	}
 
-	fn try_get_mut_as<T:'static>(&self, i: usize) -> Option<RefMut<T>>
+	fn try_get_mut_as<T:'static>(&self, i: usize) -> Option<RefMut<'_, T>>
 	{
-		match self.try_get_mut(i)
-		{
-			Some(v) => Some(RefMut::map(v, |v| v.as_any_mut().downcast_mut::<T>().unwrap())),
-			None => None,
-		}
+		self.try_get_mut(i).map(|v| RefMut::map(v, |v| v.as_any_mut().downcast_mut::<T>().unwrap()))
 	}
 
 
@@ -205,10 +197,10 @@ impl CompVec
 		{ self.try_get_mut(i).expect("no such component for this entity") }
 */
 
-	fn get_as<T:'static>(&self, i: usize) -> Ref<T>
+	fn get_as<T:'static>(&self, i: usize) -> Ref<'_, T>
 		{ self.try_get_as(i).expect("no such component for this entity") }
 
-	fn get_mut_as<T:'static>(&self, i: usize) -> RefMut<T>
+	fn get_mut_as<T:'static>(&self, i: usize) -> RefMut<'_, T>
 		{ self.try_get_mut_as(i).expect("no such component for this entity") }
 
 
@@ -311,6 +303,14 @@ impl World
 		self.comps.insert(id, CompVec::new());
 	}
 
+	pub fn is_registered<T>(&self) -> bool
+	where
+		T: 'static + Component
+	{
+		// Check if a component type has been registered
+		self.comps.contains_key(&TypeId::of::<T>())
+	}
+
 	fn recycle_entity(&mut self) -> Option<Entity>
 	{
 		// Try to recycle an entity
@@ -401,7 +401,7 @@ impl World
 		cv.set(*ent.id, Box::new(RefCell::new(val)));
 	}
 
-	pub fn try_get<T>(&self, ent: &Entity) -> Option<Ref<T>>
+	pub fn try_get<T>(&self, ent: &Entity) -> Option<Ref<'_, T>>
 	where
 		T: 'static + Component
 	{
@@ -412,7 +412,7 @@ impl World
 		cv.try_get_as(*ent.id)
 	}
 
-	pub fn get<T>(&self, ent: &Entity) -> Ref<T>
+	pub fn get<T>(&self, ent: &Entity) -> Ref<'_, T>
 	where
 		T: 'static + Component
 	{
@@ -423,7 +423,7 @@ impl World
 		cv.get_as(*ent.id)
 	}
 
-	pub fn try_get_mut<T>(&self, ent: &Entity) -> Option<RefMut<T>>
+	pub fn try_get_mut<T>(&self, ent: &Entity) -> Option<RefMut<'_, T>>
 	where
 		T: 'static + Component
 	{
@@ -434,7 +434,7 @@ impl World
 		cv.try_get_mut_as(*ent.id)
 	}
 
-	pub fn get_mut<T>(&self, ent: &Entity) -> RefMut<T>
+	pub fn get_mut<T>(&self, ent: &Entity) -> RefMut<'_, T>
 	where
 		T: 'static + Component
 	{
@@ -445,7 +445,7 @@ impl World
 		cv.get_mut_as(*ent.id)
 	}
 
-	pub fn iter<T>(&self) -> CompIter<T>
+	pub fn iter<T>(&self) -> CompIter<'_, T>
 	where
 		T: 'static + Component
 	{
@@ -456,7 +456,7 @@ impl World
 		CompIter::new(cv, &self.ents)
 	}
 
-	pub fn iter_mut<T>(&self) -> CompIterMut<T>
+	pub fn iter_mut<T>(&self) -> CompIterMut<'_, T>
 	where
 		T: 'static + Component
 	{
@@ -488,25 +488,13 @@ impl World
 	fn find_system(&self, name: &str) -> Option<&WorldSystem>
 	{
 		// Find the system with the given name
-		for ws in self.systems.iter()
-		{
-			if ws.name==name
-				{ return Some(ws); }
-		}
-
-		None
+		self.systems.iter().find(|ws| ws.name==name)
 	}
 
 	fn find_system_mut(&mut self, name: &str) -> Option<&mut WorldSystem>
 	{
 		// Find the system with the given name
-		for ws in self.systems.iter_mut()
-		{
-			if ws.name==name
-				{ return Some(ws); }
-		}
-
-		None
+		self.systems.iter_mut().find(|ws| ws.name==name)
 	}
 
 	pub fn add_system(&mut self, name: &str, sys: impl System+'static)
@@ -514,7 +502,7 @@ impl World
 		// Add a new system
 
 		// Look for duplicates
-		if let Some(_) = self.find_system(name)
+		if self.find_system(name).is_some()
 		{
 			panic!("duplicate system name");
 		}
@@ -566,3 +554,11 @@ impl World
 	}
 }
 
+impl Default for World
+{
+	fn default() -> World
+	{
+		World::new()
+	}
+}
+
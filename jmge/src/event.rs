@@ -0,0 +1,35 @@
+use std::path::PathBuf;
+
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum Event
+{
+	// Keyboard focus
+	FocusGained,
+	FocusLost,
+
+	// Mouse cursor moving over or off the window
+	CursorEntered,
+	CursorLeft,
+
+	// The user tried to close the window, call Window.cancel_close() to keep it open
+	CloseRequested,
+
+	// Window state
+	Minimized,
+	Maximized,
+	Restored,
+
+	// New framebuffer size in pixels, sent once per poll after the view was updated
+	Resized (u32, u32),
+
+	// Ratio between pixels and screen coordinates, e.g. 2.0 on a high-DPI display
+	ContentScale (f32, f32),
+
+	// Files dragged onto the window
+	FileDrop (Vec<PathBuf>),
+
+	// Joystick index, for Gamepads.get()
+	GamepadConnected (usize),
+	GamepadDisconnected (usize),
+}
@@ -1,6 +1,7 @@
 
-use super::{Error, Canvas};
+use super::{Error, Canvas, Texture, TextureAtlas};
 use std::collections::HashMap;
+use std::rc::Rc;
 
 
 pub struct Glyph
@@ -44,6 +45,7 @@ pub struct Font
 	char_map: HashMap<u32, u32>,
 	glyphs: Vec<Glyph>,
 	height: u32,
+	textures: Vec<Option<Rc<Texture>>>,
 }
 
 impl Font
@@ -165,6 +167,7 @@ impl Font
 			char_map,
 			glyphs,
 			height: ch,
+			textures: Vec::new(),
 		};
 
 		Ok(fnt)
@@ -184,6 +187,65 @@ impl Font
 		}
 	}
 
+	fn glyph_canvases(&self) -> Vec<Option<Canvas>>
+	{
+		// Create a canvas for every glyph, empty glyphs don't need one
+		self.glyphs.iter().map(|glyph|
+			{
+				if glyph.w==0 || glyph.h==0
+					{ return None; }
+
+				// Turn the set pixels white, so the glyph can be tinted
+				let data = glyph.data.iter().map(|v| if *v!=0 { 0xFFFFFFFF } else { 0 }).collect();
+				Some(Canvas::from_raw(glyph.w, glyph.h, data))
+			}).collect()
+	}
+
+	pub fn pack(&mut self, atlas: &mut TextureAtlas) -> Result<(), Error>
+	{
+		// Upload all the glyphs into a texture atlas, so they can be drawn by a Renderer
+		let mut textures = Vec::with_capacity(self.glyphs.len());
+
+		for cnv in self.glyph_canvases()
+		{
+			match cnv
+			{
+				Some (cnv) => textures.push(Some(Rc::new(atlas.add(cnv)?))),
+				None => textures.push(None),
+			}
+		}
+
+		self.textures = textures;
+
+		Ok(())
+	}
+
+	pub fn pack_soft(&mut self)
+	{
+		// Give every glyph a software texture, so a SoftRenderer can draw them without OpenGL
+		self.textures = self.glyph_canvases().into_iter().map(|cnv| cnv.map(|cnv| Rc::new(Texture::soft(cnv)))).collect();
+	}
+
+	pub fn is_packed(&self) -> bool
+	{
+		// Check if the glyphs have been packed into an atlas
+		!self.textures.is_empty()
+	}
+
+	pub fn glyph_texture(&self, ch: char) -> Option<&Rc<Texture>>
+	{
+		// Get the atlas texture for a character
+		match self.char_map.get(&(ch as u32))
+		{
+			Some(i) => match self.textures.get(*i as usize)
+				{
+					Some(Some(tex)) => Some(tex),
+					_ => None,
+				},
+			None => None,
+		}
+	}
+
 	pub fn char_width(&self, ch: char) -> u32
 	{
 		// Get the size of a character
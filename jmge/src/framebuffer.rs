@@ -0,0 +1,84 @@
+
+use super::{Error, Canvas, Texture, RawTexture, backend::with_backend};
+use std::rc::Rc;
+
+
+pub fn read_pixels(target: u32, w: u32, h: u32) -> Canvas
+{
+	// Read the pixels of a render target
+	let data = with_backend(|b| b.read_pixels(target, w, h));
+
+	// The backend returns the rows bottom-up, flip them
+	let mut cnv = Canvas::from_raw(w, h, data);
+	cnv.flip_vertical();
+
+	cnv
+}
+
+
+pub struct Framebuffer
+{
+	target: u32,
+	tex: Rc<Texture>,
+	w: u32,
+	h: u32,
+}
+
+impl Framebuffer
+{
+	pub fn new(w: u32, h: u32, smooth: bool) -> Result<Framebuffer, Error>
+	{
+		// Create the texture to render into
+		let raw = RawTexture::new(w, h, smooth);
+		let tex_id = raw.id();
+		let tex = Rc::new(Texture::from_raw(raw));
+
+		// Create the render target drawing into the texture
+		let target = with_backend(|b| b.create_target(tex_id))?;
+
+		Ok(Framebuffer
+			{
+				target,
+				tex,
+				w,
+				h,
+			})
+	}
+
+	pub fn size(&self) -> (u32, u32)
+	{
+		(self.w, self.h)
+	}
+
+	pub fn texture(&self) -> Rc<Texture>
+	{
+		// The rendered image, stored bottom-up like any OpenGL framebuffer
+		Rc::clone(&self.tex)
+	}
+
+	pub fn bind(&self)
+	{
+		// Render into this framebuffer
+		with_backend(|b|
+			{
+				b.bind_target(self.target);
+				b.set_viewport(0, 0, self.w as i32, self.h as i32);
+			});
+	}
+
+	pub fn read_pixels(&self) -> Canvas
+	{
+		// Read back the rendered image
+		read_pixels(self.target, self.w, self.h)
+	}
+}
+
+impl Drop for Framebuffer
+{
+	fn drop(&mut self)
+	{
+		// Delete the framebuffer, the texture goes away with its last reference
+		with_backend(|b| b.delete_target(self.target));
+	}
+}
+
@@ -0,0 +1,629 @@
+
+use super::{Error, VertexAttrib, AttribType, Backend, Primitive, Blend, Uniform};
+use std::collections::HashMap;
+use std::ffi::CString;
+use std::time::Duration;
+
+
+fn space_cstring(len: usize) -> CString
+{
+	// Create a C string filled with spaces
+	let mut buffer: Vec<u8> = Vec::with_capacity(len+1);
+	buffer.extend([b' '].iter().cycle().take(len));
+	unsafe { CString::from_vec_unchecked(buffer) }
+}
+
+fn compile_shader(source: &str, stype: u32) -> Result<u32, Error>
+{
+	let id = unsafe { gl::CreateShader(stype) };
+
+	unsafe
+	{
+		// Compile the shader
+		let len = source.len() as i32;
+		gl::ShaderSource(id, 1, &(source.as_ptr() as *const i8), &len);
+		gl::CompileShader(id);
+
+		// Check success
+		let mut success: i32 = 1;
+		gl::GetShaderiv(id, gl::COMPILE_STATUS, &mut success);
+
+		if success==0
+		{
+			// Failure, extract the error message
+			let mut len: i32 = 0;
+			gl::GetShaderiv(id, gl::INFO_LOG_LENGTH, &mut len);
+
+			let error: CString = space_cstring(len as usize);
+			gl::GetShaderInfoLog(id, len, std::ptr::null_mut(), error.as_ptr() as *mut i8);
+			gl::DeleteShader(id);
+
+			return Err(Error::CompileShader(error.to_string_lossy().into_owned()));
+		}
+	}
+
+	Ok(id)
+}
+
+unsafe fn setup_attribs(attribs: &[VertexAttrib], stride: usize, base: usize, divisor: u32)
+{
+	// Define the attributes, in location order
+	for (i, a) in attribs.iter().enumerate()
+	{
+		let (kind, norm) = match a.kind
+			{
+				AttribType::Float => (gl::FLOAT, gl::FALSE),
+				AttribType::UByteNorm => (gl::UNSIGNED_BYTE, gl::TRUE),
+			};
+
+		gl::EnableVertexAttribArray(i as u32);
+		gl::VertexAttribPointer(i as u32, a.count, kind, norm, stride as i32, (base+a.offset) as *const std::os::raw::c_void);
+
+		// Divisors are only available from GL 3.3 on
+		if divisor>0
+			{ gl::VertexAttribDivisor(i as u32, divisor); }
+	}
+}
+
+fn gl_primitive(prim: Primitive) -> u32
+{
+	match prim
+	{
+		Primitive::Triangles => gl::TRIANGLES,
+		Primitive::TriangleStrip => gl::TRIANGLE_STRIP,
+	}
+}
+
+
+struct GlBuffer
+{
+	vbo: u32,
+	vao: u32,
+	size: usize,
+	attribs: Vec<VertexAttrib>,
+	stride: usize,
+}
+
+
+struct GlReadback
+{
+	pbo: u32,
+	w: u32,
+	h: u32,
+
+	// Set while a readback is in flight
+	fence: Option<gl::types::GLsync>,
+}
+
+
+pub struct GlBackend
+{
+	// Buffers, by VAO name
+	buffers: HashMap<u32, GlBuffer>,
+	program: u32,
+
+	// Readbacks, by pixel buffer name
+	readbacks: HashMap<u32, GlReadback>,
+}
+
+impl GlBackend
+{
+	pub fn new() -> GlBackend
+	{
+		GlBackend
+		{
+			buffers: HashMap::new(),
+			program: 0,
+			readbacks: HashMap::new(),
+		}
+	}
+
+	fn buffer(&self, buf: u32) -> &GlBuffer
+	{
+		self.buffers.get(&buf).expect("GlBackend: unknown buffer")
+	}
+}
+
+impl Default for GlBackend
+{
+	fn default() -> GlBackend
+	{
+		GlBackend::new()
+	}
+}
+
+impl Backend for GlBackend
+{
+	fn create_texture(&mut self, w: u32, h: u32, smooth: bool, data: Option<&[u32]>) -> u32
+	{
+		// Create an OpenGL texture
+		let mut id = 0;
+		let ptr = match data
+			{
+				Some (data) => data.as_ptr() as *const std::os::raw::c_void,
+				None => std::ptr::null(),
+			};
+
+		unsafe
+		{
+			// Create the texture
+			gl::GenTextures(1, &mut id);
+
+			// Bind it and set its parameters
+			gl::BindTexture(gl::TEXTURE_2D, id);
+
+			let v = if smooth { gl::LINEAR } else { gl::NEAREST } as i32;
+			gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, v);
+			gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, v);
+
+			// Create the store
+			gl::TexImage2D(gl::TEXTURE_2D, 0, gl::RGBA as i32, w as i32, h as i32, 0, gl::RGBA, gl::UNSIGNED_BYTE, ptr);
+		}
+
+		id
+	}
+
+	fn replace_texture(&mut self, tex: u32, w: u32, h: u32, data: &[u32])
+	{
+		// Replace the whole store, the size may change
+		unsafe
+		{
+			gl::BindTexture(gl::TEXTURE_2D, tex);
+			gl::TexImage2D(gl::TEXTURE_2D, 0, gl::RGBA as i32, w as i32, h as i32, 0, gl::RGBA, gl::UNSIGNED_BYTE, data.as_ptr() as *const std::os::raw::c_void);
+		}
+	}
+
+	fn update_texture(&mut self, tex: u32, x: i32, y: i32, w: u32, h: u32, data: &[u32])
+	{
+		// Upload into part of the texture
+		unsafe
+		{
+			gl::BindTexture(gl::TEXTURE_2D, tex);
+			gl::TexSubImage2D(gl::TEXTURE_2D, 0, x, y, w as i32, h as i32, gl::RGBA, gl::UNSIGNED_BYTE, data.as_ptr() as *const std::os::raw::c_void);
+		}
+	}
+
+	fn set_texture_repeat(&mut self, tex: u32, repeat: bool)
+	{
+		// Repeat or clamp the UV coordinates
+		let v = if repeat { gl::REPEAT } else { gl::CLAMP_TO_EDGE } as i32;
+
+		unsafe
+		{
+			gl::BindTexture(gl::TEXTURE_2D, tex);
+			gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, v);
+			gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, v);
+		}
+	}
+
+	fn bind_texture(&mut self, unit: u32, tex: u32)
+	{
+		// Bind on the given unit, leaving the first one active
+		unsafe
+		{
+			gl::ActiveTexture(gl::TEXTURE0 + unit);
+			gl::BindTexture(gl::TEXTURE_2D, tex);
+			gl::ActiveTexture(gl::TEXTURE0);
+		}
+	}
+
+	fn delete_texture(&mut self, tex: u32)
+	{
+		unsafe { gl::DeleteTextures(1, &tex); }
+	}
+
+	fn create_buffer(&mut self, attribs: &[VertexAttrib], stride: usize, instanced: bool) -> u32
+	{
+		let mut vbo = 0;
+		let mut vao = 0;
+
+		unsafe
+		{
+			// Create the VBO and VAO
+			gl::GenBuffers(1, &mut vbo);
+			gl::GenVertexArrays(1, &mut vao);
+
+			// Define the attributes
+			gl::BindVertexArray(vao);
+			gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
+
+			setup_attribs(attribs, stride, 0, if instanced { 1 } else { 0 });
+
+			gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+			gl::BindVertexArray(0);
+		}
+
+		self.buffers.insert(vao, GlBuffer
+			{
+				vbo,
+				vao,
+				size: 0,
+				attribs: attribs.to_vec(),
+				stride,
+			});
+
+		vao
+	}
+
+	fn upload_buffer(&mut self, buf: u32, data: &[u8])
+	{
+		let b = self.buffers.get_mut(&buf).expect("GlBackend: unknown buffer");
+
+		unsafe
+		{
+			gl::BindBuffer(gl::ARRAY_BUFFER, b.vbo);
+
+			if data.len()>b.size
+			{
+				// Grow the buffer
+				b.size = data.len();
+				gl::BufferData(gl::ARRAY_BUFFER, b.size as isize, data.as_ptr() as *const std::os::raw::c_void, gl::STREAM_DRAW);
+			}
+			else
+			{
+				// Orphan the old store so the GPU can keep drawing from it
+				gl::BufferData(gl::ARRAY_BUFFER, b.size as isize, std::ptr::null(), gl::STREAM_DRAW);
+				gl::BufferSubData(gl::ARRAY_BUFFER, 0, data.len() as isize, data.as_ptr() as *const std::os::raw::c_void);
+			}
+
+			gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+		}
+	}
+
+	fn delete_buffer(&mut self, buf: u32)
+	{
+		// Drop the VAO and VBO
+		if let Some(b) = self.buffers.remove(&buf)
+		{
+			unsafe
+			{
+				gl::DeleteBuffers(1, &b.vbo);
+				gl::DeleteVertexArrays(1, &b.vao);
+			}
+		}
+	}
+
+	fn create_pipeline(&mut self, vert: &str, frag: &str) -> Result<u32, Error>
+	{
+		// Compile both shaders
+		let vs = compile_shader(vert, gl::VERTEX_SHADER)?;
+		let fs = match compile_shader(frag, gl::FRAGMENT_SHADER)
+			{
+				Ok (fs) => fs,
+				Err (e) => { unsafe { gl::DeleteShader(vs); } return Err(e); },
+			};
+
+		unsafe
+		{
+			// Link the program, the shaders aren't needed afterwards
+			let id = gl::CreateProgram();
+			gl::AttachShader(id, vs);
+			gl::AttachShader(id, fs);
+			gl::LinkProgram(id);
+			gl::DeleteShader(vs);
+			gl::DeleteShader(fs);
+
+			// Check success
+			let mut success: i32 = 1;
+			gl::GetProgramiv(id, gl::LINK_STATUS, &mut success);
+
+			if success==0
+			{
+				// Failure, extract the error message
+				let mut len: i32 = 0;
+				gl::GetProgramiv(id, gl::INFO_LOG_LENGTH, &mut len);
+
+				let error = space_cstring(len as usize);
+				gl::GetProgramInfoLog(id, len, std::ptr::null_mut(), error.as_ptr() as *mut i8);
+				gl::DeleteProgram(id);
+
+				return Err(Error::CompileShader(error.to_string_lossy().into_owned()));
+			}
+
+			Ok(id)
+		}
+	}
+
+	fn bind_pipeline(&mut self, pipeline: u32)
+	{
+		unsafe { gl::UseProgram(pipeline); }
+		self.program = pipeline;
+	}
+
+	fn set_uniform(&mut self, pipeline: u32, name: &str, value: Uniform)
+	{
+		// Uniforms are set on the current program
+		if self.program!=pipeline
+			{ self.bind_pipeline(pipeline); }
+
+		// Find the uniform
+		let uni = unsafe { gl::GetUniformLocation(pipeline, CString::new(name).unwrap().as_bytes_with_nul().as_ptr() as *const i8) };
+
+		if uni<0
+			{ panic!("ShaderProgram: Uniform '{}' not found", name); }
+
+		// Set it
+		unsafe
+		{
+			match value
+			{
+				Uniform::Int (v) => gl::Uniform1i(uni, v),
+				Uniform::Float (v) => gl::Uniform1f(uni, v),
+				Uniform::Vec2 (x, y) => gl::Uniform2f(uni, x, y),
+				Uniform::Vec3 (x, y, z) => gl::Uniform3f(uni, x, y, z),
+				Uniform::Vec4 (x, y, z, w) => gl::Uniform4f(uni, x, y, z, w),
+				Uniform::Matrix (ref m) => gl::UniformMatrix4fv(uni, 1, gl::FALSE, m.as_ptr()),
+			}
+		}
+	}
+
+	fn delete_pipeline(&mut self, pipeline: u32)
+	{
+		if self.program==pipeline
+			{ self.program = 0; }
+
+		unsafe { gl::DeleteProgram(pipeline); }
+	}
+
+	fn draw(&mut self, buf: u32, prim: Primitive, first: u32, count: u32)
+	{
+		unsafe
+		{
+			gl::BindVertexArray(self.buffer(buf).vao);
+			gl::DrawArrays(gl_primitive(prim), first as i32, count as i32);
+			gl::BindVertexArray(0);
+		}
+	}
+
+	fn draw_instanced(&mut self, buf: u32, prim: Primitive, first_instance: u32, instances: u32, vertices: u32)
+	{
+		let b = self.buffer(buf);
+
+		unsafe
+		{
+			// Bind the VAO
+			gl::BindVertexArray(b.vao);
+
+			// Point the attributes at the first instance, drawing from a base instance needs GL 4.2
+			gl::BindBuffer(gl::ARRAY_BUFFER, b.vbo);
+			setup_attribs(&b.attribs, b.stride, first_instance as usize*b.stride, 1);
+
+			gl::DrawArraysInstanced(gl_primitive(prim), 0, vertices as i32, instances as i32);
+
+			// Unbind
+			gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+			gl::BindVertexArray(0);
+		}
+	}
+
+	fn create_target(&mut self, tex: u32) -> Result<u32, Error>
+	{
+		// Create a framebuffer and attach the texture
+		let mut fbo = 0;
+
+		let status = unsafe
+			{
+				gl::GenFramebuffers(1, &mut fbo);
+				gl::BindFramebuffer(gl::FRAMEBUFFER, fbo);
+				gl::FramebufferTexture2D(gl::FRAMEBUFFER, gl::COLOR_ATTACHMENT0, gl::TEXTURE_2D, tex, 0);
+
+				let status = gl::CheckFramebufferStatus(gl::FRAMEBUFFER);
+				gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+
+				status
+			};
+
+		if status!=gl::FRAMEBUFFER_COMPLETE
+		{
+			unsafe { gl::DeleteFramebuffers(1, &fbo); }
+			return Err(Error::CreateFramebuffer);
+		}
+
+		Ok(fbo)
+	}
+
+	fn bind_target(&mut self, target: u32)
+	{
+		unsafe { gl::BindFramebuffer(gl::FRAMEBUFFER, target); }
+	}
+
+	fn delete_target(&mut self, target: u32)
+	{
+		unsafe { gl::DeleteFramebuffers(1, &target); }
+	}
+
+	fn set_viewport(&mut self, x: i32, y: i32, w: i32, h: i32)
+	{
+		unsafe { gl::Viewport(x, y, w, h); }
+	}
+
+	fn clear(&mut self, col: [f32; 4], rect: Option<(i32, i32, i32, i32)>)
+	{
+		unsafe
+		{
+			// Clearing follows the scissor rectangle, not the viewport
+			if let Some((x, y, w, h)) = rect
+			{
+				gl::Enable(gl::SCISSOR_TEST);
+				gl::Scissor(x, y, w, h);
+			}
+
+			gl::ClearColor(col[0], col[1], col[2], col[3]);
+			gl::Clear(gl::COLOR_BUFFER_BIT);
+
+			if rect.is_some()
+				{ gl::Disable(gl::SCISSOR_TEST); }
+		}
+	}
+
+	fn set_blend(&mut self, blend: Blend)
+	{
+		let func = match blend
+			{
+				Blend::Off => None,
+				Blend::Alpha => Some((gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA)),
+				Blend::Add => Some((gl::ONE, gl::ONE)),
+				Blend::Multiply => Some((gl::DST_COLOR, gl::ZERO)),
+			};
+
+		unsafe
+		{
+			match func
+			{
+				Some ((src, dst)) =>
+					{
+						gl::Enable(gl::BLEND);
+						gl::BlendFunc(src, dst);
+					},
+				None => gl::Disable(gl::BLEND),
+			}
+		}
+	}
+
+	fn read_pixels(&mut self, target: u32, w: u32, h: u32) -> Vec<u32>
+	{
+		let mut data: Vec<u32> = vec![0; (w*h) as usize];
+
+		unsafe
+		{
+			gl::BindFramebuffer(gl::READ_FRAMEBUFFER, target);
+			gl::PixelStorei(gl::PACK_ALIGNMENT, 4);
+			gl::ReadPixels(0, 0, w as i32, h as i32, gl::RGBA, gl::UNSIGNED_BYTE, data.as_mut_ptr() as *mut std::os::raw::c_void);
+			gl::BindFramebuffer(gl::READ_FRAMEBUFFER, 0);
+		}
+
+		data
+	}
+
+	fn create_readback(&mut self, w: u32, h: u32) -> u32
+	{
+		// Pixel buffer the GPU copies into, without stalling
+		let mut pbo = 0;
+
+		unsafe
+		{
+			gl::GenBuffers(1, &mut pbo);
+			gl::BindBuffer(gl::PIXEL_PACK_BUFFER, pbo);
+			gl::BufferData(gl::PIXEL_PACK_BUFFER, (w*h*4) as isize, std::ptr::null(), gl::STREAM_READ);
+			gl::BindBuffer(gl::PIXEL_PACK_BUFFER, 0);
+		}
+
+		self.readbacks.insert(pbo, GlReadback { pbo, w, h, fence: None });
+
+		pbo
+	}
+
+	fn start_readback(&mut self, readback: u32, target: u32)
+	{
+		let rb = self.readbacks.get_mut(&readback).expect("GlBackend: unknown readback");
+
+		unsafe
+		{
+			// A readback still in flight is dropped
+			if let Some(fence) = rb.fence.take()
+				{ gl::DeleteSync(fence); }
+
+			gl::BindFramebuffer(gl::READ_FRAMEBUFFER, target);
+			gl::BindBuffer(gl::PIXEL_PACK_BUFFER, rb.pbo);
+			gl::PixelStorei(gl::PACK_ALIGNMENT, 4);
+			gl::ReadPixels(0, 0, rb.w as i32, rb.h as i32, gl::RGBA, gl::UNSIGNED_BYTE, std::ptr::null_mut());
+			gl::BindBuffer(gl::PIXEL_PACK_BUFFER, 0);
+			gl::BindFramebuffer(gl::READ_FRAMEBUFFER, 0);
+
+			rb.fence = Some(gl::FenceSync(gl::SYNC_GPU_COMMANDS_COMPLETE, 0));
+		}
+	}
+
+	fn finish_readback(&mut self, readback: u32, wait: bool) -> Option<Vec<u32>>
+	{
+		let rb = self.readbacks.get_mut(&readback).expect("GlBackend: unknown readback");
+		let fence = rb.fence?;
+
+		// Check whether the copy is done, or wait for it
+		let timeout = if wait { u64::MAX } else { 0 };
+		let res = unsafe { gl::ClientWaitSync(fence, gl::SYNC_FLUSH_COMMANDS_BIT, timeout) };
+
+		if res!=gl::ALREADY_SIGNALED && res!=gl::CONDITION_SATISFIED
+			{ return None; }
+
+		rb.fence = None;
+
+		let count = (rb.w*rb.h) as usize;
+		let mut data: Vec<u32> = Vec::new();
+
+		unsafe
+		{
+			gl::DeleteSync(fence);
+
+			gl::BindBuffer(gl::PIXEL_PACK_BUFFER, rb.pbo);
+			let ptr = gl::MapBufferRange(gl::PIXEL_PACK_BUFFER, 0, (count*4) as isize, gl::MAP_READ_BIT) as *const u32;
+
+			if !ptr.is_null()
+			{
+				data.extend_from_slice(std::slice::from_raw_parts(ptr, count));
+				gl::UnmapBuffer(gl::PIXEL_PACK_BUFFER);
+			}
+
+			gl::BindBuffer(gl::PIXEL_PACK_BUFFER, 0);
+		}
+
+		Some(data)
+	}
+
+	fn delete_readback(&mut self, readback: u32)
+	{
+		if let Some(rb) = self.readbacks.remove(&readback)
+		{
+			unsafe
+			{
+				if let Some(fence) = rb.fence
+					{ gl::DeleteSync(fence); }
+
+				gl::DeleteBuffers(1, &rb.pbo);
+			}
+		}
+	}
+
+	fn create_timer(&mut self) -> Option<u32>
+	{
+		// Timer queries need GL 3.3 or ARB_timer_query
+		if !gl::GetQueryObjectui64v::is_loaded()
+			{ return None; }
+
+		let mut query = 0;
+		unsafe { gl::GenQueries(1, &mut query); }
+
+		Some(query)
+	}
+
+	fn begin_timer(&mut self, timer: u32)
+	{
+		unsafe { gl::BeginQuery(gl::TIME_ELAPSED, timer); }
+	}
+
+	fn end_timer(&mut self, _timer: u32)
+	{
+		unsafe { gl::EndQuery(gl::TIME_ELAPSED); }
+	}
+
+	fn timer_result(&mut self, timer: u32) -> Option<Duration>
+	{
+		// Waiting for the result would stall the pipeline, check whether it's there first
+		let mut available = 0;
+		unsafe { gl::GetQueryObjectiv(timer, gl::QUERY_RESULT_AVAILABLE, &mut available); }
+
+		if available==0
+			{ return None; }
+
+		let mut ns = 0;
+		unsafe { gl::GetQueryObjectui64v(timer, gl::QUERY_RESULT, &mut ns); }
+
+		Some(Duration::from_nanos(ns))
+	}
+
+	fn delete_timer(&mut self, timer: u32)
+	{
+		unsafe { gl::DeleteQueries(1, &timer); }
+	}
+}
+
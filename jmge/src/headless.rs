@@ -0,0 +1,210 @@
+use super::Error;
+use std::ffi::CString;
+use std::os::raw::{c_char, c_void};
+
+
+// An OpenGL context on an EGL pbuffer, for when GLFW can't start at all, e.g. without a display
+// libEGL is loaded at runtime, so it isn't needed to link or to run with a window
+
+type EglDisplay = *mut c_void;
+type EglConfig = *mut c_void;
+type EglSurface = *mut c_void;
+type EglContext = *mut c_void;
+
+const EGL_NONE: i32 = 0x3038;
+const EGL_PLATFORM_SURFACELESS_MESA: u32 = 0x31DD;
+const EGL_SURFACE_TYPE: i32 = 0x3033;
+const EGL_PBUFFER_BIT: i32 = 0x0001;
+const EGL_RENDERABLE_TYPE: i32 = 0x3040;
+const EGL_OPENGL_BIT: i32 = 0x0008;
+const EGL_RED_SIZE: i32 = 0x3024;
+const EGL_GREEN_SIZE: i32 = 0x3023;
+const EGL_BLUE_SIZE: i32 = 0x3022;
+const EGL_ALPHA_SIZE: i32 = 0x3021;
+const EGL_WIDTH: i32 = 0x3057;
+const EGL_HEIGHT: i32 = 0x3056;
+const EGL_OPENGL_API: u32 = 0x30A2;
+const EGL_CONTEXT_MAJOR_VERSION: i32 = 0x3098;
+const EGL_CONTEXT_MINOR_VERSION: i32 = 0x30FB;
+const EGL_CONTEXT_OPENGL_PROFILE_MASK: i32 = 0x30FD;
+const EGL_CONTEXT_OPENGL_CORE_PROFILE_BIT: i32 = 0x0001;
+
+
+unsafe fn to_fn<F: Copy>(p: *mut c_void) -> F
+{
+	// Only used with function pointer types, which have the size of a pointer
+	debug_assert_eq!(std::mem::size_of::<F>(), std::mem::size_of::<*mut c_void>());
+	std::mem::transmute_copy(&p)
+}
+
+struct Egl
+{
+	get_proc_address: unsafe extern "C" fn(*const c_char) -> *const c_void,
+	get_platform_display: unsafe extern "C" fn(u32, *mut c_void, *const isize) -> EglDisplay,
+	initialize: unsafe extern "C" fn(EglDisplay, *mut i32, *mut i32) -> u32,
+	terminate: unsafe extern "C" fn(EglDisplay) -> u32,
+	choose_config: unsafe extern "C" fn(EglDisplay, *const i32, *mut EglConfig, i32, *mut i32) -> u32,
+	bind_api: unsafe extern "C" fn(u32) -> u32,
+	create_pbuffer_surface: unsafe extern "C" fn(EglDisplay, EglConfig, *const i32) -> EglSurface,
+	destroy_surface: unsafe extern "C" fn(EglDisplay, EglSurface) -> u32,
+	create_context: unsafe extern "C" fn(EglDisplay, EglConfig, EglContext, *const i32) -> EglContext,
+	destroy_context: unsafe extern "C" fn(EglDisplay, EglContext) -> u32,
+	make_current: unsafe extern "C" fn(EglDisplay, EglSurface, EglSurface, EglContext) -> u32,
+}
+
+impl Egl
+{
+	fn load() -> Option<Egl>
+	{
+		// The library stays loaded, drivers don't like being unloaded
+		let lib = unsafe { libc::dlopen(b"libEGL.so.1\0".as_ptr() as *const c_char, libc::RTLD_NOW) };
+
+		if lib.is_null()
+			{ return None; }
+
+		let sym = |name: &str| -> Option<*mut c_void>
+			{
+				let name = CString::new(name).unwrap();
+				let p = unsafe { libc::dlsym(lib, name.as_ptr()) };
+				if p.is_null() { None } else { Some(p) }
+			};
+
+		unsafe
+		{
+			Some(Egl
+			{
+				get_proc_address: to_fn(sym("eglGetProcAddress")?),
+				get_platform_display: to_fn(sym("eglGetPlatformDisplay")?),
+				initialize: to_fn(sym("eglInitialize")?),
+				terminate: to_fn(sym("eglTerminate")?),
+				choose_config: to_fn(sym("eglChooseConfig")?),
+				bind_api: to_fn(sym("eglBindAPI")?),
+				create_pbuffer_surface: to_fn(sym("eglCreatePbufferSurface")?),
+				destroy_surface: to_fn(sym("eglDestroySurface")?),
+				create_context: to_fn(sym("eglCreateContext")?),
+				destroy_context: to_fn(sym("eglDestroyContext")?),
+				make_current: to_fn(sym("eglMakeCurrent")?),
+			})
+		}
+	}
+}
+
+
+pub(crate) struct HeadlessContext
+{
+	egl: Egl,
+	display: EglDisplay,
+	surface: EglSurface,
+	context: EglContext,
+	w: u32,
+	h: u32,
+}
+
+impl HeadlessContext
+{
+	pub fn new(w: u32, h: u32) -> Result<HeadlessContext, Error>
+	{
+		let fail = |s: &str| Error::CreateWindow(format!("could not create a headless OpenGL context, {}", s));
+
+		let egl = Egl::load().ok_or_else(|| fail("libEGL was not found"))?;
+
+		// The EGL calls only get pointers to locals and to what EGL returned
+		unsafe
+		{
+			// Mesa's surfaceless platform needs neither a display server nor a GPU
+			let display = (egl.get_platform_display)(EGL_PLATFORM_SURFACELESS_MESA, std::ptr::null_mut(), std::ptr::null());
+
+			if display.is_null() || (egl.initialize)(display, std::ptr::null_mut(), std::ptr::null_mut())==0
+				{ return Err(fail("no surfaceless EGL display")); }
+
+			// The pbuffer is the default framebuffer, like a window's
+			let config_attribs =
+				[
+					EGL_SURFACE_TYPE, EGL_PBUFFER_BIT,
+					EGL_RENDERABLE_TYPE, EGL_OPENGL_BIT,
+					EGL_RED_SIZE, 8,
+					EGL_GREEN_SIZE, 8,
+					EGL_BLUE_SIZE, 8,
+					EGL_ALPHA_SIZE, 8,
+					EGL_NONE,
+				];
+
+			let mut config = std::ptr::null_mut();
+			let mut count = 0;
+
+			if (egl.choose_config)(display, config_attribs.as_ptr(), &mut config, 1, &mut count)==0 || count==0
+			{
+				(egl.terminate)(display);
+				return Err(fail("no RGBA pbuffer config"));
+			}
+
+			let surface_attribs = [EGL_WIDTH, w as i32, EGL_HEIGHT, h as i32, EGL_NONE];
+			let surface = (egl.create_pbuffer_surface)(display, config, surface_attribs.as_ptr());
+
+			if surface.is_null()
+			{
+				(egl.terminate)(display);
+				return Err(fail("could not create the pbuffer"));
+			}
+
+			// Same version and profile as the windows get
+			let context_attribs =
+				[
+					EGL_CONTEXT_MAJOR_VERSION, 3,
+					EGL_CONTEXT_MINOR_VERSION, 3,
+					EGL_CONTEXT_OPENGL_PROFILE_MASK, EGL_CONTEXT_OPENGL_CORE_PROFILE_BIT,
+					EGL_NONE,
+				];
+
+			(egl.bind_api)(EGL_OPENGL_API);
+			let context = (egl.create_context)(display, config, std::ptr::null_mut(), context_attribs.as_ptr());
+
+			if context.is_null() || (egl.make_current)(display, surface, surface, context)==0
+			{
+				if !context.is_null()
+					{ (egl.destroy_context)(display, context); }
+
+				(egl.destroy_surface)(display, surface);
+				(egl.terminate)(display);
+				return Err(fail("no OpenGL 3.3 core context"));
+			}
+
+			Ok(HeadlessContext
+			{
+				egl,
+				display,
+				surface,
+				context,
+				w,
+				h,
+			})
+		}
+	}
+
+	pub fn get_proc_address(&self, name: &str) -> *const c_void
+	{
+		let name = CString::new(name).unwrap();
+		unsafe { (self.egl.get_proc_address)(name.as_ptr()) }
+	}
+
+	pub fn size(&self) -> (u32, u32)
+	{
+		(self.w, self.h)
+	}
+}
+
+impl Drop for HeadlessContext
+{
+	fn drop(&mut self)
+	{
+		let none = std::ptr::null_mut();
+
+		unsafe
+		{
+			(self.egl.make_current)(self.display, none, none, none);
+			(self.egl.destroy_context)(self.display, self.context);
+			(self.egl.destroy_surface)(self.display, self.surface);
+			(self.egl.terminate)(self.display);
+		}
+	}
+}
@@ -1,5 +1,49 @@
 
-pub use glfw::Key;
+pub use glfw::{Key, GamepadButton, GamepadAxis};
+use std::time::Instant;
+
+
+#[derive(Clone, Copy, PartialEq, Default, Debug)]
+pub struct Modifiers
+{
+	pub shift: bool,
+	pub ctrl: bool,
+	pub alt: bool,
+	pub super_key: bool,
+
+	// Lock states, not modifiers by themselves
+	pub caps_lock: bool,
+	pub num_lock: bool,
+}
+
+impl Modifiers
+{
+	pub fn from_glfw(mods: glfw::Modifiers) -> Modifiers
+	{
+		Modifiers
+		{
+			shift: mods.contains(glfw::Modifiers::Shift),
+			ctrl: mods.contains(glfw::Modifiers::Control),
+			alt: mods.contains(glfw::Modifiers::Alt),
+			super_key: mods.contains(glfw::Modifiers::Super),
+			caps_lock: mods.contains(glfw::Modifiers::CapsLock),
+			num_lock: mods.contains(glfw::Modifiers::NumLock),
+		}
+	}
+
+	pub fn none(&self) -> bool
+	{
+		// Whether no modifier key is held, the locks don't count
+		!(self.shift || self.ctrl || self.alt || self.super_key)
+	}
+}
+
+//------------------------------------------------------------------------------------------------------------------------
+//------------------------------------------------------------------------------------------------------------------------
+//------------------------------------------------------------------------------------------------------------------------
+
+// Bits of the button masks, GLFW itself reports 8 buttons
+pub(crate) const NUM_BUTTONS: u8 = 32;
 
 
 pub struct Mouse
@@ -8,8 +52,28 @@ pub struct Mouse
 	oy: i32,
 	x: i32,
 	y: i32,
-	obuts: u8,
-	buts: u8,
+	obuts: u32,
+	buts: u32,
+	scroll: (f32, f32),
+	mods: Modifiers,
+
+	// Cursor over the window, and whether that changed on this frame
+	inside: bool,
+	inside_changed: bool,
+
+	// Last press, for double-click detection
+	click_time: Option<Instant>,
+	click_but: u8,
+	click_pos: (i32, i32),
+	double_click: Option<u8>,
+	double_click_time: f32,
+
+	// Button and start position of a drag, which begins once the cursor moved far enough
+	drag_start: Option<(u8, i32, i32)>,
+	dragging: bool,
+	drag_started: bool,
+	drag_ended: Option<(u8, i32, i32)>,
+	drag_threshold: i32,
 }
 
 impl Mouse
@@ -25,6 +89,20 @@ impl Mouse
 			y: 0,
 			obuts: 0,
 			buts: 0,
+			scroll: (0.0, 0.0),
+			mods: Modifiers::default(),
+			inside: false,
+			inside_changed: false,
+			click_time: None,
+			click_but: 0,
+			click_pos: (0, 0),
+			double_click: None,
+			double_click_time: 0.4,
+			drag_start: None,
+			dragging: false,
+			drag_started: false,
+			drag_ended: None,
+			drag_threshold: 4,
 		}
 	}
 
@@ -34,6 +112,11 @@ impl Mouse
 		self.ox = self.x;
 		self.oy = self.y;
 		self.obuts = self.buts;
+		self.scroll = (0.0, 0.0);
+		self.inside_changed = false;
+		self.double_click = None;
+		self.drag_started = false;
+		self.drag_ended = None;
 	}
 
 	pub fn set_pos(&mut self, x: i32, y: i32)
@@ -41,6 +124,16 @@ impl Mouse
 		// Set a new mouse position
 		self.x = x;
 		self.y = y;
+
+		// Start dragging once the cursor moved far enough with the button down
+		if let Some((_, sx, sy)) = self.drag_start
+		{
+			if !self.dragging && ((x-sx).abs()>=self.drag_threshold || (y-sy).abs()>=self.drag_threshold)
+			{
+				self.dragging = true;
+				self.drag_started = true;
+			}
+		}
 	}
 
 	pub fn pos(&self) -> (i32, i32)					{ (self.x, self.y) }
@@ -48,60 +141,159 @@ impl Mouse
 	pub fn pos_delta(&self) -> (i32, i32)			{ (self.x-self.ox, self.y-self.oy) }
 	pub fn moved(&self) -> bool						{ (self.x!=self.ox) || (self.y!=self.oy) }
 
+	pub fn add_scroll(&mut self, x: f32, y: f32)
+	{
+		// Accumulate the wheel movement of this frame
+		self.scroll.0 += x;
+		self.scroll.1 += y;
+	}
+
+	pub fn scroll(&self) -> (f32, f32)				{ self.scroll }
+
+	pub fn set_modifiers(&mut self, mods: Modifiers)	{ self.mods = mods; }
+	pub fn modifiers(&self) -> Modifiers			{ self.mods }
+
+	pub fn set_inside(&mut self, inside: bool)
+	{
+		self.inside_changed = self.inside!=inside;
+		self.inside = inside;
+	}
+
+	pub fn is_inside(&self) -> bool					{ self.inside }
+	pub fn entered(&self) -> bool					{ self.inside_changed && self.inside }
+	pub fn left(&self) -> bool						{ self.inside_changed && !self.inside }
+
 	pub fn set_but(&mut self, but: u8, down: bool)
 	{
-		// Set/clear a button state
-		let but = 1<<but;
+		// Set/clear a button state, buttons beyond the mask are ignored
+		if but>=NUM_BUTTONS
+			{ return; }
+
+		let bit = 1<<but;
 
 		if down
 		{
-			self.buts |= but;
+			self.buts |= bit;
+			self.press_at(but, Instant::now());
 		}
 		else
 		{
-			self.buts &= 255-but;
+			self.buts &= !bit;
+			self.release(but);
 		}
 	}
 
-	pub fn buts(&self) -> u8						{ self.buts }
-	pub fn buts_old(&self) -> u8					{ self.obuts }
+	fn press_at(&mut self, but: u8, now: Instant)
+	{
+		// A second press of the same button, soon enough and close enough, is a double-click
+		let (px, py) = self.click_pos;
+
+		let double = match self.click_time
+			{
+				Some (t) => but==self.click_but && now.duration_since(t).as_secs_f32()<=self.double_click_time
+					&& (self.x-px).abs()<=self.drag_threshold && (self.y-py).abs()<=self.drag_threshold,
+				None => false,
+			};
+
+		if double
+		{
+			// A third press starts over
+			self.double_click = Some(but);
+			self.click_time = None;
+		}
+		else
+		{
+			self.click_time = Some(now);
+			self.click_but = but;
+			self.click_pos = (self.x, self.y);
+		}
+
+		if self.drag_start.is_none()
+			{ self.drag_start = Some((but, self.x, self.y)); }
+	}
+
+	fn release(&mut self, but: u8)
+	{
+		// Releasing the button that started a drag ends it
+		if let Some(start) = self.drag_start
+		{
+			if start.0==but
+			{
+				if self.dragging
+					{ self.drag_ended = Some(start); }
+
+				self.drag_start = None;
+				self.dragging = false;
+			}
+		}
+	}
+
+	pub fn buts(&self) -> u32						{ self.buts }
+	pub fn buts_old(&self) -> u32					{ self.obuts }
 
 	pub fn but_down(&self, but: u8) -> bool
 	{
 		// Get the down/up state of a specific button
-		if but>7
-			{ panic!("Mouse.but_down(): invalid button index"); }
-
-		(self.buts & (1<<but)) > 0
+		but<NUM_BUTTONS && (self.buts & (1<<but)) > 0
 	}
 
 	pub fn but_down_only(&self, but: u8) -> bool
 	{
 		// Check if a button is the only button down
-		if but>7
-			{ panic!("Mouse.but_down(): invalid button index"); }
-
-		self.buts == (1<<but)
+		but<NUM_BUTTONS && self.buts == (1<<but)
 	}
 
 	pub fn but_pressed(&self, but: u8) -> bool
 	{
 		// Check if a button was pressed on this frame
-		if but>7
-			{ panic!("Mouse.but_down(): invalid button index"); }
-		
-		((self.buts & (1<<but)) > 0) && ((self.obuts & (1<<but)) == 0)
+		but<NUM_BUTTONS && ((self.buts & (1<<but)) > 0) && ((self.obuts & (1<<but)) == 0)
 	}
 
 	pub fn but_released(&self, but: u8) -> bool
 	{
 		// Check if a button was released on this frame
-		if but>7
-			{ panic!("Mouse.but_down(): invalid button index"); }
-		
-		((self.buts & (1<<but)) == 0) && ((self.obuts & (1<<but)) > 0)
+		but<NUM_BUTTONS && ((self.buts & (1<<but)) == 0) && ((self.obuts & (1<<but)) > 0)
+	}
+
+	pub fn double_clicked(&self, but: u8) -> bool
+	{
+		// Check if a button was pressed a second time on this frame, quickly enough
+		self.double_click==Some(but)
 	}
 
+	pub fn set_double_click_time(&mut self, secs: f32)
+	{
+		self.double_click_time = secs;
+	}
+
+	pub fn set_drag_threshold(&mut self, dist: i32)
+	{
+		// Distance the cursor has to move with a button down before it counts as a drag
+		self.drag_threshold = dist.max(0);
+	}
+
+	pub fn is_dragging(&self) -> bool				{ self.dragging }
+	pub fn drag_started(&self) -> bool				{ self.drag_started }
+
+	pub fn drag(&self) -> Option<(u8, i32, i32)>
+	{
+		// Button and start position of the current drag
+		if self.dragging { self.drag_start } else { None }
+	}
+
+	pub fn drag_ended(&self) -> Option<(u8, i32, i32)>
+	{
+		// Button and start position of a drag that ended on this frame, the end is pos()
+		self.drag_ended
+	}
+}
+
+impl Default for Mouse
+{
+	fn default() -> Mouse
+	{
+		Mouse::new()
+	}
 }
 
 //------------------------------------------------------------------------------------------------------------------------
@@ -110,11 +302,29 @@ impl Mouse
 
 const NUM_KEYS: usize = glfw::ffi::KEY_LAST as usize + 1;
 
+// Every key except Unknown
+pub(crate) const KEYS: [Key; 120] = [
+		Key::Space, Key::Apostrophe, Key::Comma, Key::Minus, Key::Period, Key::Slash,
+		Key::Num0, Key::Num1, Key::Num2, Key::Num3, Key::Num4, Key::Num5, Key::Num6, Key::Num7, Key::Num8, Key::Num9,
+		Key::Semicolon, Key::Equal,
+		Key::A, Key::B, Key::C, Key::D, Key::E, Key::F, Key::G, Key::H, Key::I, Key::J, Key::K, Key::L, Key::M,
+		Key::N, Key::O, Key::P, Key::Q, Key::R, Key::S, Key::T, Key::U, Key::V, Key::W, Key::X, Key::Y, Key::Z,
+		Key::LeftBracket, Key::Backslash, Key::RightBracket, Key::GraveAccent, Key::World1, Key::World2,
+		Key::Escape, Key::Enter, Key::Tab, Key::Backspace, Key::Insert, Key::Delete, Key::Right, Key::Left, Key::Down, Key::Up,
+		Key::PageUp, Key::PageDown, Key::Home, Key::End, Key::CapsLock, Key::ScrollLock, Key::NumLock, Key::PrintScreen, Key::Pause,
+		Key::F1, Key::F2, Key::F3, Key::F4, Key::F5, Key::F6, Key::F7, Key::F8, Key::F9, Key::F10, Key::F11, Key::F12, Key::F13,
+		Key::F14, Key::F15, Key::F16, Key::F17, Key::F18, Key::F19, Key::F20, Key::F21, Key::F22, Key::F23, Key::F24, Key::F25,
+		Key::Kp0, Key::Kp1, Key::Kp2, Key::Kp3, Key::Kp4, Key::Kp5, Key::Kp6, Key::Kp7, Key::Kp8, Key::Kp9,
+		Key::KpDecimal, Key::KpDivide, Key::KpMultiply, Key::KpSubtract, Key::KpAdd, Key::KpEnter, Key::KpEqual,
+		Key::LeftShift, Key::LeftControl, Key::LeftAlt, Key::LeftSuper, Key::RightShift, Key::RightControl, Key::RightAlt, Key::RightSuper, Key::Menu,
+	];
+
 
 pub struct Keyboard
 {
 	keys: Vec<u32>,
 	okeys: Vec<u32>,
+	mods: Modifiers,
 }
 
 impl Keyboard
@@ -126,6 +336,7 @@ impl Keyboard
 		{
 			keys: vec![0; NUM_KEYS],
 			okeys: vec![0; NUM_KEYS],
+			mods: Modifiers::default(),
 		}
 	}
 
@@ -153,6 +364,17 @@ impl Keyboard
 		self.keys[key as usize] = match down { true => 1, false => 0};
 	}
 
+	pub fn set_modifiers(&mut self, mods: Modifiers)
+	{
+		self.mods = mods;
+	}
+
+	pub fn modifiers(&self) -> Modifiers
+	{
+		// Modifier keys held during the last key event
+		self.mods
+	}
+
 	pub fn key_down(&self, key: Key) -> bool
 	{
 		// Check wether a key is currently marked as down
@@ -172,6 +394,455 @@ impl Keyboard
 	}
 }
 
+impl Default for Keyboard
+{
+	fn default() -> Keyboard
+	{
+		Keyboard::new()
+	}
+}
+
+
+//------------------------------------------------------------------------------------------------------------------------
+//------------------------------------------------------------------------------------------------------------------------
+//------------------------------------------------------------------------------------------------------------------------
+
+pub(crate) const MAX_GAMEPADS: usize = 16;
+const NUM_AXES: usize = 6;
+
+const JOYSTICKS: [glfw::JoystickId; MAX_GAMEPADS] = [
+		glfw::JoystickId::Joystick1, glfw::JoystickId::Joystick2, glfw::JoystickId::Joystick3, glfw::JoystickId::Joystick4,
+		glfw::JoystickId::Joystick5, glfw::JoystickId::Joystick6, glfw::JoystickId::Joystick7, glfw::JoystickId::Joystick8,
+		glfw::JoystickId::Joystick9, glfw::JoystickId::Joystick10, glfw::JoystickId::Joystick11, glfw::JoystickId::Joystick12,
+		glfw::JoystickId::Joystick13, glfw::JoystickId::Joystick14, glfw::JoystickId::Joystick15, glfw::JoystickId::Joystick16,
+	];
+
+pub(crate) const BUTTONS: [GamepadButton; 15] = [
+		GamepadButton::ButtonA, GamepadButton::ButtonB, GamepadButton::ButtonX, GamepadButton::ButtonY,
+		GamepadButton::ButtonLeftBumper, GamepadButton::ButtonRightBumper,
+		GamepadButton::ButtonBack, GamepadButton::ButtonStart, GamepadButton::ButtonGuide,
+		GamepadButton::ButtonLeftThumb, GamepadButton::ButtonRightThumb,
+		GamepadButton::ButtonDpadUp, GamepadButton::ButtonDpadRight, GamepadButton::ButtonDpadDown, GamepadButton::ButtonDpadLeft,
+	];
+
+pub(crate) const AXES: [GamepadAxis; NUM_AXES] = [
+		GamepadAxis::AxisLeftX, GamepadAxis::AxisLeftY, GamepadAxis::AxisRightX, GamepadAxis::AxisRightY,
+		GamepadAxis::AxisLeftTrigger, GamepadAxis::AxisRightTrigger,
+	];
+
+
+fn apply_deadzone(v: f32, deadzone: f32) -> f32
+{
+	// Zero inside the deadzone, rescaled so the output still covers the whole range
+	let a = v.abs();
+
+	if a<=deadzone
+		{ return 0.0; }
+
+	v.signum() * ((a-deadzone) / (1.0-deadzone)).min(1.0)
+}
+
+
+pub struct Gamepad
+{
+	connected: bool,
+	mapped: bool,
+	name: String,
+
+	buts: u32,
+	obuts: u32,
+	axes: [f32; NUM_AXES],
+	deadzone: f32,
+
+	// Unmapped state, for joysticks without a gamepad mapping
+	raw_axes: Vec<f32>,
+	raw_buts: Vec<bool>,
+}
+
+impl Gamepad
+{
+	fn new() -> Gamepad
+	{
+		Gamepad
+		{
+			connected: false,
+			mapped: false,
+			name: String::new(),
+			buts: 0,
+			obuts: 0,
+			axes: [0.0; NUM_AXES],
+			deadzone: 0.2,
+			raw_axes: Vec::new(),
+			raw_buts: Vec::new(),
+		}
+	}
+
+	fn update(&mut self, joy: &glfw::Joystick)
+	{
+		// Read the current state, through the gamepad mapping if there's one
+		self.raw_axes = joy.get_axes();
+		self.raw_buts = joy.get_buttons().iter().map(|b| *b!=0).collect();
+
+		match joy.get_gamepad_state()
+		{
+			Some (state) =>
+				{
+					self.mapped = true;
+					self.buts = 0;
+
+					for (i, but) in BUTTONS.iter().enumerate()
+					{
+						if state.get_button_state(*but)==glfw::Action::Press
+							{ self.buts |= 1<<i; }
+					}
+
+					for (i, axis) in AXES.iter().enumerate()
+						{ self.axes[i] = state.get_axis(*axis); }
+				},
+
+			None =>
+				{
+					self.mapped = false;
+					self.buts = 0;
+					self.axes = [0.0; NUM_AXES];
+				},
+		}
+	}
+
+	fn button_bit(but: GamepadButton) -> u32
+	{
+		1 << (but as u32)
+	}
+
+	pub fn is_connected(&self) -> bool					{ self.connected }
+	pub fn is_mapped(&self) -> bool						{ self.mapped }
+	pub fn name(&self) -> &str							{ &self.name }
+
+	pub fn raw_axes(&self) -> &[f32]					{ &self.raw_axes }
+	pub fn raw_buttons(&self) -> &[bool]				{ &self.raw_buts }
+
+	pub fn but_down(&self, but: GamepadButton) -> bool
+	{
+		(self.buts & Gamepad::button_bit(but)) > 0
+	}
+
+	pub fn but_pressed(&self, but: GamepadButton) -> bool
+	{
+		// Check if a button was pressed on this frame
+		let bit = Gamepad::button_bit(but);
+		((self.buts & bit) > 0) && ((self.obuts & bit) == 0)
+	}
+
+	pub fn but_released(&self, but: GamepadButton) -> bool
+	{
+		// Check if a button was released on this frame
+		let bit = Gamepad::button_bit(but);
+		((self.buts & bit) == 0) && ((self.obuts & bit) > 0)
+	}
+
+	pub fn axis(&self, axis: GamepadAxis) -> f32
+	{
+		// Sticks go from -1 to 1, triggers from 0 to 1, with the deadzone applied
+		let v = self.axes[axis as usize];
+
+		match axis
+		{
+			GamepadAxis::AxisLeftTrigger | GamepadAxis::AxisRightTrigger =>
+				{
+					// Triggers rest at -1
+					apply_deadzone((v+1.0)*0.5, self.deadzone)
+				},
+			_ => apply_deadzone(v, self.deadzone),
+		}
+	}
+
+	pub fn left_stick(&self) -> (f32, f32)
+	{
+		self.stick(self.axes[0], self.axes[1])
+	}
+
+	pub fn right_stick(&self) -> (f32, f32)
+	{
+		self.stick(self.axes[2], self.axes[3])
+	}
+
+	fn stick(&self, x: f32, y: f32) -> (f32, f32)
+	{
+		// Radial deadzone, so diagonals aren't snapped to the axes
+		let len = (x*x + y*y).sqrt();
+
+		if len<=self.deadzone
+			{ return (0.0, 0.0); }
+
+		let scaled = apply_deadzone(len, self.deadzone);
+		(x/len*scaled, y/len*scaled)
+	}
+}
+
+
+pub struct Gamepads
+{
+	pads: Vec<Gamepad>,
+}
+
+impl Gamepads
+{
+	pub fn new() -> Gamepads
+	{
+		Gamepads
+		{
+			pads: (0..MAX_GAMEPADS).map(|_| Gamepad::new()).collect(),
+		}
+	}
+
+	pub fn reset(&mut self)
+	{
+		// Reset the state
+		for pad in self.pads.iter_mut()
+			{ pad.obuts = pad.buts; }
+	}
+
+	pub fn update(&mut self, glfw: &mut glfw::Glfw) -> Vec<(usize, bool)>
+	{
+		// Poll all the joysticks, returns the ones that were connected (true) or disconnected (false)
+		let mut changes = Vec::new();
+
+		for (i, id) in JOYSTICKS.iter().enumerate()
+		{
+			let joy = glfw.get_joystick(*id);
+			let pad = &mut self.pads[i];
+			let present = joy.is_present();
+
+			if present!=pad.connected
+			{
+				changes.push((i, present));
+
+				let deadzone = pad.deadzone;
+				*pad = Gamepad::new();
+				pad.deadzone = deadzone;
+				pad.connected = present;
+
+				if present
+					{ pad.name = joy.get_gamepad_name().or_else(|| joy.get_name()).unwrap_or_default(); }
+			}
+
+			if present
+				{ pad.update(&joy); }
+		}
+
+		changes
+	}
+
+	pub fn get(&self, index: usize) -> Option<&Gamepad>
+	{
+		// Index of the joystick, None past the 16 joysticks GLFW knows
+		self.pads.get(index)
+	}
+
+	pub fn first(&self) -> Option<&Gamepad>
+	{
+		// The first connected gamepad with a mapping, for single player games
+		self.pads.iter().find(|p| p.connected && p.mapped)
+	}
+
+	pub fn connected(&self) -> Vec<usize>
+	{
+		(0..MAX_GAMEPADS).filter(|i| self.pads[*i].connected).collect()
+	}
+
+	pub fn set_deadzone(&mut self, deadzone: f32)
+	{
+		// Fraction of the axis range that reads as zero
+		let deadzone = deadzone.clamp(0.0, 0.99);
+
+		for pad in self.pads.iter_mut()
+			{ pad.deadzone = deadzone; }
+	}
+}
+
+impl Default for Gamepads
+{
+	fn default() -> Gamepads
+	{
+		Gamepads::new()
+	}
+}
+
+//------------------------------------------------------------------------------------------------------------------------
+//------------------------------------------------------------------------------------------------------------------------
+//------------------------------------------------------------------------------------------------------------------------
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum TextEdit
+{
+	Backspace,
+	Delete,
+	Enter,
+	Tab,
+	Escape,
+	Left,
+	Right,
+	Up,
+	Down,
+	Home,
+	End,
+}
+
+impl TextEdit
+{
+	pub fn from_key(key: Key) -> Option<TextEdit>
+	{
+		// Keys that edit text rather than type it
+		match key
+		{
+			Key::Backspace => Some(TextEdit::Backspace),
+			Key::Delete => Some(TextEdit::Delete),
+			Key::Enter | Key::KpEnter => Some(TextEdit::Enter),
+			Key::Tab => Some(TextEdit::Tab),
+			Key::Escape => Some(TextEdit::Escape),
+			Key::Left => Some(TextEdit::Left),
+			Key::Right => Some(TextEdit::Right),
+			Key::Up => Some(TextEdit::Up),
+			Key::Down => Some(TextEdit::Down),
+			Key::Home => Some(TextEdit::Home),
+			Key::End => Some(TextEdit::End),
+			_ => None,
+		}
+	}
+}
+
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum TextEvent
+{
+	// Typed character, after keyboard layout and dead key handling
+	Char (char),
+
+	// Editing key, repeated while held
+	Edit (TextEdit, Modifiers),
+}
+
+
+pub struct TextInput
+{
+	active: bool,
+	events: Vec<TextEvent>,
+}
+
+impl TextInput
+{
+	pub fn new() -> TextInput
+	{
+		TextInput
+		{
+			active: false,
+			events: Vec::new(),
+		}
+	}
+
+	pub fn reset(&mut self)
+	{
+		// Only the current frame's input is kept
+		self.events.clear();
+	}
+
+	pub fn start(&mut self)
+	{
+		// Start collecting text, e.g. when a text field gets focus
+		self.active = true;
+	}
+
+	pub fn stop(&mut self)
+	{
+		self.active = false;
+		self.events.clear();
+	}
+
+	pub fn is_active(&self) -> bool
+	{
+		self.active
+	}
+
+	pub fn push(&mut self, ev: TextEvent)
+	{
+		// Ignored while text input is stopped
+		if self.active
+			{ self.events.push(ev); }
+	}
+
+	pub fn events(&self) -> &[TextEvent]
+	{
+		// Characters and editing keys of this frame, in order
+		&self.events
+	}
+
+	pub fn chars(&self) -> String
+	{
+		// Just the characters typed on this frame
+		self.events.iter().filter_map(|ev| match ev { TextEvent::Char (c) => Some(*c), _ => None }).collect()
+	}
+
+	pub fn apply(&self, text: &mut String) -> bool
+	{
+		// Simple line editing at the end of a string, returns true when enter was pressed
+		let mut cursor = text.len();
+		self.edit(text, &mut cursor)
+	}
+
+	pub fn edit(&self, text: &mut String, cursor: &mut usize) -> bool
+	{
+		// Line editing around a cursor, a byte offset kept on a character boundary
+		// Returns true when enter was pressed
+		let mut enter = false;
+		*cursor = (*cursor).min(text.len());
+
+		while !text.is_char_boundary(*cursor)
+			{ *cursor -= 1; }
+
+		// Offsets of the characters before and after the cursor
+		let prev = |text: &String, cursor: usize| text[..cursor].chars().next_back().map_or(cursor, |c| cursor-c.len_utf8());
+		let next = |text: &String, cursor: usize| text[cursor..].chars().next().map_or(cursor, |c| cursor+c.len_utf8());
+
+		for ev in self.events.iter()
+		{
+			match ev
+			{
+				TextEvent::Char (c) =>
+					{
+						text.insert(*cursor, *c);
+						*cursor += c.len_utf8();
+					},
+				TextEvent::Edit (TextEdit::Backspace, _) =>
+					{
+						let start = prev(text, *cursor);
+						text.replace_range(start..*cursor, "");
+						*cursor = start;
+					},
+				TextEvent::Edit (TextEdit::Delete, _) =>
+					{
+						let end = next(text, *cursor);
+						text.replace_range(*cursor..end, "");
+					},
+				TextEvent::Edit (TextEdit::Left, _) => *cursor = prev(text, *cursor),
+				TextEvent::Edit (TextEdit::Right, _) => *cursor = next(text, *cursor),
+				TextEvent::Edit (TextEdit::Home, _) => *cursor = 0,
+				TextEvent::Edit (TextEdit::End, _) => *cursor = text.len(),
+				TextEvent::Edit (TextEdit::Enter, _) => enter = true,
+				_ => (),
+			}
+		}
+
+		enter
+	}
+}
+
+impl Default for TextInput
+{
+	fn default() -> TextInput
+	{
+		TextInput::new()
+	}
+}
 
 //------------------------------------------------------------------------------------------------------------------------
 //------------------------------------------------------------------------------------------------------------------------
@@ -182,6 +853,8 @@ pub struct Input
 {
 	mouse: Mouse,
 	keyboard: Keyboard,
+	text: TextInput,
+	gamepads: Gamepads,
 }
 
 impl Input
@@ -193,6 +866,8 @@ impl Input
 		{
 			mouse: Mouse::new(),
 			keyboard: Keyboard::new(),
+			text: TextInput::new(),
+			gamepads: Gamepads::new(),
 		}
 	}
 
@@ -201,6 +876,8 @@ impl Input
 		// Reset the input state for a new frame
 		self.mouse.reset();
 		self.keyboard.reset();
+		self.text.reset();
+		self.gamepads.reset();
 	}
 
 	pub fn mouse(&self) -> &Mouse							{ &self.mouse }
@@ -209,5 +886,174 @@ impl Input
 	pub fn keyboard(&self) -> &Keyboard						{ &self.keyboard }
 	pub fn keyboard_mut(&mut self) -> &mut Keyboard			{ &mut self.keyboard }
 
+	pub fn text(&self) -> &TextInput						{ &self.text }
+	pub fn text_mut(&mut self) -> &mut TextInput			{ &mut self.text }
+
+	pub fn gamepads(&self) -> &Gamepads						{ &self.gamepads }
+	pub fn gamepads_mut(&mut self) -> &mut Gamepads			{ &mut self.gamepads }
+
+}
+
+impl Default for Input
+{
+	fn default() -> Input
+	{
+		Input::new()
+	}
 }
 
+//------------------------------------------------------------------------------------------------------------------------
+//------------------------------------------------------------------------------------------------------------------------
+//------------------------------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+
+	fn typed(events: &[TextEvent]) -> TextInput
+	{
+		let mut input = TextInput::new();
+		input.start();
+
+		for ev in events.iter()
+			{ input.push(*ev); }
+
+		input
+	}
+
+	fn key(edit: TextEdit) -> TextEvent
+	{
+		TextEvent::Edit(edit, Modifiers::default())
+	}
+
+	#[test]
+	fn backspace_at_start()
+	{
+		let mut text = String::from("abc");
+		let mut cursor = 0;
+		typed(&[key(TextEdit::Backspace)]).edit(&mut text, &mut cursor);
+		assert_eq!((text.as_str(), cursor), ("abc", 0));
+
+		// At the end, through apply()
+		typed(&[key(TextEdit::Backspace), TextEvent::Char('d')]).apply(&mut text);
+		assert_eq!(text, "abd");
+	}
+
+	#[test]
+	fn delete_at_end()
+	{
+		let mut text = String::from("abc");
+		let mut cursor = 3;
+		typed(&[key(TextEdit::Delete)]).edit(&mut text, &mut cursor);
+		assert_eq!((text.as_str(), cursor), ("abc", 3));
+
+		// Before the last character
+		typed(&[key(TextEdit::Left), key(TextEdit::Delete)]).edit(&mut text, &mut cursor);
+		assert_eq!((text.as_str(), cursor), ("ab", 2));
+	}
+
+	#[test]
+	fn multibyte()
+	{
+		// Two, three and four byte characters
+		let mut text = String::from("é€😀");
+		let mut cursor = text.len();
+
+		typed(&[key(TextEdit::Left)]).edit(&mut text, &mut cursor);
+		assert_eq!(cursor, 5);
+
+		typed(&[key(TextEdit::Left), key(TextEdit::Left), key(TextEdit::Left)]).edit(&mut text, &mut cursor);
+		assert_eq!(cursor, 0);
+
+		typed(&[key(TextEdit::Right), TextEvent::Char('ß'), key(TextEdit::Right), key(TextEdit::Backspace)]).edit(&mut text, &mut cursor);
+		assert_eq!((text.as_str(), cursor), ("éß😀", 4));
+
+		typed(&[key(TextEdit::Delete), key(TextEdit::End), key(TextEdit::Right)]).edit(&mut text, &mut cursor);
+		assert_eq!((text.as_str(), cursor), ("éß", 4));
+
+		// A cursor inside a character moves back to its start
+		let mut cursor = 1;
+		typed(&[TextEvent::Char('a')]).edit(&mut text, &mut cursor);
+		assert_eq!((text.as_str(), cursor), ("aéß", 1));
+
+		typed(&[key(TextEdit::Home), key(TextEdit::Enter)]).edit(&mut text, &mut cursor);
+		assert_eq!(cursor, 0);
+	}
+
+	fn click(mouse: &mut Mouse, but: u8, at: Instant)
+	{
+		mouse.reset();
+		mouse.press_at(but, at);
+	}
+
+	#[test]
+	fn double_click()
+	{
+		let t = Instant::now();
+		let ms = std::time::Duration::from_millis;
+
+		// Inside the 0.4s threshold
+		let mut mouse = Mouse::new();
+		click(&mut mouse, 0, t);
+		assert!(!mouse.double_clicked(0));
+		click(&mut mouse, 0, t+ms(300));
+		assert!(mouse.double_clicked(0));
+
+		// A third press starts over, and the state only lasts a frame
+		click(&mut mouse, 0, t+ms(400));
+		assert!(!mouse.double_clicked(0));
+		mouse.reset();
+		assert!(!mouse.double_clicked(0));
+
+		// Outside the threshold, the late press can start a new double-click
+		let mut mouse = Mouse::new();
+		click(&mut mouse, 0, t);
+		click(&mut mouse, 0, t+ms(500));
+		assert!(!mouse.double_clicked(0));
+		click(&mut mouse, 0, t+ms(700));
+		assert!(mouse.double_clicked(0));
+
+		// Another button, or too far from the first press
+		let mut mouse = Mouse::new();
+		click(&mut mouse, 0, t);
+		click(&mut mouse, 1, t+ms(100));
+		assert!(!mouse.double_clicked(0) && !mouse.double_clicked(1));
+
+		let mut mouse = Mouse::new();
+		click(&mut mouse, 0, t);
+		mouse.set_pos(10, 0);
+		click(&mut mouse, 0, t+ms(100));
+		assert!(!mouse.double_clicked(0));
+	}
+
+	#[test]
+	fn deadzone()
+	{
+		// Without a deadzone the axis is unchanged
+		assert_eq!(apply_deadzone(0.0, 0.0), 0.0);
+		assert_eq!(apply_deadzone(0.5, 0.0), 0.5);
+		assert_eq!(apply_deadzone(-0.5, 0.0), -0.5);
+
+		// Zero up to the threshold, then rising from zero
+		assert_eq!(apply_deadzone(0.0, 0.2), 0.0);
+		assert_eq!(apply_deadzone(0.2, 0.2), 0.0);
+		assert_eq!(apply_deadzone(-0.2, 0.2), 0.0);
+		assert!((apply_deadzone(0.6, 0.2) - 0.5).abs() < 1e-6);
+		assert!((apply_deadzone(-0.6, 0.2) + 0.5).abs() < 1e-6);
+
+		// The full range is still reached
+		assert_eq!(apply_deadzone(1.0, 0.2), 1.0);
+		assert_eq!(apply_deadzone(-1.0, 0.2), -1.0);
+		assert_eq!(apply_deadzone(1.0, 0.0), 1.0);
+	}
+
+	#[test]
+	fn gamepad_index()
+	{
+		let pads = Gamepads::new();
+		assert!(!pads.get(0).unwrap().is_connected());
+		assert!(pads.get(MAX_GAMEPADS-1).is_some());
+		assert!(pads.get(MAX_GAMEPADS).is_none());
+	}
+}
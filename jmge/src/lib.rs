@@ -3,13 +3,27 @@ mod color;
 pub use color::Color;
 
 mod window;
-pub use window::Window;
+pub use window::{Window, WindowBuilder, WindowMode, ScaleMode, CursorMode, CursorShape};
+
+mod headless;
+
+mod event;
+pub use event::Event;
 
 mod shader;
 pub use shader::{VertexShader, FragmentShader, ShaderProgram};
 
 mod vbo;
-pub use vbo::{VertexBuffer};
+pub use vbo::{VertexBuffer, VertexAttrib, AttribType};
+
+mod backend;
+pub use backend::{Backend, Primitive, Blend, Uniform, set_backend};
+
+mod glbackend;
+pub use glbackend::GlBackend;
+
+mod recbackend;
+pub use recbackend::{RecordingBackend, BackendCall};
 
 mod texture;
 pub use texture::{Texture, RawTexture, TextureAtlas};
@@ -17,14 +31,50 @@ pub use texture::{Texture, RawTexture, TextureAtlas};
 mod canvas;
 pub use canvas::Canvas;
 
+mod framebuffer;
+pub use framebuffer::Framebuffer;
+
+mod recorder;
+pub use recorder::{FrameRecorder, RecordFormat};
+
+mod postfx;
+pub use postfx::{PostProcess, Effect};
+
 mod input;
-pub use input::{Input, Mouse, Keyboard, Key};
+pub use input::{Input, Mouse, Keyboard, Key, Modifiers, TextInput, TextEvent, TextEdit, Gamepads, Gamepad, GamepadButton, GamepadAxis};
+
+mod action;
+pub use action::{ActionMap, Binding};
 
 mod font;
 pub use font::{Font, Glyph};
 
 mod renderer;
-pub use renderer::{Renderer, Quad, Renderable};
+pub use renderer::{Renderer, QuadRenderer, Quad, Renderable, PixelSnap};
+
+mod softrender;
+pub use softrender::SoftRenderer;
+
+mod stats;
+pub use stats::RenderStats;
+
+mod timer;
+pub use timer::FrameTimer;
+
+mod shape;
+pub use shape::Shape;
+
+mod text;
+pub use text::{Text, TextAlign};
+
+mod nineslice;
+pub use nineslice::{NineSlice, SliceMode};
+
+mod light;
+pub use light::{Light, LightKind, LightRenderer, LightingSystem};
+
+mod particle;
+pub use particle::{ParticleEmitter, ParticleSystem, EmitterDef, Burst, Curve, ColorCurve};
 
 mod ecs;
 pub use ecs::{World, Component, Entity, System};
@@ -39,6 +89,9 @@ pub use sprite::{SpriteSheet, Sprite, SpriteSystem};
 mod tilemap;
 pub use tilemap::{TileMap, TileMapRenderer};
 
+mod app;
+pub use app::{App, AppContext, run};
+
 
 
 #[derive(Debug)]
@@ -51,22 +104,40 @@ pub enum Error
 	NoAudioDevice,
 	LoadSound,
 	LoadSpriteSheet (String),
+	InitGlfw (String),
+	CreateWindow (String),
+	CreateFramebuffer,
+	LoadParticles (String),
+	LoadActions (String),
+	SaveActions (String),
+	SaveImage (String),
+	RecordFrames (String),
+	InvalidGamepad (usize),
 }
 
 
-impl ToString for Error
+impl std::fmt::Display for Error
 {
-	fn to_string(&self) -> String
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result
 	{
 		match self
 		{
-			Error::CompileShader (s)		=> format!("Error compiling a shader: {}", s),
-			Error::LoadImage (s)			=> format!("Error loading an image: {}", s),
-			Error::PackAtlas				=> format!("Could not fit a canvas into a texture atlas"),
-			Error::LoadFont (s)				=> format!("Error loading a font: {}", s),
-			Error::NoAudioDevice			=> format!("No audio device found"),
-			Error::LoadSound				=> format!("Error loading a sound file"),
-			Error::LoadSpriteSheet (s)		=> format!("Error loading a sprite sheet: {}", s),
+			Error::CompileShader (s)		=> write!(f, "Error compiling a shader: {}", s),
+			Error::LoadImage (s)			=> write!(f, "Error loading an image: {}", s),
+			Error::PackAtlas				=> write!(f, "Could not fit a canvas into a texture atlas"),
+			Error::LoadFont (s)				=> write!(f, "Error loading a font: {}", s),
+			Error::NoAudioDevice			=> write!(f, "No audio device found"),
+			Error::LoadSound				=> write!(f, "Error loading a sound file"),
+			Error::LoadSpriteSheet (s)		=> write!(f, "Error loading a sprite sheet: {}", s),
+			Error::InitGlfw (s)				=> write!(f, "Error initializing GLFW: {}", s),
+			Error::CreateWindow (s)			=> write!(f, "Error creating the window: {}", s),
+			Error::CreateFramebuffer		=> write!(f, "Could not create a complete framebuffer"),
+			Error::LoadParticles (s)		=> write!(f, "Error loading a particle emitter: {}", s),
+			Error::LoadActions (s)			=> write!(f, "Error loading an action map: {}", s),
+			Error::SaveActions (s)			=> write!(f, "Error saving an action map: {}", s),
+			Error::SaveImage (s)			=> write!(f, "Error saving an image: {}", s),
+			Error::RecordFrames (s)			=> write!(f, "Error recording frames: {}", s),
+			Error::InvalidGamepad (i)		=> write!(f, "No gamepad with index {}", i),
 		}
 	}
 }
@@ -0,0 +1,349 @@
+
+use super::{Error, Color, Framebuffer, ShaderProgram, Renderer, Component, World, System, VertexBuffer, Blend, backend::with_backend};
+use nalgebra::base::Matrix4;
+use std::rc::Rc;
+use std::cell::RefCell;
+
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum LightKind
+{
+	Point,
+
+	// Direction and half cone angle, in radians
+	Spot { direction: f32, angle: f32 },
+}
+
+
+#[derive(Component)]
+pub struct Light
+{
+	// Position
+	pub x: f32,
+	pub y: f32,
+
+	// Color and strength
+	pub color: Color,
+	pub intensity: f32,
+
+	// Reach, and how fast the light fades over it
+	pub radius: f32,
+	pub falloff: f32,
+
+	// Distance above the sprites, for normal mapping
+	pub height: f32,
+
+	// Point or spot light
+	pub kind: LightKind,
+
+	// Enabled flag
+	pub enabled: bool,
+}
+
+impl Light
+{
+	pub fn point(x: f32, y: f32, radius: f32, color: Color) -> Light
+	{
+		// Create a point light with mostly default values
+		Light
+		{
+			x,
+			y,
+			color,
+			intensity: 1.0,
+			radius,
+			falloff: 1.0,
+			height: radius / 4.0,
+			kind: LightKind::Point,
+			enabled: true,
+		}
+	}
+
+	pub fn spot(x: f32, y: f32, radius: f32, color: Color, direction: f32, angle: f32) -> Light
+	{
+		// Create a spot light with mostly default values
+		let mut light = Light::point(x, y, radius, color);
+		light.kind = LightKind::Spot { direction, angle };
+
+		light
+	}
+}
+
+//------------------------------------------------------------------------------------------------------------------------
+//------------------------------------------------------------------------------------------------------------------------
+//------------------------------------------------------------------------------------------------------------------------
+
+struct LightData
+{
+	x: f32,
+	y: f32,
+	radius: f32,
+	falloff: f32,
+	height: f32,
+	color: (f32, f32, f32),
+	spot_dir: (f32, f32),
+	spot_cos: f32,
+}
+
+impl LightData
+{
+	fn new(light: &Light) -> LightData
+	{
+		let (spot_dir, spot_cos) = match light.kind
+			{
+				LightKind::Point => ((0.0, 0.0), -1.0),
+				LightKind::Spot { direction, angle } => ((direction.cos(), direction.sin()), angle.cos()),
+			};
+
+		let i = light.intensity;
+
+		LightData
+		{
+			x: light.x,
+			y: light.y,
+			radius: light.radius,
+			falloff: light.falloff,
+			height: light.height,
+			color: (light.color.r()*i, light.color.g()*i, light.color.b()*i),
+			spot_dir,
+			spot_cos,
+		}
+	}
+}
+
+
+pub struct LightingSystem
+{
+	lights: Rc<RefCell<Vec<LightData>>>,
+}
+
+impl System for LightingSystem
+{
+	fn run(&mut self, world: &World)
+	{
+		// Collect the enabled lights of the world, replacing the ones of the previous run
+		let mut lights = self.lights.borrow_mut();
+		lights.clear();
+
+		if !world.is_registered::<Light>()
+			{ return; }
+
+		for (_, light) in world.iter::<Light>()
+		{
+			if light.enabled
+				{ lights.push(LightData::new(&light)); }
+		}
+	}
+}
+
+//------------------------------------------------------------------------------------------------------------------------
+//------------------------------------------------------------------------------------------------------------------------
+//------------------------------------------------------------------------------------------------------------------------
+
+
+pub struct LightRenderer
+{
+	normals: Framebuffer,
+	light_map: Framebuffer,
+	shader: ShaderProgram,
+	combine: ShaderProgram,
+	verts: VertexBuffer<()>,
+	lights: Vec<LightData>,
+	world_lights: Rc<RefCell<Vec<LightData>>>,
+	ambient: Color,
+}
+
+impl LightRenderer
+{
+	pub fn new(w: u32, h: u32) -> Result<LightRenderer, Error>
+	{
+		// Create the shaders
+		let shader = ShaderProgram::from_str(
+			include_str!("../shaders/light.vert"),
+			include_str!("../shaders/light.frag")
+		)?;
+
+		let combine = ShaderProgram::from_str(
+			include_str!("../shaders/post.vert"),
+			include_str!("../shaders/post_copy.frag")
+		)?;
+
+		// The lights and the full screen pass are generated from the vertex index
+		let verts = VertexBuffer::new(&[]);
+
+		Ok(LightRenderer
+		{
+			normals: Framebuffer::new(w, h, false)?,
+			light_map: Framebuffer::new(w, h, false)?,
+			shader,
+			combine,
+			verts,
+			lights: Vec::new(),
+			world_lights: Rc::new(RefCell::new(Vec::new())),
+			ambient: Color::rgb(0.1, 0.1, 0.15),
+		})
+	}
+
+	pub fn resize(&mut self, w: u32, h: u32) -> Result<(), Error>
+	{
+		// Re-create the buffers with the new size
+		self.normals = Framebuffer::new(w, h, false)?;
+		self.light_map = Framebuffer::new(w, h, false)?;
+
+		Ok(())
+	}
+
+	pub fn set_ambient(&mut self, col: Color)
+	{
+		// Light level where no light reaches
+		self.ambient = col;
+	}
+
+	pub fn ambient(&self) -> Color
+	{
+		self.ambient
+	}
+
+	pub fn system(&self) -> LightingSystem
+	{
+		// System collecting the world's lights for this renderer, add it to the world
+		LightingSystem
+		{
+			lights: Rc::clone(&self.world_lights),
+		}
+	}
+
+	pub fn add_light(&mut self, light: &Light)
+	{
+		// Queue a light for the next light map only, on top of the world's
+		if light.enabled
+			{ self.lights.push(LightData::new(light)); }
+	}
+
+	pub fn prepare(&mut self, rend: &mut Renderer, proj_mat: &Matrix4<f32>)
+	{
+		// Build the light map from the world's and the queued lights, the renderer keeps its queue
+		// This leaves the light map bound, rebind the scene target before rendering it
+		let (w, h) = self.light_map.size();
+
+		// Draw the normals of the queued sprites
+		self.normals.bind();
+
+		with_backend(|b| b.clear([0.0, 0.0, 0.0, 0.0], None));
+
+		rend.render_normals(proj_mat);
+
+		// Accumulate the lights over the ambient level
+		self.light_map.bind();
+
+		let ambient = self.ambient;
+
+		with_backend(|b|
+			{
+				b.clear([ambient.r(), ambient.g(), ambient.b(), 1.0], None);
+				b.set_blend(Blend::Add);
+			});
+
+		self.shader.enable();
+		self.shader.set_uniform_matrix("Projection", proj_mat);
+		self.shader.set_uniform_vec2("ScreenSize", w as f32, h as f32);
+		self.normals.texture().enable();
+
+		let world_lights = self.world_lights.borrow();
+
+		for l in world_lights.iter().chain(self.lights.iter())
+		{
+			self.shader.set_uniform_vec2("LightPos", l.x, l.y);
+			self.shader.set_uniform_float("Radius", l.radius);
+			self.shader.set_uniform_float("Falloff", l.falloff);
+			self.shader.set_uniform_float("Height", l.height);
+			self.shader.set_uniform_vec3("LightColor", l.color.0, l.color.1, l.color.2);
+			self.shader.set_uniform_vec2("SpotDir", l.spot_dir.0, l.spot_dir.1);
+			self.shader.set_uniform_float("SpotCos", l.spot_cos);
+
+			self.verts.draw_strip(0, 4);
+		}
+
+		with_backend(|b| b.set_blend(Blend::Alpha));
+
+		self.lights.clear();
+	}
+
+	pub fn apply(&self)
+	{
+		// Multiply the light map over the bound target
+		self.combine.enable();
+		self.light_map.texture().enable();
+
+		with_backend(|b| b.set_blend(Blend::Multiply));
+		self.verts.draw_triangles(0, 3);
+		with_backend(|b| b.set_blend(Blend::Alpha));
+	}
+}
+
+//------------------------------------------------------------------------------------------------------------------------
+//------------------------------------------------------------------------------------------------------------------------
+//------------------------------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+
+	fn system() -> (LightingSystem, Rc<RefCell<Vec<LightData>>>)
+	{
+		let lights = Rc::new(RefCell::new(Vec::new()));
+		(LightingSystem { lights: Rc::clone(&lights) }, lights)
+	}
+
+	#[test]
+	fn collect()
+	{
+		let mut world = World::new();
+		world.register::<Light>();
+
+		let white = Color::rgb(1.0, 1.0, 1.0);
+
+		let lamp = world.new_entity();
+		world.set(&lamp, Light::point(10.5, 20.25, 100.0, white));
+
+		let torch = world.new_entity();
+		world.set(&torch, Light::spot(30.0, 40.0, 50.0, white, 0.0, std::f32::consts::FRAC_PI_3));
+
+		let off = world.new_entity();
+		let mut light = Light::point(0.0, 0.0, 10.0, white);
+		light.enabled = false;
+		world.set(&off, light);
+
+		let (mut sys, lights) = system();
+		sys.run(&world);
+
+		// Only the enabled ones, with their positions kept as is
+		{
+			let lights = lights.borrow();
+			assert_eq!(lights.len(), 2);
+
+			let lamp = lights.iter().find(|l| l.radius==100.0).unwrap();
+			assert_eq!((lamp.x, lamp.y), (10.5, 20.25));
+			assert_eq!(lamp.spot_cos, -1.0);
+
+			let torch = lights.iter().find(|l| l.radius==50.0).unwrap();
+			assert_eq!(torch.spot_dir, (1.0, 0.0));
+			assert!((torch.spot_cos-0.5).abs()<1e-6);
+		}
+
+		// Each run replaces the previous lights
+		world.get_mut::<Light>(&torch).enabled = false;
+		sys.run(&world);
+		assert_eq!(lights.borrow().len(), 1);
+	}
+
+	#[test]
+	fn unregistered()
+	{
+		// A world without lights gives an empty list
+		let (mut sys, lights) = system();
+		sys.run(&World::new());
+		assert!(lights.borrow().is_empty());
+	}
+}
@@ -0,0 +1,166 @@
+
+use super::{Color, Texture, Quad, Component};
+use std::rc::Rc;
+
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum SliceMode
+{
+	Stretch,
+	Tile,
+}
+
+
+fn split(total: f32, a: f32, b: f32) -> (f32, f32, f32)
+{
+	// Split a length into two borders and a middle part
+	// Borders shrink proportionally when they don't fit
+	if a+b > total
+	{
+		let k = total / (a+b);
+		(a*k, 0.0, b*k)
+	}
+	else
+	{
+		(a, total-a-b, b)
+	}
+}
+
+
+#[derive(Component)]
+pub struct NineSlice
+{
+	// Texture to slice
+	pub texture: Rc<Texture>,
+
+	// Position
+	pub x: f32,
+	pub y: f32,
+
+	// Size of the whole panel
+	pub w: u32,
+	pub h: u32,
+
+	// Border insets in texels: left, top, right, bottom
+	pub insets: (u32, u32, u32, u32),
+
+	// How the edges and center fill their area
+	pub mode: SliceMode,
+
+	// Color tint
+	pub color: Color,
+
+	// Visibility flag
+	pub visible: bool,
+}
+
+impl NineSlice
+{
+	pub fn new(tex: &Rc<Texture>, x: f32, y: f32, w: u32, h: u32, insets: (u32, u32, u32, u32)) -> NineSlice
+	{
+		// Create a new nine-slice with mostly default values
+		let (tw, th) = tex.size();
+		if insets.0+insets.2 > tw || insets.1+insets.3 > th
+			{ panic!("NineSlice::new(): insets larger than the texture"); }
+
+		NineSlice
+		{
+			texture: Rc::clone(tex),
+			x,
+			y,
+			w,
+			h,
+			insets,
+			mode: SliceMode::Stretch,
+			color: Color::rgb(1.0, 1.0, 1.0),
+			visible: true,
+		}
+	}
+
+	fn add_cell(&self, quads: &mut Vec<Quad>, src: (f32, f32, f32, f32), dst: (f32, f32, f32, f32), tile: bool)
+	{
+		let (sx, sy, sw, sh) = src;
+		let (dx, dy, dw, dh) = dst;
+
+		// Skip empty cells
+		if sw<=0.0 || sh<=0.0 || dw<=0.0 || dh<=0.0
+			{ return; }
+
+		if !tile
+		{
+			// Stretch the source over the whole cell
+			let quad = Quad::new(&self.texture)
+				.with_src(sx, sy, sw, sh)
+				.with_size(dw, dh)
+				.with_pos(dx, dy)
+				.with_color(self.color);
+
+			quads.push(quad);
+			return;
+		}
+
+		// Repeat the source, cropping the last row and column
+		let mut ty = 0.0;
+
+		while ty<dh
+		{
+			let ch = sh.min(dh-ty);
+			let mut tx = 0.0;
+
+			while tx<dw
+			{
+				let cw = sw.min(dw-tx);
+
+				let quad = Quad::new(&self.texture)
+					.with_src(sx, sy, cw, ch)
+					.with_pos(dx+tx, dy+ty)
+					.with_color(self.color);
+
+				quads.push(quad);
+				tx += sw;
+			}
+
+			ty += sh;
+		}
+	}
+
+	pub fn build_quads(&self) -> Vec<Quad>
+	{
+		// Build the quads for all nine cells
+		let mut quads = Vec::new();
+
+		let (tw, th) = self.texture.size();
+		let (l, t, r, b) = self.insets;
+		let (l, t, r, b) = (l as f32, t as f32, r as f32, b as f32);
+
+		// Source columns and rows
+		let scols = [(0.0, l), (l, tw as f32-l-r), (tw as f32-r, r)];
+		let srows = [(0.0, t), (t, th as f32-t-b), (th as f32-b, b)];
+
+		// Destination columns and rows
+		let (dl, dc, dr) = split(self.w as f32, l, r);
+		let (dt, dm, db) = split(self.h as f32, t, b);
+		let (x, y) = (self.x, self.y);
+
+		let dcols = [(x, dl), (x+dl, dc), (x+dl+dc, dr)];
+		let drows = [(y, dt), (y+dt, dm), (y+dt+dm, db)];
+
+		for row in 0..3
+		{
+			for col in 0..3
+			{
+				// Corners are always stretched, they only get resized when the borders don't fit
+				let corner = row!=1 && col!=1;
+				let tile = !corner && self.mode==SliceMode::Tile;
+
+				let src = (scols[col].0, srows[row].0, scols[col].1, srows[row].1);
+				let dst = (dcols[col].0, drows[row].0, dcols[col].1, drows[row].1);
+
+				self.add_cell(&mut quads, src, dst, tile);
+			}
+		}
+
+		quads
+	}
+}
+
@@ -0,0 +1,780 @@
+
+use super::{Error, Color, Texture, Quad, Component, System, World};
+use json::JsonValue;
+use std::rc::Rc;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use std::sync::atomic::{AtomicU32, Ordering};
+
+
+fn lerp(a: f32, b: f32, t: f32) -> f32
+{
+	a + (b-a)*t
+}
+
+fn find_keys<T: Copy>(keys: &[(f32, T)], t: f32) -> Option<(T, T, f32)>
+{
+	// Find the keys around t, and the position between them
+	if keys.is_empty()
+		{ return None; }
+
+	if t<=keys[0].0
+		{ return Some((keys[0].1, keys[0].1, 0.0)); }
+
+	for i in 1..keys.len()
+	{
+		let (t1, v1) = keys[i];
+
+		if t<=t1
+		{
+			let (t0, v0) = keys[i-1];
+			let f = if t1>t0 { (t-t0) / (t1-t0) } else { 1.0 };
+
+			return Some((v0, v1, f));
+		}
+	}
+
+	let last = keys[keys.len()-1].1;
+	Some((last, last, 0.0))
+}
+
+
+#[derive(Clone)]
+pub struct Curve
+{
+	// Keys as (time, value), time going from 0 to 1 over the particle life
+	keys: Vec<(f32, f32)>,
+}
+
+impl Curve
+{
+	pub fn new(keys: &[(f32, f32)]) -> Curve
+	{
+		let mut keys = keys.to_vec();
+		keys.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+
+		Curve
+		{
+			keys,
+		}
+	}
+
+	pub fn constant(v: f32) -> Curve
+	{
+		Curve::new(&[(0.0, v)])
+	}
+
+	pub fn eval(&self, t: f32) -> f32
+	{
+		// Linear interpolation between the keys
+		match find_keys(&self.keys, t)
+		{
+			Some ((a, b, f)) => lerp(a, b, f),
+			None => 1.0,
+		}
+	}
+}
+
+
+#[derive(Clone)]
+pub struct ColorCurve
+{
+	keys: Vec<(f32, Color)>,
+}
+
+impl ColorCurve
+{
+	pub fn new(keys: &[(f32, Color)]) -> ColorCurve
+	{
+		let mut keys = keys.to_vec();
+		keys.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+
+		ColorCurve
+		{
+			keys,
+		}
+	}
+
+	pub fn constant(col: Color) -> ColorCurve
+	{
+		ColorCurve::new(&[(0.0, col)])
+	}
+
+	pub fn eval(&self, t: f32) -> Color
+	{
+		// Linear interpolation between the keys, per channel
+		match find_keys(&self.keys, t)
+		{
+			Some ((a, b, f)) => Color::rgba(lerp(a.r(), b.r(), f), lerp(a.g(), b.g(), f), lerp(a.b(), b.b(), f), lerp(a.a(), b.a(), f)),
+			None => Color::rgb(1.0, 1.0, 1.0),
+		}
+	}
+}
+
+//------------------------------------------------------------------------------------------------------------------------
+//------------------------------------------------------------------------------------------------------------------------
+//------------------------------------------------------------------------------------------------------------------------
+
+#[derive(Clone, Copy)]
+pub struct Burst
+{
+	// Time since the start of the emitter cycle, in seconds
+	pub time: f32,
+	pub count: u32,
+}
+
+
+#[derive(Clone)]
+pub struct EmitterDef
+{
+	// Particles alive at once
+	pub max_particles: usize,
+
+	// Continuous spawn rate, in particles per second
+	pub rate: f32,
+
+	// Bursts within a cycle
+	pub bursts: Vec<Burst>,
+
+	// Cycle length in seconds, 0 for an endless emitter
+	pub duration: f32,
+	pub looping: bool,
+
+	// Half size of the spawn area around the emitter
+	pub area: (f32, f32),
+
+	// Ranges, picked at random for every particle
+	pub lifetime: (f32, f32),
+	pub speed: (f32, f32),
+	pub direction: (f32, f32),
+	pub accel_x: (f32, f32),
+	pub accel_y: (f32, f32),
+	pub angle: (f32, f32),
+	pub spin: (f32, f32),
+
+	// Evolution over the particle life
+	pub scale: Curve,
+	pub color: ColorCurve,
+}
+
+impl Default for EmitterDef
+{
+	fn default() -> EmitterDef
+	{
+		EmitterDef
+		{
+			max_particles: 256,
+			rate: 10.0,
+			bursts: Vec::new(),
+			duration: 0.0,
+			looping: true,
+			area: (0.0, 0.0),
+			lifetime: (1.0, 1.0),
+			speed: (0.0, 0.0),
+			direction: (0.0, 2.0*std::f32::consts::PI),
+			accel_x: (0.0, 0.0),
+			accel_y: (0.0, 0.0),
+			angle: (0.0, 0.0),
+			spin: (0.0, 0.0),
+			scale: Curve::constant(1.0),
+			color: ColorCurve::constant(Color::rgb(1.0, 1.0, 1.0)),
+		}
+	}
+}
+
+
+fn load_error(s: &str) -> Error
+{
+	Error::LoadParticles(s.to_string())
+}
+
+fn parse_f32(j: &JsonValue, name: &str, default: f32) -> Result<f32, Error>
+{
+	// Optional number
+	if j[name].is_null()
+		{ return Ok(default); }
+
+	j[name].as_f32().ok_or_else(|| load_error(&format!("'{}' must be a number", name)))
+}
+
+fn parse_range(j: &JsonValue, name: &str, default: (f32, f32)) -> Result<(f32, f32), Error>
+{
+	// Optional range, either a single number or [min, max]
+	let v = &j[name];
+
+	if v.is_null()
+		{ return Ok(default); }
+
+	if let Some(n) = v.as_f32()
+		{ return Ok((n, n)); }
+
+	match (v[0].as_f32(), v[1].as_f32())
+	{
+		(Some (a), Some (b)) if v.len()==2 => Ok((a, b)),
+		_ => Err(load_error(&format!("'{}' must be a number or a [min, max] pair", name))),
+	}
+}
+
+fn parse_curve(j: &JsonValue, name: &str) -> Result<Option<Curve>, Error>
+{
+	// Optional curve, either a single number or a list of [time, value] keys
+	let v = &j[name];
+
+	if v.is_null()
+		{ return Ok(None); }
+
+	if let Some(n) = v.as_f32()
+		{ return Ok(Some(Curve::constant(n))); }
+
+	let mut keys = Vec::new();
+
+	for k in v.members()
+	{
+		match (k[0].as_f32(), k[1].as_f32())
+		{
+			(Some (t), Some (val)) => keys.push((t, val)),
+			_ => return Err(load_error(&format!("'{}' keys must be [time, value]", name))),
+		}
+	}
+
+	if keys.is_empty()
+		{ return Err(load_error(&format!("'{}' has no keys", name))); }
+
+	Ok(Some(Curve::new(&keys)))
+}
+
+fn parse_color_curve(j: &JsonValue, name: &str) -> Result<Option<ColorCurve>, Error>
+{
+	// Optional color curve, a list of [time, r, g, b, a] keys
+	let v = &j[name];
+
+	if v.is_null()
+		{ return Ok(None); }
+
+	let mut keys = Vec::new();
+
+	for k in v.members()
+	{
+		let vals: Vec<f32> = k.members().filter_map(|n| n.as_f32()).collect();
+
+		if vals.len()!=5 || k.len()!=5
+			{ return Err(load_error(&format!("'{}' keys must be [time, r, g, b, a]", name))); }
+
+		keys.push((vals[0], Color::rgba(vals[1], vals[2], vals[3], vals[4])));
+	}
+
+	if keys.is_empty()
+		{ return Err(load_error(&format!("'{}' has no keys", name))); }
+
+	Ok(Some(ColorCurve::new(&keys)))
+}
+
+
+impl EmitterDef
+{
+	pub fn parse(s: &str) -> Result<EmitterDef, Error>
+	{
+		// Parse the JSON data
+		let json = match json::parse(s)
+			{
+				Ok (json) => json,
+				Err (_) => return Err(load_error("Error parsing JSON data")),
+			};
+
+		if !json.is_object()
+			{ return Err(load_error("Expected an object")); }
+
+		// Missing values keep their defaults
+		let def = EmitterDef::default();
+
+		let max_particles = match json["max_particles"].is_null()
+			{
+				true => def.max_particles,
+				false => json["max_particles"].as_usize().ok_or_else(|| load_error("'max_particles' must be a positive integer"))?,
+			};
+
+		let looping = match json["looping"].is_null()
+			{
+				true => def.looping,
+				false => json["looping"].as_bool().ok_or_else(|| load_error("'looping' must be a boolean"))?,
+			};
+
+		let mut bursts = Vec::new();
+
+		for b in json["bursts"].members()
+		{
+			let time = b["time"].as_f32().ok_or_else(|| load_error("Burst 'time' must be a number"))?;
+			let count = b["count"].as_u32().ok_or_else(|| load_error("Burst 'count' must be a positive integer"))?;
+
+			bursts.push(Burst { time, count });
+		}
+
+		Ok(EmitterDef
+		{
+			max_particles,
+			rate: parse_f32(&json, "rate", def.rate)?,
+			bursts,
+			duration: parse_f32(&json, "duration", def.duration)?,
+			looping,
+			area: parse_range(&json, "area", def.area)?,
+			lifetime: parse_range(&json, "lifetime", def.lifetime)?,
+			speed: parse_range(&json, "speed", def.speed)?,
+			direction: parse_range(&json, "direction", def.direction)?,
+			accel_x: parse_range(&json, "accel_x", def.accel_x)?,
+			accel_y: parse_range(&json, "accel_y", def.accel_y)?,
+			angle: parse_range(&json, "angle", def.angle)?,
+			spin: parse_range(&json, "spin", def.spin)?,
+			scale: parse_curve(&json, "scale")?.unwrap_or(def.scale),
+			color: parse_color_curve(&json, "color")?.unwrap_or(def.color),
+		})
+	}
+
+	pub fn from_file(fname: &str) -> Result<EmitterDef, Error>
+	{
+		// Load the file
+		let s = match std::fs::read_to_string(fname)
+			{
+				Ok (s) => s,
+				Err (_) => return Err(load_error("Could not read the file")),
+			};
+
+		EmitterDef::parse(&s)
+	}
+}
+
+//------------------------------------------------------------------------------------------------------------------------
+//------------------------------------------------------------------------------------------------------------------------
+//------------------------------------------------------------------------------------------------------------------------
+
+static SEED: AtomicU32 = AtomicU32::new(0x9E3779B9);
+
+
+struct Rng (u32);
+
+impl Rng
+{
+	fn new() -> Rng
+	{
+		// Different seed for every emitter
+		let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.subsec_nanos()).unwrap_or(0);
+		let seed = SEED.fetch_add(0x9E3779B9, Ordering::Relaxed) ^ nanos;
+
+		Rng(seed | 1)
+	}
+
+	fn next(&mut self) -> u32
+	{
+		// Xorshift
+		self.0 ^= self.0 << 13;
+		self.0 ^= self.0 >> 17;
+		self.0 ^= self.0 << 5;
+		self.0
+	}
+
+	fn range(&mut self, r: (f32, f32)) -> f32
+	{
+		// Random value between min and max
+		let f = (self.next() >> 8) as f32 / 16777216.0;
+		lerp(r.0, r.1, f)
+	}
+}
+
+
+struct Particle
+{
+	x: f32,
+	y: f32,
+	vx: f32,
+	vy: f32,
+	ax: f32,
+	ay: f32,
+	angle: f32,
+	spin: f32,
+	age: f32,
+	life: f32,
+}
+
+
+#[derive(Component)]
+pub struct ParticleEmitter
+{
+	def: Rc<EmitterDef>,
+	texture: Rc<Texture>,
+
+	// Position, new particles spawn around it and don't follow it afterwards
+	pub x: f32,
+	pub y: f32,
+
+	// Spawning flag, live particles keep going when it's off
+	pub active: bool,
+
+	// Visibility flag
+	pub visible: bool,
+
+	particles: Vec<Particle>,
+	time: f32,
+	spawn_acc: f32,
+	rng: Rng,
+}
+
+impl ParticleEmitter
+{
+	pub fn new(def: &Rc<EmitterDef>, tex: &Rc<Texture>, x: f32, y: f32) -> ParticleEmitter
+	{
+		ParticleEmitter
+		{
+			def: Rc::clone(def),
+			texture: Rc::clone(tex),
+			x,
+			y,
+			active: true,
+			visible: true,
+			particles: Vec::with_capacity(def.max_particles),
+			time: 0.0,
+			spawn_acc: 0.0,
+			rng: Rng::new(),
+		}
+	}
+
+	pub fn def(&self) -> &Rc<EmitterDef>
+	{
+		&self.def
+	}
+
+	pub fn count(&self) -> usize
+	{
+		// Live particles
+		self.particles.len()
+	}
+
+	pub fn is_finished(&self) -> bool
+	{
+		// Done spawning and all particles are dead
+		!self.active && self.particles.is_empty()
+	}
+
+	pub fn restart(&mut self)
+	{
+		// Start a new cycle, live particles are kept
+		self.active = true;
+		self.time = 0.0;
+		self.spawn_acc = 0.0;
+	}
+
+	pub fn clear(&mut self)
+	{
+		// Kill all the particles
+		self.particles.clear();
+	}
+
+	pub fn burst(&mut self, count: u32)
+	{
+		// Spawn particles right away
+		for _ in 0..count
+		{
+			if self.particles.len()>=self.def.max_particles
+				{ break; }
+
+			self.spawn();
+		}
+	}
+
+	fn spawn(&mut self)
+	{
+		// Pick the particle properties
+		let def = &self.def;
+		let rng = &mut self.rng;
+
+		let dir = rng.range(def.direction);
+		let speed = rng.range(def.speed);
+
+		let p = Particle
+			{
+				x: self.x + rng.range((-def.area.0, def.area.0)),
+				y: self.y + rng.range((-def.area.1, def.area.1)),
+				vx: dir.cos()*speed,
+				vy: dir.sin()*speed,
+				ax: rng.range(def.accel_x),
+				ay: rng.range(def.accel_y),
+				angle: rng.range(def.angle),
+				spin: rng.range(def.spin),
+				age: 0.0,
+				life: rng.range(def.lifetime).max(0.001),
+			};
+
+		self.particles.push(p);
+	}
+
+	fn fire_bursts(&mut self, from: f32, to: f32)
+	{
+		// Fire the bursts within [from, to)
+		for i in 0..self.def.bursts.len()
+		{
+			let b = self.def.bursts[i];
+
+			if b.time>=from && b.time<to
+				{ self.burst(b.count); }
+		}
+	}
+
+	pub fn update(&mut self, dt: f32)
+	{
+		// Move the live particles, removing the dead ones
+		let mut i = 0;
+
+		while i<self.particles.len()
+		{
+			let p = &mut self.particles[i];
+			p.age += dt;
+
+			if p.age>=p.life
+			{
+				self.particles.swap_remove(i);
+				continue;
+			}
+
+			p.vx += p.ax*dt;
+			p.vy += p.ay*dt;
+			p.x += p.vx*dt;
+			p.y += p.vy*dt;
+			p.angle += p.spin*dt;
+
+			i += 1;
+		}
+
+		if !self.active
+			{ return; }
+
+		// Advance the cycle
+		let from = self.time;
+		self.time += dt;
+
+		let duration = self.def.duration;
+		let ended = duration>0.0 && self.time>=duration;
+
+		self.fire_bursts(from, if ended { duration } else { self.time });
+
+		// Continuous spawning
+		self.spawn_acc += self.def.rate*dt;
+		let n = self.spawn_acc as u32;
+		self.spawn_acc -= n as f32;
+		self.burst(n);
+
+		// End of the cycle
+		if ended
+		{
+			if self.def.looping
+			{
+				self.time = (self.time-duration) % duration;
+				self.fire_bursts(0.0, self.time);
+			}
+			else
+			{
+				self.active = false;
+			}
+		}
+	}
+
+	pub fn build_quads(&self) -> Vec<Quad>
+	{
+		// Create a quad for every live particle, centered on it
+		let (w, h) = self.texture.size();
+		let (w, h) = (w as f32, h as f32);
+
+		self.particles.iter().map(|p|
+			{
+				let t = p.age / p.life;
+				let s = self.def.scale.eval(t);
+
+				Quad::new(&self.texture)
+					.with_pos(p.x, p.y)
+					.with_size(w, h)
+					.with_origin(w/2.0, h/2.0)
+					.with_scale(s, s)
+					.with_angle(p.angle)
+					.with_color(self.def.color.eval(t))
+			}).collect()
+	}
+}
+
+
+pub struct ParticleSystem
+{
+	time: Instant,
+}
+
+impl ParticleSystem
+{
+	pub fn new() -> ParticleSystem
+	{
+		ParticleSystem
+		{
+			time: Instant::now(),
+		}
+	}
+}
+
+impl Default for ParticleSystem
+{
+	fn default() -> ParticleSystem
+	{
+		ParticleSystem::new()
+	}
+}
+
+impl System for ParticleSystem
+{
+	fn run(&mut self, world: &World)
+	{
+		// Get the time since the last run, capped to avoid a spawn storm after a stall
+		let dt = self.time.elapsed().as_secs_f32().min(0.1);
+		self.time = Instant::now();
+
+		// Update all the emitters
+		for (_, mut em) in world.iter_mut::<ParticleEmitter>()
+		{
+			em.update(dt);
+		}
+	}
+}
+
+
+//------------------------------------------------------------------------------------------------------------------------
+//------------------------------------------------------------------------------------------------------------------------
+//------------------------------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+	use super::super::Canvas;
+
+	fn near(a: f32, b: f32) -> bool
+	{
+		(a-b).abs()<1e-5
+	}
+
+	fn emitter(def: EmitterDef) -> ParticleEmitter
+	{
+		let tex = Rc::new(Texture::soft(Canvas::new(2, 2, Color::rgb(1.0, 1.0, 1.0))));
+		ParticleEmitter::new(&Rc::new(def), &tex, 0.0, 0.0)
+	}
+
+	fn parse_error(s: &str) -> String
+	{
+		match EmitterDef::parse(s)
+		{
+			Ok (_) => panic!("{} should not parse", s),
+			Err (e) => e.to_string(),
+		}
+	}
+
+	#[test]
+	fn parse_sparks()
+	{
+		let def = EmitterDef::parse(include_str!("../assets/sparks.json")).unwrap();
+
+		assert_eq!(def.max_particles, 400);
+		assert_eq!(def.rate, 60.0);
+		assert_eq!(def.bursts.len(), 1);
+		assert_eq!((def.bursts[0].time, def.bursts[0].count), (0.0, 40));
+		assert_eq!(def.duration, 2.0);
+		assert!(def.looping);
+		assert_eq!(def.area, (4.0, 4.0));
+		assert_eq!(def.lifetime, (0.6, 1.4));
+		assert_eq!(def.accel_y, (250.0, 350.0));
+		assert!(near(def.scale.eval(0.0), 2.0));
+		assert!(near(def.color.eval(1.0).a(), 0.0));
+
+		// Missing values keep their defaults
+		assert_eq!(def.accel_x, (0.0, 0.0));
+		assert_eq!(EmitterDef::parse("{}").unwrap().max_particles, 256);
+	}
+
+	#[test]
+	fn parse_errors()
+	{
+		assert!(parse_error("[1, 2]").contains("Expected an object"));
+		assert!(parse_error("{ \"rate\": \"fast\" }").contains("'rate' must be a number"));
+		assert!(parse_error("{ \"looping\": 1 }").contains("'looping' must be a boolean"));
+		assert!(parse_error("{ \"speed\": [1, 2, 3] }").contains("'speed' must be a number or a [min, max] pair"));
+		assert!(parse_error("{ \"scale\": [ [0] ] }").contains("'scale' keys must be [time, value]"));
+		assert!(parse_error("{ \"color\": [ [0, 1, 1, 1] ] }").contains("'color' keys must be [time, r, g, b, a]"));
+
+		// A burst needs both of its fields
+		assert!(parse_error("{ \"bursts\": [ { \"time\": 0 } ] }").contains("Burst 'count'"));
+	}
+
+	#[test]
+	fn curves()
+	{
+		let c = Curve::new(&[(1.0, 0.0), (0.0, 2.0), (0.5, 1.0)]);
+
+		// At the keys, given out of order
+		assert!(near(c.eval(0.0), 2.0));
+		assert!(near(c.eval(0.5), 1.0));
+		assert!(near(c.eval(1.0), 0.0));
+
+		// Between them, and clamped outside
+		assert!(near(c.eval(0.25), 1.5));
+		assert!(near(c.eval(0.75), 0.5));
+		assert!(near(c.eval(-1.0), 2.0));
+		assert!(near(c.eval(2.0), 0.0));
+
+		let cc = ColorCurve::new(&[(0.0, Color::rgba(1.0, 0.0, 0.0, 1.0)), (1.0, Color::rgba(0.0, 0.0, 1.0, 0.0))]);
+		// Colors are stored with 8 bits per channel
+		let mid = cc.eval(0.5);
+		let near8 = |a: f32, b: f32| (a-b).abs()<=1.0/255.0;
+		assert!(near8(mid.r(), 0.5) && near8(mid.g(), 0.0) && near8(mid.b(), 0.5) && near8(mid.a(), 0.5));
+	}
+
+	#[test]
+	fn lifetime()
+	{
+		let mut em = emitter(EmitterDef { rate: 0.0, lifetime: (0.5, 0.5), ..EmitterDef::default() });
+
+		em.burst(10);
+		assert_eq!(em.count(), 10);
+
+		em.update(0.4);
+		assert_eq!(em.count(), 10);
+
+		em.update(0.2);
+		assert_eq!(em.count(), 0);
+	}
+
+	#[test]
+	fn spawn_rate()
+	{
+		// 2.5 particles per update, the fractions carry over
+		let mut em = emitter(EmitterDef { rate: 10.0, lifetime: (10.0, 10.0), ..EmitterDef::default() });
+
+		let counts: Vec<usize> = (0..4).map(|_| { em.update(0.25); em.count() }).collect();
+		assert_eq!(counts, vec![2, 5, 7, 10]);
+
+		// Never more than the maximum
+		let mut em = emitter(EmitterDef { rate: 1000.0, max_particles: 20, lifetime: (10.0, 10.0), ..EmitterDef::default() });
+		em.update(1.0);
+		assert_eq!(em.count(), 20);
+	}
+
+	#[test]
+	fn cycles()
+	{
+		let burst = Burst { time: 0.0, count: 5 };
+		let def = EmitterDef { rate: 0.0, bursts: vec![burst], duration: 1.0, lifetime: (10.0, 10.0), ..EmitterDef::default() };
+
+		// Looping emitters fire their bursts again every cycle
+		let mut em = emitter(def.clone());
+		em.update(0.5);
+		assert_eq!(em.count(), 5);
+		em.update(0.6);
+		assert_eq!(em.count(), 10);
+
+		// Others stop after the first one
+		let mut em = emitter(EmitterDef { looping: false, ..def });
+		em.update(1.1);
+		em.update(1.0);
+		assert_eq!(em.count(), 5);
+		assert!(!em.active);
+	}
+}
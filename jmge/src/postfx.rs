@@ -0,0 +1,231 @@
+
+use super::{Error, Texture, Framebuffer, ShaderProgram, Window, VertexBuffer, Blend, backend::with_backend};
+use std::rc::Rc;
+
+
+#[derive(Clone)]
+pub enum Effect
+{
+	// Glow around the parts brighter than the threshold
+	Bloom { threshold: f32, intensity: f32 },
+
+	// Darkened horizontal lines, count is the number of lines over the screen height
+	Scanlines { intensity: f32, count: f32 },
+
+	// Darkened corners, radius and softness are relative to the screen size
+	Vignette { radius: f32, softness: f32 },
+
+	// Color lookup through a 256x16 raw LUT texture
+	ColorGrade { lut: Rc<Texture> },
+
+	// Offset of the whole image, in pixels
+	Shake { x: f32, y: f32 },
+
+	// Gaussian blur, radius in pixels
+	Blur { radius: f32 },
+}
+
+
+fn load_shader(frag: &str) -> Result<ShaderProgram, Error>
+{
+	// All passes share the full screen vertex shader
+	ShaderProgram::from_str(include_str!("../shaders/post.vert"), frag)
+}
+
+
+pub struct PostProcess
+{
+	targets: [Framebuffer; 2],
+	cur: usize,
+	effects: Vec<Effect>,
+	verts: VertexBuffer<()>,
+
+	copy: ShaderProgram,
+	bloom: ShaderProgram,
+	scanlines: ShaderProgram,
+	vignette: ShaderProgram,
+	grade: ShaderProgram,
+	shake: ShaderProgram,
+	blur: ShaderProgram,
+}
+
+impl PostProcess
+{
+	pub fn new(w: u32, h: u32) -> Result<PostProcess, Error>
+	{
+		// Create the ping-pong targets
+		let targets = [Framebuffer::new(w, h, true)?, Framebuffer::new(w, h, true)?];
+
+		for fb in targets.iter()
+		{
+			// Offset samples must not wrap around
+			fb.texture().set_repeat(false);
+		}
+
+		// The full screen triangle is generated from the vertex index
+		let verts = VertexBuffer::new(&[]);
+
+		Ok(PostProcess
+		{
+			targets,
+			cur: 0,
+			effects: Vec::new(),
+			verts,
+			copy: load_shader(include_str!("../shaders/post_copy.frag"))?,
+			bloom: load_shader(include_str!("../shaders/post_bloom.frag"))?,
+			scanlines: load_shader(include_str!("../shaders/post_scanlines.frag"))?,
+			vignette: load_shader(include_str!("../shaders/post_vignette.frag"))?,
+			grade: load_shader(include_str!("../shaders/post_grade.frag"))?,
+			shake: load_shader(include_str!("../shaders/post_shake.frag"))?,
+			blur: load_shader(include_str!("../shaders/post_blur.frag"))?,
+		})
+	}
+
+	pub fn size(&self) -> (u32, u32)
+	{
+		self.targets[0].size()
+	}
+
+	pub fn resize(&mut self, w: u32, h: u32) -> Result<(), Error>
+	{
+		// Re-create the targets with the new size
+		let targets = [Framebuffer::new(w, h, true)?, Framebuffer::new(w, h, true)?];
+
+		for fb in targets.iter()
+			{ fb.texture().set_repeat(false); }
+
+		self.targets = targets;
+
+		Ok(())
+	}
+
+	pub fn add(&mut self, effect: Effect)
+	{
+		// Add an effect at the end of the chain
+		self.effects.push(effect);
+	}
+
+	pub fn effects(&self) -> &Vec<Effect>
+	{
+		&self.effects
+	}
+
+	pub fn effects_mut(&mut self) -> &mut Vec<Effect>
+	{
+		// Change, reorder or remove effects, e.g. to update the shake offset every frame
+		&mut self.effects
+	}
+
+	pub fn begin(&mut self)
+	{
+		// Render the scene into the first target
+		self.cur = 0;
+		self.targets[0].bind();
+	}
+
+	fn pass(&mut self)
+	{
+		// Draw the current target into the other one
+		// The shader must be enabled and its uniforms set
+		let src = self.cur;
+		let dst = 1-src;
+
+		self.targets[dst].bind();
+		self.targets[src].texture().enable();
+
+		self.verts.draw_triangles(0, 3);
+
+		self.cur = dst;
+	}
+
+	fn run_effect(&mut self, effect: &Effect, w: f32, h: f32)
+	{
+		// Run the passes for an effect
+		match *effect
+		{
+			Effect::Bloom { threshold, intensity } =>
+				{
+					self.bloom.enable();
+					self.bloom.set_uniform_vec2("TexelSize", 1.0/w, 1.0/h);
+					self.bloom.set_uniform_float("Threshold", threshold);
+					self.bloom.set_uniform_float("Intensity", intensity);
+					self.pass();
+				},
+
+			Effect::Scanlines { intensity, count } =>
+				{
+					self.scanlines.enable();
+					self.scanlines.set_uniform_float("Intensity", intensity);
+					self.scanlines.set_uniform_float("Count", count);
+					self.pass();
+				},
+
+			Effect::Vignette { radius, softness } =>
+				{
+					self.vignette.enable();
+					self.vignette.set_uniform_float("Radius", radius);
+					self.vignette.set_uniform_float("Softness", softness);
+					self.pass();
+				},
+
+			Effect::ColorGrade { ref lut } =>
+				{
+					self.grade.enable();
+					self.grade.set_uniform_int("tex", 0);
+					self.grade.set_uniform_int("Lut", 1);
+
+					// The LUT goes on the second texture unit
+					lut.enable_on(1);
+
+					self.pass();
+				},
+
+			Effect::Shake { x, y } =>
+				{
+					// Texture coordinates go up, screen coordinates go down
+					self.shake.enable();
+					self.shake.set_uniform_vec2("Offset", -x/w, y/h);
+					self.pass();
+				},
+
+			Effect::Blur { radius } =>
+				{
+					// Separable blur, horizontal then vertical
+					self.blur.enable();
+					self.blur.set_uniform_vec2("Direction", radius/w/3.0, 0.0);
+					self.pass();
+					self.blur.set_uniform_vec2("Direction", 0.0, radius/h/3.0);
+					self.pass();
+				},
+		}
+	}
+
+	pub fn present(&mut self, wnd: &Window)
+	{
+		// Run the scene through all the effects
+		let (w, h) = self.size();
+		let (w, h) = (w as f32, h as f32);
+
+		with_backend(|b| b.set_blend(Blend::Off));
+
+		let effects = std::mem::take(&mut self.effects);
+
+		for effect in effects.iter()
+		{
+			self.run_effect(effect, w, h);
+		}
+
+		self.effects = effects;
+
+		// Copy the result to the window
+		wnd.bind_default_framebuffer();
+
+		self.copy.enable();
+		self.targets[self.cur].texture().enable();
+
+		self.verts.draw_triangles(0, 3);
+
+		with_backend(|b| b.set_blend(Blend::Alpha));
+	}
+}
+
@@ -0,0 +1,456 @@
+
+use super::{Error, VertexAttrib, Backend, Primitive, Blend, Uniform};
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::cell::RefCell;
+use std::time::Duration;
+
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum BackendCall
+{
+	CreateTexture { tex: u32, w: u32, h: u32, smooth: bool },
+	ReplaceTexture { tex: u32, w: u32, h: u32 },
+	UpdateTexture { tex: u32, x: i32, y: i32, w: u32, h: u32 },
+	SetTextureRepeat { tex: u32, repeat: bool },
+	BindTexture { unit: u32, tex: u32 },
+	DeleteTexture (u32),
+
+	CreateBuffer { buf: u32, stride: usize, instanced: bool },
+	UploadBuffer { buf: u32, data: Vec<u8> },
+	DeleteBuffer (u32),
+
+	CreatePipeline (u32),
+	BindPipeline (u32),
+	SetUniform { pipeline: u32, name: String, value: Uniform },
+	DeletePipeline (u32),
+
+	Draw { buf: u32, prim: Primitive, first: u32, count: u32 },
+	DrawInstanced { buf: u32, prim: Primitive, first_instance: u32, instances: u32, vertices: u32 },
+
+	CreateTarget { target: u32, tex: u32 },
+	BindTarget (u32),
+	DeleteTarget (u32),
+	SetViewport { x: i32, y: i32, w: i32, h: i32 },
+	Clear { col: [f32; 4], rect: Option<(i32, i32, i32, i32)> },
+	SetBlend (Blend),
+
+	ReadPixels { target: u32, w: u32, h: u32 },
+	CreateReadback { readback: u32, w: u32, h: u32 },
+	StartReadback { readback: u32, target: u32 },
+	FinishReadback (u32),
+	DeleteReadback (u32),
+
+	CreateTimer (u32),
+	BeginTimer (u32),
+	EndTimer (u32),
+	DeleteTimer (u32),
+}
+
+
+pub struct RecordingBackend
+{
+	next_id: u32,
+	log: Rc<RefCell<Vec<BackendCall>>>,
+
+	// Readback sizes, they give back black pixels
+	readbacks: HashMap<u32, (u32, u32)>,
+}
+
+impl RecordingBackend
+{
+	pub fn new() -> RecordingBackend
+	{
+		// Backend that doesn't draw anything, it only logs the calls
+		RecordingBackend
+		{
+			next_id: 1,
+			log: Rc::new(RefCell::new(Vec::new())),
+			readbacks: HashMap::new(),
+		}
+	}
+
+	pub fn log(&self) -> Rc<RefCell<Vec<BackendCall>>>
+	{
+		// Shared log, keep a reference before handing the backend to set_backend()
+		Rc::clone(&self.log)
+	}
+
+	fn new_id(&mut self) -> u32
+	{
+		let id = self.next_id;
+		self.next_id += 1;
+		id
+	}
+
+	fn push(&self, call: BackendCall)
+	{
+		self.log.borrow_mut().push(call);
+	}
+}
+
+impl Default for RecordingBackend
+{
+	fn default() -> RecordingBackend
+	{
+		RecordingBackend::new()
+	}
+}
+
+impl Backend for RecordingBackend
+{
+	fn create_texture(&mut self, w: u32, h: u32, smooth: bool, _data: Option<&[u32]>) -> u32
+	{
+		let tex = self.new_id();
+		self.push(BackendCall::CreateTexture { tex, w, h, smooth });
+		tex
+	}
+
+	fn replace_texture(&mut self, tex: u32, w: u32, h: u32, _data: &[u32])
+	{
+		self.push(BackendCall::ReplaceTexture { tex, w, h });
+	}
+
+	fn update_texture(&mut self, tex: u32, x: i32, y: i32, w: u32, h: u32, _data: &[u32])
+	{
+		self.push(BackendCall::UpdateTexture { tex, x, y, w, h });
+	}
+
+	fn set_texture_repeat(&mut self, tex: u32, repeat: bool)
+	{
+		self.push(BackendCall::SetTextureRepeat { tex, repeat });
+	}
+
+	fn bind_texture(&mut self, unit: u32, tex: u32)
+	{
+		self.push(BackendCall::BindTexture { unit, tex });
+	}
+
+	fn delete_texture(&mut self, tex: u32)
+	{
+		self.push(BackendCall::DeleteTexture(tex));
+	}
+
+	fn create_buffer(&mut self, _attribs: &[VertexAttrib], stride: usize, instanced: bool) -> u32
+	{
+		let buf = self.new_id();
+		self.push(BackendCall::CreateBuffer { buf, stride, instanced });
+		buf
+	}
+
+	fn upload_buffer(&mut self, buf: u32, data: &[u8])
+	{
+		self.push(BackendCall::UploadBuffer { buf, data: data.to_vec() });
+	}
+
+	fn delete_buffer(&mut self, buf: u32)
+	{
+		self.push(BackendCall::DeleteBuffer(buf));
+	}
+
+	fn create_pipeline(&mut self, _vert: &str, _frag: &str) -> Result<u32, Error>
+	{
+		// Shaders aren't compiled, so this can't fail
+		let pipeline = self.new_id();
+		self.push(BackendCall::CreatePipeline(pipeline));
+		Ok(pipeline)
+	}
+
+	fn bind_pipeline(&mut self, pipeline: u32)
+	{
+		self.push(BackendCall::BindPipeline(pipeline));
+	}
+
+	fn set_uniform(&mut self, pipeline: u32, name: &str, value: Uniform)
+	{
+		self.push(BackendCall::SetUniform { pipeline, name: name.to_string(), value });
+	}
+
+	fn delete_pipeline(&mut self, pipeline: u32)
+	{
+		self.push(BackendCall::DeletePipeline(pipeline));
+	}
+
+	fn draw(&mut self, buf: u32, prim: Primitive, first: u32, count: u32)
+	{
+		self.push(BackendCall::Draw { buf, prim, first, count });
+	}
+
+	fn draw_instanced(&mut self, buf: u32, prim: Primitive, first_instance: u32, instances: u32, vertices: u32)
+	{
+		self.push(BackendCall::DrawInstanced { buf, prim, first_instance, instances, vertices });
+	}
+
+	fn create_target(&mut self, tex: u32) -> Result<u32, Error>
+	{
+		let target = self.new_id();
+		self.push(BackendCall::CreateTarget { target, tex });
+		Ok(target)
+	}
+
+	fn bind_target(&mut self, target: u32)
+	{
+		self.push(BackendCall::BindTarget(target));
+	}
+
+	fn delete_target(&mut self, target: u32)
+	{
+		self.push(BackendCall::DeleteTarget(target));
+	}
+
+	fn set_viewport(&mut self, x: i32, y: i32, w: i32, h: i32)
+	{
+		self.push(BackendCall::SetViewport { x, y, w, h });
+	}
+
+	fn clear(&mut self, col: [f32; 4], rect: Option<(i32, i32, i32, i32)>)
+	{
+		self.push(BackendCall::Clear { col, rect });
+	}
+
+	fn set_blend(&mut self, blend: Blend)
+	{
+		self.push(BackendCall::SetBlend(blend));
+	}
+
+	fn read_pixels(&mut self, target: u32, w: u32, h: u32) -> Vec<u32>
+	{
+		self.push(BackendCall::ReadPixels { target, w, h });
+		vec![0; (w*h) as usize]
+	}
+
+	fn create_readback(&mut self, w: u32, h: u32) -> u32
+	{
+		let readback = self.new_id();
+		self.readbacks.insert(readback, (w, h));
+		self.push(BackendCall::CreateReadback { readback, w, h });
+		readback
+	}
+
+	fn start_readback(&mut self, readback: u32, target: u32)
+	{
+		self.push(BackendCall::StartReadback { readback, target });
+	}
+
+	fn finish_readback(&mut self, readback: u32, _wait: bool) -> Option<Vec<u32>>
+	{
+		// Always ready, like the timers
+		let (w, h) = self.readbacks[&readback];
+		self.push(BackendCall::FinishReadback(readback));
+		Some(vec![0; (w*h) as usize])
+	}
+
+	fn delete_readback(&mut self, readback: u32)
+	{
+		self.readbacks.remove(&readback);
+		self.push(BackendCall::DeleteReadback(readback));
+	}
+
+	fn create_timer(&mut self) -> Option<u32>
+	{
+		let timer = self.new_id();
+		self.push(BackendCall::CreateTimer(timer));
+		Some(timer)
+	}
+
+	fn begin_timer(&mut self, timer: u32)
+	{
+		self.push(BackendCall::BeginTimer(timer));
+	}
+
+	fn end_timer(&mut self, timer: u32)
+	{
+		self.push(BackendCall::EndTimer(timer));
+	}
+
+	fn timer_result(&mut self, _timer: u32) -> Option<Duration>
+	{
+		// Nothing is drawn, so it takes no time, and the result is always ready
+		Some(Duration::from_secs(0))
+	}
+
+	fn delete_timer(&mut self, timer: u32)
+	{
+		self.push(BackendCall::DeleteTimer(timer));
+	}
+}
+
+
+//------------------------------------------------------------------------------------------------------------------------
+//------------------------------------------------------------------------------------------------------------------------
+//------------------------------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+	use super::super::{set_backend, Renderer, QuadRenderer, Quad, Texture, Canvas, Color, Framebuffer, LightRenderer, Light};
+	use nalgebra::base::Matrix4;
+
+	fn install() -> Rc<RefCell<Vec<BackendCall>>>
+	{
+		// Record the calls made on this thread from now on
+		let rec = RecordingBackend::new();
+		let log = rec.log();
+		set_backend(Box::new(rec));
+
+		log
+	}
+
+	fn texture() -> Rc<Texture>
+	{
+		Rc::new(Texture::from_canvas(&Canvas::new(10, 10, Color::rgb(1.0, 1.0, 1.0)), false))
+	}
+
+	fn proj() -> Matrix4<f32>
+	{
+		Matrix4::new_orthographic(0.0, 100.0, 100.0, 0.0, -1.0, 1.0)
+	}
+
+	fn is_draw(c: &BackendCall) -> bool
+	{
+		matches!(c, BackendCall::Draw { .. } | BackendCall::DrawInstanced { .. })
+	}
+
+	#[test]
+	fn textured_quad()
+	{
+		let log = install();
+
+		let tex = texture();
+		let mut rend = Renderer::new().unwrap();
+		rend.add_quad(Quad::new(&tex).with_pos(10.0, 10.0));
+		rend.render(&proj());
+
+		let log = log.borrow();
+		let pos = |f: &dyn Fn(&BackendCall) -> bool| log.iter().position(f).unwrap();
+
+		// The texture is created, the instance uploaded, then drawn with a pipeline bound
+		let create = pos(&|c| matches!(c, BackendCall::CreateTexture { w: 10, h: 10, .. }));
+		let upload = pos(&|c| matches!(c, BackendCall::UploadBuffer { .. }));
+		let bind = pos(&|c| matches!(c, BackendCall::BindPipeline(_)));
+		let draw = pos(&is_draw);
+
+		assert!(create<upload && upload<draw && bind<draw);
+		assert_eq!(log.iter().filter(|c| is_draw(c)).count(), 1);
+
+		match log[draw]
+		{
+			BackendCall::DrawInstanced { prim, instances, vertices, .. } =>
+				{
+					assert_eq!(prim, Primitive::TriangleStrip);
+					assert_eq!((instances, vertices), (1, 4));
+				},
+			_ => panic!("expected an instanced draw"),
+		}
+	}
+
+	#[test]
+	fn texture_change()
+	{
+		let log = install();
+
+		let (tex1, tex2) = (texture(), texture());
+		let mut rend = Renderer::new().unwrap();
+		rend.add_quad(Quad::new(&tex1).with_pos(10.0, 10.0));
+		rend.add_quad(Quad::new(&tex2).with_pos(20.0, 10.0));
+
+		let ids: Vec<u32> = log.borrow().iter()
+			.filter_map(|c| match *c { BackendCall::CreateTexture { tex, w: 10, .. } => Some(tex), _ => None })
+			.collect();
+
+		log.borrow_mut().clear();
+		rend.render(&proj());
+
+		// Each texture is bound before its own draw
+		let log = log.borrow();
+		let seq: Vec<&BackendCall> = log.iter()
+			.filter(|c| is_draw(c) || matches!(c, BackendCall::BindTexture { unit: 0, .. }))
+			.collect();
+
+		assert_eq!(seq.len(), 4);
+
+		for (i, id) in ids.iter().enumerate()
+		{
+			assert_eq!(*seq[i*2], BackendCall::BindTexture { unit: 0, tex: *id });
+			assert!(matches!(*seq[i*2+1], BackendCall::DrawInstanced { instances: 1, .. }));
+		}
+	}
+
+	#[test]
+	fn framebuffer()
+	{
+		let log = install();
+
+		let fb = Framebuffer::new(32, 16, false).unwrap();
+		fb.bind();
+		let cnv = fb.read_pixels();
+		drop(fb);
+
+		assert_eq!(cnv.size(), (32, 16));
+
+		let log = log.borrow();
+		let (tex, target) = match log[..]
+			{
+				[BackendCall::CreateTexture { tex, w: 32, h: 16, .. }, BackendCall::CreateTarget { target, tex: t }, ..] if t==tex => (tex, target),
+				_ => panic!("the target should be created on its texture"),
+			};
+
+		assert_eq!(log[2..], [
+				BackendCall::BindTarget(target),
+				BackendCall::SetViewport { x: 0, y: 0, w: 32, h: 16 },
+				BackendCall::ReadPixels { target, w: 32, h: 16 },
+				BackendCall::DeleteTarget(target),
+				BackendCall::DeleteTexture(tex),
+			]);
+	}
+
+	#[test]
+	fn lighting()
+	{
+		let log = install();
+
+		let mut rend = Renderer::new().unwrap();
+		let mut lights = LightRenderer::new(64, 64).unwrap();
+		lights.set_ambient(Color::rgb(0.0, 0.0, 0.0));
+		lights.add_light(&Light::point(10.0, 10.0, 20.0, Color::rgb(1.0, 1.0, 1.0)));
+		lights.add_light(&Light::point(40.0, 40.0, 20.0, Color::rgb(1.0, 1.0, 1.0)));
+
+		log.borrow_mut().clear();
+		lights.prepare(&mut rend, &proj());
+		lights.apply();
+
+		// Only the order of the target, clear, blend and draw calls matters here
+		let calls: Vec<BackendCall> = log.borrow().iter()
+			.filter(|c| !matches!(c, BackendCall::BindPipeline(_) | BackendCall::SetUniform { .. } | BackendCall::BindTexture { .. }
+				| BackendCall::UploadBuffer { .. } | BackendCall::SetViewport { .. } | BackendCall::BeginTimer(_) | BackendCall::EndTimer(_)))
+			.map(|c| match c
+				{
+					BackendCall::BindTarget(_) => BackendCall::BindTarget(0),
+					BackendCall::Draw { prim, first, count, .. } => BackendCall::Draw { buf: 0, prim: *prim, first: *first, count: *count },
+					c => c.clone(),
+				})
+			.collect();
+
+		let strip = BackendCall::Draw { buf: 0, prim: Primitive::TriangleStrip, first: 0, count: 4 };
+
+		assert_eq!(calls, [
+				// The normals
+				BackendCall::BindTarget(0),
+				BackendCall::Clear { col: [0.0, 0.0, 0.0, 0.0], rect: None },
+
+				// The lights, added over the ambient level
+				BackendCall::BindTarget(0),
+				BackendCall::Clear { col: [0.0, 0.0, 0.0, 1.0], rect: None },
+				BackendCall::SetBlend(Blend::Add),
+				strip.clone(),
+				strip,
+				BackendCall::SetBlend(Blend::Alpha),
+
+				// The light map, multiplied over the scene
+				BackendCall::SetBlend(Blend::Multiply),
+				BackendCall::Draw { buf: 0, prim: Primitive::Triangles, first: 0, count: 3 },
+				BackendCall::SetBlend(Blend::Alpha),
+			]);
+	}
+}
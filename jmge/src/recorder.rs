@@ -0,0 +1,301 @@
+
+use super::{Error, Canvas, backend::with_backend};
+use std::collections::VecDeque;
+use std::sync::mpsc::{channel, Sender};
+use std::thread::JoinHandle;
+
+
+// Readbacks in flight, the oldest one is normally done by the time the ring wraps
+const NUM_PBOS: usize = 3;
+
+
+pub enum RecordFormat
+{
+	// Numbered PNG files, named <prefix>00000.png, <prefix>00001.png, ...
+	Png { prefix: String },
+
+	// Single animated GIF, delay between frames in milliseconds
+	Gif { fname: String, delay: u32 },
+}
+
+
+fn encode_frames(format: RecordFormat, frames: std::sync::mpsc::Receiver<Canvas>) -> Result<(), Error>
+{
+	// Write the frames as they arrive, until the recorder is finished
+	let err = |e: &dyn ToString| Error::RecordFrames(e.to_string());
+
+	match format
+	{
+		RecordFormat::Png { prefix } =>
+			{
+				for (i, mut cnv) in frames.iter().enumerate()
+				{
+					cnv.flip_vertical();
+					cnv.save_png(&format!("{}{:05}.png", prefix, i))?;
+				}
+			},
+
+		RecordFormat::Gif { fname, delay } =>
+			{
+				let file = std::fs::File::create(&fname).map_err(|e| err(&e))?;
+				let mut encoder = image::gif::GifEncoder::new(file);
+
+				for mut cnv in frames.iter()
+				{
+					cnv.flip_vertical();
+
+					let (w, h) = cnv.size();
+					let img = image::RgbaImage::from_raw(w, h, cnv.to_bytes())
+						.ok_or_else(|| Error::RecordFrames(format!("bad {}x{} frame", w, h)))?;
+					let frame = image::Frame::from_parts(img, 0, 0, image::Delay::from_numer_denom_ms(delay, 1));
+
+					encoder.encode_frame(frame).map_err(|e| err(&e))?;
+				}
+			},
+	}
+
+	Ok(())
+}
+
+
+pub struct FrameRecorder
+{
+	w: u32,
+	h: u32,
+	every: u32,
+	frame: u64,
+
+	readbacks: [u32; NUM_PBOS],
+	pos: usize,
+	pending: VecDeque<u32>,
+
+	sender: Option<Sender<Canvas>>,
+	worker: Option<JoinHandle<Result<(), Error>>>,
+}
+
+impl FrameRecorder
+{
+	pub fn new(w: u32, h: u32, every: u32, format: RecordFormat) -> FrameRecorder
+	{
+		// Create the readbacks the frames are read into
+		let mut readbacks = [0; NUM_PBOS];
+		with_backend(|b| readbacks.iter_mut().for_each(|rb| *rb = b.create_readback(w, h)));
+
+		// Encoding and writing to disk happens on a worker thread
+		let (sender, receiver) = channel();
+		let worker = std::thread::spawn(move || encode_frames(format, receiver));
+
+		FrameRecorder
+		{
+			w,
+			h,
+			every: every.max(1),
+			frame: 0,
+			readbacks,
+			pos: 0,
+			pending: VecDeque::new(),
+			sender: Some(sender),
+			worker: Some(worker),
+		}
+	}
+
+	// Spelled out, div_ceil() and is_multiple_of() are too recent for older compilers
+	#[allow(clippy::manual_div_ceil)]
+	pub fn frames(&self) -> u64
+	{
+		// Number of frames captured so far
+		let every = self.every as u64;
+		(self.frame + every - 1) / every
+	}
+
+	#[allow(clippy::manual_is_multiple_of)]
+	pub fn capture(&mut self)
+	{
+		// Call once per frame, after rendering and before swapping
+		// Only every Nth frame is read back, starting with the first one
+		let grab = self.frame % self.every as u64 == 0;
+		self.frame += 1;
+
+		if grab
+		{
+			// Make room in the ring, waiting for the oldest readback if needed
+			if self.pending.len()==NUM_PBOS
+				{ self.collect(true); }
+
+			// Start an asynchronous readback of the window
+			let rb = self.readbacks[self.pos];
+			self.pos = (self.pos+1) % NUM_PBOS;
+
+			with_backend(|b| b.start_readback(rb, 0));
+			self.pending.push_back(rb);
+		}
+
+		// Hand over whatever is ready, without blocking
+		while !self.pending.is_empty() && self.collect(false) {}
+	}
+
+	fn collect(&mut self, wait: bool) -> bool
+	{
+		// Take the oldest readback once it's done, and send it to the worker
+		let rb = match self.pending.front()
+			{
+				Some (rb) => *rb,
+				None => return false,
+			};
+
+		let data = match with_backend(|b| b.finish_readback(rb, wait))
+			{
+				Some (data) => data,
+				None => return false,
+			};
+
+		self.pending.pop_front();
+
+		// The worker flips the rows, the frame is dropped if the pixels couldn't be read
+		if data.len()==(self.w*self.h) as usize
+		{
+			if let Some(ref sender) = self.sender
+				{ let _ = sender.send(Canvas::from_raw(self.w, self.h, data)); }
+		}
+
+		true
+	}
+
+	pub fn finish(mut self) -> Result<(), Error>
+	{
+		// Wait for all the frames to be written
+		self.stop()
+	}
+
+	fn stop(&mut self) -> Result<(), Error>
+	{
+		// Drain the readbacks in flight
+		while self.collect(true) {}
+
+		// Closing the channel ends the worker
+		self.sender = None;
+
+		match self.worker.take()
+		{
+			Some (worker) => match worker.join()
+				{
+					Ok (res) => res,
+					Err (_) => Err(Error::RecordFrames("the encoding thread panicked".to_string())),
+				},
+			None => Ok(()),
+		}
+	}
+}
+
+impl Drop for FrameRecorder
+{
+	fn drop(&mut self)
+	{
+		// Finish writing if finish() wasn't called, errors can't be reported here
+		let _ = self.stop();
+
+		let readbacks = self.readbacks;
+		with_backend(|b| readbacks.iter().for_each(|rb| b.delete_readback(*rb)));
+	}
+}
+
+//------------------------------------------------------------------------------------------------------------------------
+//------------------------------------------------------------------------------------------------------------------------
+//------------------------------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+	use super::super::{set_backend, RecordingBackend, BackendCall, Color};
+
+	fn temp_name(name: &str) -> String
+	{
+		let dir = std::env::temp_dir().join(format!("jmge-recorder-{}", std::process::id()));
+		std::fs::create_dir_all(&dir).unwrap();
+
+		dir.join(name).to_str().unwrap().to_string()
+	}
+
+	fn encode(format: RecordFormat, frames: Vec<Canvas>) -> Result<(), Error>
+	{
+		let (sender, receiver) = channel();
+
+		for cnv in frames
+			{ sender.send(cnv).unwrap(); }
+
+		drop(sender);
+		encode_frames(format, receiver)
+	}
+
+	#[test]
+	fn frame_counting()
+	{
+		let rec = RecordingBackend::new();
+		let log = rec.log();
+		set_backend(Box::new(rec));
+
+		let prefix = temp_name("count_");
+		let mut recorder = FrameRecorder::new(4, 2, 3, RecordFormat::Png { prefix: prefix.clone() });
+
+		// Frames 0, 3 and 6 are grabbed
+		for i in 0..7
+		{
+			recorder.capture();
+			assert_eq!(recorder.frames(), i/3+1);
+		}
+
+		recorder.finish().unwrap();
+
+		let starts = log.borrow().iter().filter(|c| matches!(c, BackendCall::StartReadback { target: 0, .. })).count();
+		let finishes = log.borrow().iter().filter(|c| matches!(c, BackendCall::FinishReadback(_))).count();
+		assert_eq!((starts, finishes), (3, 3));
+
+		// Every readback is deleted, and each grabbed frame written
+		let deletes = log.borrow().iter().filter(|c| matches!(c, BackendCall::DeleteReadback(_))).count();
+		assert_eq!(deletes, NUM_PBOS);
+
+		for i in 0..3
+			{ assert_eq!(Canvas::from_file(&format!("{}{:05}.png", prefix, i)).unwrap().size(), (4, 2)); }
+
+		assert!(Canvas::from_file(&format!("{}00003.png", prefix)).is_err());
+	}
+
+	#[test]
+	fn encode_png()
+	{
+		// The rows come bottom-up, the files are top-down
+		let frame = ||
+			{
+				let mut cnv = Canvas::new(3, 2, Color::rgb(0.0, 0.0, 0.0));
+				cnv.set_pixel(1, 0, Color::rgb(1.0, 0.0, 0.0));
+				cnv
+			};
+
+		let prefix = temp_name("png_");
+		encode(RecordFormat::Png { prefix: prefix.clone() }, vec![frame(), frame()]).unwrap();
+
+		for i in 0..2
+		{
+			let cnv = Canvas::from_file(&format!("{}{:05}.png", prefix, i)).unwrap();
+			assert_eq!(cnv.get_pixel(1, 1).0, Color::rgb(1.0, 0.0, 0.0).0);
+			assert_eq!(cnv.get_pixel(1, 0).0, Color::rgb(0.0, 0.0, 0.0).0);
+		}
+	}
+
+	#[test]
+	fn encode_gif()
+	{
+		let fname = temp_name("anim.gif");
+		let frames = (0..3).map(|_| Canvas::new(5, 4, Color::rgb(0.0, 1.0, 0.0))).collect();
+		encode(RecordFormat::Gif { fname: fname.clone(), delay: 40 }, frames).unwrap();
+
+		let data = std::fs::read(&fname).unwrap();
+		assert_eq!(&data[..6], b"GIF89a");
+
+		// Nowhere to write to
+		let fname = temp_name("missing/anim.gif");
+		let res = encode(RecordFormat::Gif { fname, delay: 40 }, Vec::new());
+		assert!(matches!(res, Err(Error::RecordFrames(_))));
+	}
+}
@@ -1,10 +1,13 @@
 
-use super::{ShaderProgram, VertexBuffer, Error, Color, Texture, Component, World};
+use super::{RenderStats, ShaderProgram, VertexBuffer, VertexAttrib, AttribType, Error, Color, Canvas, Texture, Component, World, Shape, Font, Text, TextAlign, NineSlice, ParticleEmitter};
+use super::stats::GpuTimer;
 use std::rc::Rc;
-use nalgebra::base::Matrix4;
+use std::time::Instant;
+use nalgebra::base::{Matrix4, Vector4};
 
 
 #[repr(C)]
+#[derive(Default)]
 struct Vertex
 {
 	pub x: f32,
@@ -21,6 +24,75 @@ struct Vertex
 	pub oy: f32,
 }
 
+const VERTEX_ATTRIBS: [VertexAttrib; 7] =
+	[
+		// Position
+		VertexAttrib { count: 2, kind: AttribType::Float, offset: 0 },
+
+		// Color
+		VertexAttrib { count: 4, kind: AttribType::UByteNorm, offset: 8 },
+
+		// TexCoord
+		VertexAttrib { count: 2, kind: AttribType::Float, offset: 12 },
+
+		// Translation
+		VertexAttrib { count: 2, kind: AttribType::Float, offset: 20 },
+
+		// Scale
+		VertexAttrib { count: 2, kind: AttribType::Float, offset: 28 },
+
+		// Angle
+		VertexAttrib { count: 1, kind: AttribType::Float, offset: 36 },
+
+		// Origin
+		VertexAttrib { count: 2, kind: AttribType::Float, offset: 40 },
+	];
+
+
+#[repr(C)]
+#[derive(Default)]
+struct Instance
+{
+	pub w: f32,
+	pub h: f32,
+	pub col: u32,
+	pub u1: f32,
+	pub v1: f32,
+	pub u2: f32,
+	pub v2: f32,
+	pub tx: f32,
+	pub ty: f32,
+	pub sx: f32,
+	pub sy: f32,
+	pub angle: f32,
+	pub ox: f32,
+	pub oy: f32,
+}
+
+const INSTANCE_ATTRIBS: [VertexAttrib; 7] =
+	[
+		// Size
+		VertexAttrib { count: 2, kind: AttribType::Float, offset: 0 },
+
+		// Color
+		VertexAttrib { count: 4, kind: AttribType::UByteNorm, offset: 8 },
+
+		// UV rectangle
+		VertexAttrib { count: 4, kind: AttribType::Float, offset: 12 },
+
+		// Translation
+		VertexAttrib { count: 2, kind: AttribType::Float, offset: 28 },
+
+		// Scale
+		VertexAttrib { count: 2, kind: AttribType::Float, offset: 36 },
+
+		// Angle
+		VertexAttrib { count: 1, kind: AttribType::Float, offset: 44 },
+
+		// Origin
+		VertexAttrib { count: 2, kind: AttribType::Float, offset: 48 },
+	];
+
 
 #[derive(Component)]
 pub struct Renderable
@@ -28,9 +100,9 @@ pub struct Renderable
 	// Texture to render
 	pub texture: Rc<Texture>,
 
-	// Position
-	pub x: i32,
-	pub y: i32,
+	// Position, snapped according to the renderer's PixelSnap policy
+	pub x: f32,
+	pub y: f32,
 
 	// Color tint
 	pub color: Color,
@@ -43,16 +115,27 @@ pub struct Renderable
 	pub y_scale: f32,
 
 	// Origin
-	pub x_origin: i32,
-	pub y_origin: i32,
+	pub x_origin: f32,
+	pub y_origin: f32,
 
 	// Rotation angle
 	pub angle: f32,
+
+	// Mirroring
+	pub flip_x: bool,
+	pub flip_y: bool,
+
+	// Source rectangle within the texture, in texels
+	pub src: Option<(u32, u32, u32, u32)>,
+
+	// UV scrolling and repeat count, for raw textures only
+	pub uv_scroll: (f32, f32),
+	pub uv_repeat: (f32, f32),
 }
 
 impl Renderable
 {
-	pub fn new(tex: &Rc<Texture>, x: i32, y: i32) -> Renderable
+	pub fn new(tex: &Rc<Texture>, x: f32, y: f32) -> Renderable
 	{
 		// Create a new renderable with mostly default values
 		Renderable
@@ -64,9 +147,44 @@ impl Renderable
 			visible: true,
 			x_scale: 1.0,
 			y_scale: 1.0,
-			x_origin: 0,
-			y_origin: 0,
+			x_origin: 0.0,
+			y_origin: 0.0,
 			angle: 0.0,
+			flip_x: false,
+			flip_y: false,
+			src: None,
+			uv_scroll: (0.0, 0.0),
+			uv_repeat: (1.0, 1.0),
+		}
+	}
+}
+
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum PixelSnap
+{
+	// Positions are used as they are
+	None,
+
+	// Positions are rounded to whole units, which are screen pixels with the default projection
+	Pixel,
+
+	// Positions are rounded to a grid of virtual pixels, each covering the given number of units
+	// E.g. 4.0 for pixel art scaled up 4 times, so sprites only move by whole art pixels
+	VirtualPixel (f32),
+}
+
+impl PixelSnap
+{
+	pub fn apply(&self, x: f32, y: f32) -> (f32, f32)
+	{
+		// Snap a position
+		match *self
+		{
+			PixelSnap::None => (x, y),
+			PixelSnap::Pixel => (x.round(), y.round()),
+			PixelSnap::VirtualPixel (size) if size>0.0 => ((x/size).round()*size, (y/size).round()*size),
+			PixelSnap::VirtualPixel (_) => (x, y),
 		}
 	}
 }
@@ -99,6 +217,17 @@ pub struct Quad
 	pub ox: f32,
 	pub oy: f32,
 
+	// Mirroring
+	pub flip_x: bool,
+	pub flip_y: bool,
+
+	// Source rectangle within the texture, in texels
+	pub src: Option<(f32, f32, f32, f32)>,
+
+	// UV scrolling and repeat count, for raw textures only
+	pub uv_scroll: (f32, f32),
+	pub uv_repeat: (f32, f32),
+
 	// Texture
 	pub tex: Rc<Texture>,
 }
@@ -121,6 +250,11 @@ impl Quad
 			angle: 0.0,
 			ox: 0.0,
 			oy: 0.0,
+			flip_x: false,
+			flip_y: false,
+			src: None,
+			uv_scroll: (0.0, 0.0),
+			uv_repeat: (1.0, 1.0),
 			tex: Rc::clone(tex),
 		}
 	}
@@ -165,6 +299,80 @@ impl Quad
 		self
 	}
 
+	pub fn with_flip(mut self, flip_x: bool, flip_y: bool) -> Quad
+	{
+		self.flip_x = flip_x;
+		self.flip_y = flip_y;
+		self
+	}
+
+	pub fn with_src(mut self, x: f32, y: f32, w: f32, h: f32) -> Quad
+	{
+		// Only draw part of the texture, and size the quad to match
+		self.src = Some((x, y, w, h));
+		self.w = w;
+		self.h = h;
+		self
+	}
+
+	pub fn with_uv_scroll(mut self, u: f32, v: f32) -> Quad
+	{
+		self.uv_scroll = (u, v);
+		self
+	}
+
+	pub fn with_uv_repeat(mut self, u: f32, v: f32) -> Quad
+	{
+		self.uv_repeat = (u, v);
+		self
+	}
+
+	fn calc_uv(&self) -> (f32, f32, f32, f32)
+	{
+		// Start from the whole texture, within its atlas
+		self.calc_uv_in(self.tex.uv())
+	}
+
+	pub(crate) fn calc_uv_in(&self, base: (f32, f32, f32, f32)) -> (f32, f32, f32, f32)
+	{
+		// Start from the given UV rectangle
+		let (mut u1, mut v1, mut u2, mut v2) = base;
+
+		// Restrict to the source rectangle
+		if let Some((sx, sy, sw, sh)) = self.src
+		{
+			let (tw, th) = self.tex.size();
+			let du = (u2-u1) / tw as f32;
+			let dv = (v2-v1) / th as f32;
+
+			u1 += sx*du;
+			v1 += sy*dv;
+			u2 = u1 + sw*du;
+			v2 = v1 + sh*dv;
+		}
+
+		// Scrolling and repeating would bleed into neighbouring atlas entries
+		if self.tex.is_raw()
+		{
+			let uw = (u2-u1) * self.uv_repeat.0;
+			let vh = (v2-v1) * self.uv_repeat.1;
+
+			u1 += self.uv_scroll.0;
+			v1 += self.uv_scroll.1;
+			u2 = u1 + uw;
+			v2 = v1 + vh;
+		}
+
+		// Mirror
+		if self.flip_x
+			{ std::mem::swap(&mut u1, &mut u2); }
+
+		if self.flip_y
+			{ std::mem::swap(&mut v1, &mut v2); }
+
+		(u1, v1, u2, v2)
+	}
+
 	fn write_vertex(&self, vtx: &mut Vertex, x: f32, y: f32, u: f32, v: f32)
 	{
 		// Write vertex data
@@ -182,13 +390,58 @@ impl Quad
 		vtx.oy = -self.oy;
 	}
 
+	pub fn bounds(&self) -> (f32, f32, f32, f32)
+	{
+		// Get the bounding box of the transformed quad, as min and max corners
+		let mut b = (f32::MAX, f32::MAX, f32::MIN, f32::MIN);
+
+		for &(cx, cy) in [(0.0, 0.0), (self.w, 0.0), (0.0, self.h), (self.w, self.h)].iter()
+		{
+			let (x, y) = self.transform(cx, cy);
+			b = (b.0.min(x), b.1.min(y), b.2.max(x), b.3.max(y));
+		}
+
+		b
+	}
+
+	pub(crate) fn transform(&self, x: f32, y: f32) -> (f32, f32)
+	{
+		// Quad coordinates to world ones, like the shaders: origin, scale, rotation, translation
+		let (sin, cos) = self.angle.sin_cos();
+		let lx = (x-self.ox) * self.sx;
+		let ly = (y-self.oy) * self.sy;
+
+		(self.x + lx*cos - ly*sin, self.y + lx*sin + ly*cos)
+	}
+
+	fn write_instance(&self, inst: &mut Instance)
+	{
+		// Write instance data, the corners are generated by the shader
+		let (u1, v1, u2, v2) = self.calc_uv();
+
+		inst.w = self.w;
+		inst.h = self.h;
+		inst.col = self.col;
+		inst.u1 = u1;
+		inst.v1 = v1;
+		inst.u2 = u2;
+		inst.v2 = v2;
+		inst.tx = self.x;
+		inst.ty = self.y;
+		inst.sx = self.sx;
+		inst.sy = self.sy;
+		inst.angle = self.angle;
+		inst.ox = -self.ox;
+		inst.oy = -self.oy;
+	}
+
 	fn write_vertices(&self, v: &mut [Vertex])
 	{
 		let x1 = 0.0;
 		let y1 = 0.0;
-		let x2 = self.w as f32;
-		let y2 = self.h as f32;
-		let (u1, v1, u2, v2) = self.tex.uv();
+		let x2 = self.w;
+		let y2 = self.h;
+		let (u1, v1, u2, v2) = self.calc_uv();
 
 		// First triangle
 		self.write_vertex(&mut v[0], x1, y1, u1, v1);
@@ -204,11 +457,267 @@ impl Quad
 }
 
 
+fn write_shape_vertices(shape: &Shape, uv: (f32, f32), v: &mut [Vertex])
+{
+	// Shapes are already in world coordinates, so they don't need any transform
+	for (vtx, &(x, y)) in v.iter_mut().zip(shape.points.iter())
+	{
+		vtx.x = x;
+		vtx.y = y;
+		vtx.col = shape.col;
+		vtx.u = uv.0;
+		vtx.v = uv.1;
+		vtx.tx = 0.0;
+		vtx.ty = 0.0;
+		vtx.sx = 1.0;
+		vtx.sy = 1.0;
+		vtx.angle = 0.0;
+		vtx.ox = 0.0;
+		vtx.oy = 0.0;
+	}
+}
+
+
+pub trait QuadRenderer
+{
+	// Queue a quad
+	fn add_quad(&mut self, quad: Quad);
+
+	// Queue a shape
+	fn add_shape(&mut self, shape: Shape);
+
+	// Draw the queued primitives and clear the queue
+	fn render(&mut self, proj_mat: &Matrix4<f32>) -> RenderStats;
+
+	// Snapping policy for the positions of the world's objects
+	fn pixel_snap(&self) -> PixelSnap;
+	fn set_pixel_snap(&mut self, snap: PixelSnap);
+
+	fn add_world(&mut self, world: &World)
+	{
+		// Quads of world objects get their translation snapped
+		let snap = self.pixel_snap();
+		let snapped = |q: Quad| { let (x, y) = snap.apply(q.x, q.y); q.with_pos(x, y) };
+
+		// Add all the renderables from the provided world
+		for (_, rend) in world.iter::<Renderable>()
+		{
+			// Create a quad for the renderable
+			let (w, h) = rend.texture.size();
+
+			let quad = Quad::new(&rend.texture)
+				.with_pos(rend.x, rend.y)
+				.with_size(w as f32, h as f32)
+				.with_color(rend.color)
+				.with_scale(rend.x_scale, rend.y_scale)
+				.with_angle(rend.angle)
+				.with_origin(rend.x_origin, rend.y_origin)
+				.with_flip(rend.flip_x, rend.flip_y)
+				.with_uv_scroll(rend.uv_scroll.0, rend.uv_scroll.1)
+				.with_uv_repeat(rend.uv_repeat.0, rend.uv_repeat.1);
+
+			let quad = match rend.src
+				{
+					Some ((sx, sy, sw, sh)) => quad.with_src(sx as f32, sy as f32, sw as f32, sh as f32),
+					None => quad,
+				};
+
+			self.add_quad(snapped(quad));
+		}
+
+		// Add all the particles
+		if world.is_registered::<ParticleEmitter>()
+		{
+			for (_, em) in world.iter::<ParticleEmitter>()
+			{
+				if !em.visible
+					{ continue; }
+
+				for quad in em.build_quads()
+					{ self.add_quad(snapped(quad)); }
+			}
+		}
+
+		// Add all the nine-slices
+		if world.is_registered::<NineSlice>()
+		{
+			for (_, ns) in world.iter::<NineSlice>()
+			{
+				if !ns.visible
+					{ continue; }
+
+				for quad in ns.build_quads()
+					{ self.add_quad(snapped(quad)); }
+			}
+		}
+
+		// Add all the texts
+		if world.is_registered::<Text>()
+		{
+			for (_, text) in world.iter::<Text>()
+			{
+				if !text.visible
+					{ continue; }
+
+				// Each glyph gets the text's transform, so the whole string rotates and scales around its anchor
+				let quads = glyph_quads(&text.font, &text.text, text.align, |tex|
+					Quad::new(tex)
+						.with_pos(text.x, text.y)
+						.with_color(text.color)
+						.with_scale(text.x_scale, text.y_scale)
+						.with_angle(text.angle)
+				);
+
+				for quad in quads
+					{ self.add_quad(snapped(quad)); }
+			}
+		}
+	}
+
+	fn add_nine_slice(&mut self, ns: &NineSlice)
+	{
+		// Add the quads for all nine cells
+		for quad in ns.build_quads()
+		{
+			self.add_quad(quad);
+		}
+	}
+
+	fn add_particles(&mut self, em: &ParticleEmitter)
+	{
+		// Add a quad for every live particle
+		for quad in em.build_quads()
+		{
+			self.add_quad(quad);
+		}
+	}
+
+	fn add_text(&mut self, font: &Font, x: f32, y: f32, color: Color, text: &str)
+	{
+		// Add a left-aligned string of text
+		let quads = glyph_quads(font, text, TextAlign::Left, |tex|
+			Quad::new(tex)
+				.with_pos(x, y)
+				.with_color(color)
+		);
+
+		for quad in quads
+			{ self.add_quad(quad); }
+	}
+}
+
+
+fn glyph_quads<F>(font: &Font, text: &str, align: TextAlign, make_quad: F) -> Vec<Quad>
+where
+	F: Fn(&Rc<Texture>) -> Quad
+{
+	// The glyphs need textures
+	if !font.is_packed()
+		{ panic!("QuadRenderer.add_text(): the font hasn't been packed, see Font::pack() and Font::pack_soft()"); }
+
+	let mut quads = Vec::new();
+
+	for (line, s) in text.split('\n').enumerate()
+	{
+		// Align the line around the anchor point
+		let w = font.str_width(s) as f32;
+
+		let mut gx = match align
+			{
+				TextAlign::Left => 0.0,
+				TextAlign::Center => -w / 2.0,
+				TextAlign::Right => -w,
+			};
+
+		let gy = (line as u32 * font.height()) as f32;
+
+		// Add a quad per glyph, offsetting its origin so it sits at its place in the string
+		for ch in s.chars()
+		{
+			if let Some(tex) = font.glyph_texture(ch)
+			{
+				let quad = make_quad(tex);
+				let (ox, oy) = (quad.ox - gx, quad.oy - gy);
+
+				quads.push(quad.with_origin(ox, oy));
+			}
+
+			gx += font.char_width(ch) as f32;
+		}
+	}
+
+	quads
+}
+
+
+enum Prim
+{
+	Quad (Quad),
+	Shape (Shape),
+}
+
+impl Prim
+{
+	fn vertex_count(&self) -> usize
+	{
+		match *self
+		{
+			Prim::Quad (_) => 6,
+			Prim::Shape (ref shape) => shape.points.len(),
+		}
+	}
+
+	fn bounds(&self) -> (f32, f32, f32, f32)
+	{
+		match *self
+		{
+			Prim::Quad (ref quad) => quad.bounds(),
+			Prim::Shape (ref shape) => shape.bounds(),
+		}
+	}
+
+	fn is_quad(&self) -> bool
+	{
+		match *self
+		{
+			Prim::Quad (_) => true,
+			Prim::Shape (_) => false,
+		}
+	}
+}
+
+
 pub struct Renderer
 {
 	shader: ShaderProgram,
+	inst_shader: ShaderProgram,
 	vb: VertexBuffer<Vertex>,
-	quads: Vec<Quad>,
+	ib: VertexBuffer<Instance>,
+	instanced: bool,
+	prims: Vec<Prim>,
+	white: Rc<Texture>,
+	flat_normal: Rc<Texture>,
+	culling: bool,
+	snap: PixelSnap,
+	drawn: usize,
+	culled: usize,
+	gpu_timer: Option<GpuTimer>,
+}
+
+
+fn view_rect(proj_mat: &Matrix4<f32>) -> Option<(f32, f32, f32, f32)>
+{
+	// Find the world area covered by the projection, camera transforms included
+	let inv = proj_mat.try_inverse()?;
+	let mut b = (f32::MAX, f32::MAX, f32::MIN, f32::MIN);
+
+	for &(x, y) in [(-1.0, -1.0), (1.0, -1.0), (-1.0, 1.0), (1.0, 1.0)].iter()
+	{
+		let p = inv * Vector4::new(x, y, 0.0, 1.0);
+		b = (b.0.min(p.x), b.1.min(p.y), b.2.max(p.x), b.3.max(p.y));
+	}
+
+	Some(b)
 }
 
 
@@ -216,114 +725,480 @@ impl Renderer
 {
 	pub fn new() -> Result<Renderer, Error>
 	{
-		// Create the shader and VBO
+		// Create the shaders and VBOs
 		let shader = ShaderProgram::from_str(
 			include_str!("../shaders/renderable.vert"),
 			include_str!("../shaders/renderable.frag")
 		)?;
 
-		let vb = VertexBuffer::new();
+		let inst_shader = ShaderProgram::from_str(
+			include_str!("../shaders/renderable_inst.vert"),
+			include_str!("../shaders/renderable.frag")
+		)?;
+
+		let vb = VertexBuffer::new(&VERTEX_ATTRIBS);
+		let ib = VertexBuffer::new_instanced(&INSTANCE_ATTRIBS);
+
+		// Shapes need a white texel to sample from until one is provided
+		let white = Rc::new(Texture::from_canvas(&Canvas::new(1, 1, Color::rgb(1.0, 1.0, 1.0)), false));
+
+		// Textures without a normal map get a flat one during the normal pass
+		let flat_normal = Rc::new(Texture::from_canvas(&Canvas::new(1, 1, Color::rgb8(128, 128, 255)), false));
+
+		// Normal maps go on the second texture unit
+		for sh in [&shader, &inst_shader].iter()
+		{
+			sh.enable();
+			sh.set_uniform_int("normals", 1);
+		}
 
 		// Create the renderer
 		let rend = Renderer
 			{
 				shader,
+				inst_shader,
 				vb,
-				quads: Vec::new(),
+				ib,
+				instanced: true,
+				prims: Vec::new(),
+				white,
+				flat_normal,
+				culling: true,
+				snap: PixelSnap::None,
+				drawn: 0,
+				culled: 0,
+				gpu_timer: GpuTimer::new(),
 			};
 
 		Ok(rend)
 	}
 
-	pub fn add_quad(&mut self, quad: Quad)
+	pub fn set_instanced(&mut self, instanced: bool)
 	{
-		// Add a quad to the queue
-		self.quads.push(quad);
+		// Choose between one instance record per quad, or 6 full vertices
+		self.instanced = instanced;
 	}
 
-	pub fn add_world(&mut self, world: &World)
+	pub fn instanced(&self) -> bool
 	{
-		// Add all the renderables from the provided world
-		for (_, rend) in world.iter::<Renderable>()
+		self.instanced
+	}
+
+	pub fn set_culling(&mut self, culling: bool)
+	{
+		// Enable or disable skipping the primitives outside of the view
+		self.culling = culling;
+	}
+
+	pub fn culling(&self) -> bool
+	{
+		self.culling
+	}
+
+	pub fn cull_counts(&self) -> (usize, usize)
+	{
+		// Number of drawn and culled primitives during the last render
+		(self.drawn, self.culled)
+	}
+
+	fn cull(&mut self, proj_mat: &Matrix4<f32>)
+	{
+		// Drop the primitives that are entirely outside the view
+		let total = self.prims.len();
+
+		if let Some((vx1, vy1, vx2, vy2)) = view_rect(proj_mat)
 		{
-			// Create a quad for the renderable
-			let (w, h) = rend.texture.size();
+			self.prims.retain(|p|
+				{
+					let (x1, y1, x2, y2) = p.bounds();
+					x2>=vx1 && x1<=vx2 && y2>=vy1 && y1<=vy2
+				});
+		}
 
-			let quad = Quad::new(&rend.texture)
-				.with_pos(rend.x as f32, rend.y as f32)
-				.with_size(w as f32, h as f32)
-				.with_color(rend.color)
-				.with_scale(rend.x_scale, rend.y_scale)
-				.with_angle(rend.angle)
-				.with_origin(rend.x_origin as f32, rend.y_origin as f32);
+		self.drawn = self.prims.len();
+		self.culled = total-self.drawn;
+	}
 
-			self.add_quad(quad);
+	pub fn set_white_texture(&mut self, tex: &Rc<Texture>)
+	{
+		// Use a white texture for drawing shapes
+		// Putting it in the same atlas as the sprites avoids breaking batches
+		self.white = Rc::clone(tex);
+	}
+
+	fn prim_texture<'a>(&'a self, prim: &'a Prim) -> &'a Rc<Texture>
+	{
+		// Get the texture a primitive is drawn with
+		match *prim
+		{
+			Prim::Quad (ref quad) => &quad.tex,
+			Prim::Shape (_) => &self.white,
 		}
 	}
 
-	fn write_vb(&mut self)
+	fn uses_instance(&self, prim: &Prim) -> bool
 	{
-		// Map enough vertices for all the quads
-		let mut map = self.vb.map(self.quads.len()*6);
-		let mut pos = 0;
+		// Quads are drawn as instances, everything else as plain triangles
+		self.instanced && prim.is_quad()
+	}
+
+	fn write_vb(&mut self) -> (usize, usize)
+	{
+		// Count the vertices and instances needed for all the primitives
+		let mut vertices = 0;
+		let mut instances = 0;
+
+		for prim in self.prims.iter()
+		{
+			if self.uses_instance(prim)
+				{ instances += 1; }
+			else
+				{ vertices += prim.vertex_count(); }
+		}
+
+		// Map them
+		let mut vmap = if vertices>0 { Some(self.vb.map(vertices)) } else { None };
+		let mut imap = if instances>0 { Some(self.ib.map(instances)) } else { None };
+		let mut vpos = 0;
+		let mut ipos = 0;
+
+		// Shapes all sample the middle of the white texture
+		let (u1, v1, u2, v2) = self.white.uv();
+		let white_uv = ((u1+u2) / 2.0, (v1+v2) / 2.0);
 
-		// Write all the vertices
-		for quad in self.quads.iter()
+		// Write all the data
+		for prim in self.prims.iter()
 		{
-			quad.write_vertices(&mut map[pos..pos+6]);
-			pos += 6;
+			match *prim
+			{
+				Prim::Quad (ref quad) if self.instanced =>
+					{
+						// One instance per quad
+						if let Some(ref mut map) = imap
+							{ quad.write_instance(&mut map[ipos]); }
+
+						ipos += 1;
+					},
+
+				Prim::Quad (ref quad) =>
+					{
+						if let Some(ref mut map) = vmap
+							{ quad.write_vertices(&mut map[vpos..vpos+6]); }
+
+						vpos += 6;
+					},
+
+				Prim::Shape (ref shape) =>
+					{
+						let count = shape.points.len();
+
+						if let Some(ref mut map) = vmap
+							{ write_shape_vertices(shape, white_uv, &mut map[vpos..vpos+count]); }
+
+						vpos += count;
+					},
+			}
 		}
+
+		(vertices, instances)
 	}
 
-	fn count_similar(&self, start: usize) -> usize
+	fn count_similar(&self, start: usize) -> (usize, usize)
 	{
-		// Count how many quads use the same texture as the start one
+		// Count how many primitives use the same texture and drawing path as the start one
+		// Also count how many vertices or instances they take
 		let mut count = 1;
-		let len = self.quads.len();
-		let orig = &self.quads[start].tex;
+		let mut size = self.prim_size(&self.prims[start]);
+		let len = self.prims.len();
+		let orig = self.prim_texture(&self.prims[start]);
+		let inst = self.uses_instance(&self.prims[start]);
 
 		for c in start+1..len
 		{
 			// Stop if it differs
-			if !orig.is_same(&self.quads[c].tex)
+			if !orig.is_same(self.prim_texture(&self.prims[c])) || inst!=self.uses_instance(&self.prims[c])
 				{ break; }
 
 			count += 1;
+			size += self.prim_size(&self.prims[c]);
 		}
 
-		count
+		(count, size)
+	}
+
+	fn prim_size(&self, prim: &Prim) -> usize
+	{
+		// Number of instances or vertices a primitive takes
+		if self.uses_instance(prim)
+			{ 1 }
+		else
+			{ prim.vertex_count() }
+	}
+
+	pub fn render_normals(&mut self, proj_mat: &Matrix4<f32>) -> RenderStats
+	{
+		// Draw the normal maps of the queued primitives, keeping them queued for the color pass
+		self.draw(proj_mat, true)
+	}
+
+	fn bind_texture(&self, tex: &Texture, normals: bool)
+	{
+		// Bind the normal map on the second unit for the normal pass
+		if normals && !tex.enable_normals(1)
+			{ self.flat_normal.enable_on(1); }
+
+		tex.enable();
 	}
 
-	pub fn render(&mut self, proj_mat: &Matrix4<f32>)
+	fn draw(&mut self, proj_mat: &Matrix4<f32>, normals: bool) -> RenderStats
 	{
-		// Build the vertex buffer data
-		self.write_vb();
+		let mut stats = RenderStats::default();
+
+		// Skip what can't be seen
+		if self.culling
+		{
+			self.cull(proj_mat);
+		}
+		else
+		{
+			self.drawn = self.prims.len();
+			self.culled = 0;
+		}
 
-		// Set the projection matrix
-		self.shader.enable();
-		self.shader.set_uniform_matrix("Projection", proj_mat);
+		stats.culled = self.culled;
+
+		// Nothing to do without primitives
+		if self.prims.is_empty()
+			{ return stats; }
+
+		for prim in self.prims.iter()
+		{
+			if prim.is_quad()
+				{ stats.quads += 1; }
+			else
+				{ stats.shapes += 1; }
+		}
+
+		// Build the vertex and instance buffer data
+		let start = Instant::now();
+		let (vertices, instances) = self.write_vb();
+
+		stats.vertices = vertices;
+		stats.instances = instances;
+		stats.write_time = start.elapsed();
+
+		let start = Instant::now();
+
+		if let Some(ref mut timer) = self.gpu_timer
+			{ timer.begin(); }
+
+		// Set the projection matrix and pass
+		for sh in [&self.shader, &self.inst_shader].iter()
+		{
+			sh.enable();
+			sh.set_uniform_matrix("Projection", proj_mat);
+			sh.set_uniform_int("NormalPass", if normals { 1 } else { 0 });
+		}
 
 
-		// Draw the quads
+		// Draw the primitives
 		let mut pos = 0;
-		let len = self.quads.len();
+		let mut first_vtx = 0;
+		let mut first_inst = 0;
+		let mut bound: Option<usize> = None;
+		let len = self.prims.len();
 
 		while pos<len
 		{
-			// Count how many use the same texture
-			let count = self.count_similar(pos);
+			// Count how many can be drawn together
+			let (count, size) = self.count_similar(pos);
 
-			//println!("Start: {}, Count: {}", pos, count);
+			// Only bind the texture if it changed, batches also break when switching between instances and triangles
+			let tex = self.prim_texture(&self.prims[pos]);
+			let rebind = match bound
+				{
+					Some (b) => !self.prim_texture(&self.prims[b]).is_same(tex),
+					None => true,
+				};
+
+			if rebind
+			{
+				self.bind_texture(tex, normals);
+				bound = Some(pos);
+				stats.texture_binds += 1;
+			}
+
+			if pos>0
+			{
+				if rebind
+					{ stats.texture_breaks += 1; }
+				else
+					{ stats.path_breaks += 1; }
+			}
 
 			// Draw them
-			self.quads[pos].tex.enable();
-			self.vb.draw_triangles(pos as u32*6, count as u32*6);
+			if self.uses_instance(&self.prims[pos])
+			{
+				self.inst_shader.enable();
+				self.ib.draw_instanced_quads(first_inst as u32, size as u32);
+				first_inst += size;
+			}
+			else
+			{
+				self.shader.enable();
+				self.vb.draw_triangles(first_vtx as u32, size as u32);
+				first_vtx += size;
+			}
 
+			stats.draw_calls += 1;
 			pos += count;
 		}
 
-		// Clear the queued quads
-		self.quads.clear();
+		if let Some(ref mut timer) = self.gpu_timer
+		{
+			timer.end();
+			stats.gpu_time = timer.last();
+		}
+
+		stats.draw_time = start.elapsed();
+
+		stats
 	}
 }
 
+impl QuadRenderer for Renderer
+{
+	fn add_quad(&mut self, quad: Quad)
+	{
+		// Add a quad to the queue
+		self.prims.push(Prim::Quad(quad));
+	}
+
+	fn add_shape(&mut self, shape: Shape)
+	{
+		// Add a shape to the queue
+		if !shape.points.is_empty()
+			{ self.prims.push(Prim::Shape(shape)); }
+	}
+
+	fn render(&mut self, proj_mat: &Matrix4<f32>) -> RenderStats
+	{
+		// Draw the queued primitives
+		let stats = self.draw(proj_mat, false);
+
+		// Clear them
+		self.prims.clear();
+
+		stats
+	}
+
+	fn pixel_snap(&self) -> PixelSnap
+	{
+		self.snap
+	}
+
+	fn set_pixel_snap(&mut self, snap: PixelSnap)
+	{
+		self.snap = snap;
+	}
+}
+
+//------------------------------------------------------------------------------------------------------------------------
+//------------------------------------------------------------------------------------------------------------------------
+//------------------------------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+	use super::super::{set_backend, RecordingBackend};
+	use nalgebra::base::Vector3;
+
+	fn near(a: (f32, f32, f32, f32), b: (f32, f32, f32, f32)) -> bool
+	{
+		(a.0-b.0).abs()<1e-4 && (a.1-b.1).abs()<1e-4 && (a.2-b.2).abs()<1e-4 && (a.3-b.3).abs()<1e-4
+	}
+
+	fn texture() -> Rc<Texture>
+	{
+		Rc::new(Texture::soft(Canvas::new(10, 10, Color::rgb(1.0, 1.0, 1.0))))
+	}
+
+	fn proj() -> Matrix4<f32>
+	{
+		// A 100x100 view
+		Matrix4::new_orthographic(0.0, 100.0, 100.0, 0.0, -1.0, 1.0)
+	}
+
+	#[test]
+	fn bounds()
+	{
+		let tex = texture();
+
+		// Scaled, off its origin
+		let q = Quad::new(&tex).with_pos(20.0, 30.0).with_scale(2.0, 3.0).with_origin(5.0, 10.0);
+		assert!(near(q.bounds(), (10.0, 0.0, 30.0, 30.0)));
+
+		// A quarter turn around the top left corner
+		let q = Quad::new(&tex).with_pos(20.0, 30.0).with_angle(std::f32::consts::FRAC_PI_2);
+		assert!(near(q.bounds(), (10.0, 30.0, 20.0, 40.0)));
+
+		// An eighth turn around the center grows the box by sqrt(2)
+		let q = Quad::new(&tex).with_pos(50.0, 50.0).with_origin(5.0, 5.0).with_angle(std::f32::consts::FRAC_PI_4);
+		let r = 5.0 * 2.0f32.sqrt();
+		assert!(near(q.bounds(), (50.0-r, 50.0-r, 50.0+r, 50.0+r)));
+	}
+
+	#[test]
+	fn view()
+	{
+		assert!(near(view_rect(&proj()).unwrap(), (0.0, 0.0, 100.0, 100.0)));
+
+		// A camera moved 50 to the right
+		let cam = proj() * Matrix4::new_translation(&Vector3::new(-50.0, 0.0, 0.0));
+		assert!(near(view_rect(&cam).unwrap(), (50.0, 0.0, 150.0, 100.0)));
+	}
+
+	#[test]
+	fn culling()
+	{
+		// Drawn through the recording backend, the textures can't be software ones
+		set_backend(Box::new(RecordingBackend::new()));
+
+		let tex = Rc::new(Texture::from_canvas(&Canvas::new(10, 10, Color::rgb(1.0, 1.0, 1.0)), false));
+		let mut rend = Renderer::new().unwrap();
+
+		// Inside, straddling the right edge, outside on the right, outside above
+		let quads = || vec![
+				Quad::new(&tex).with_pos(10.0, 10.0),
+				Quad::new(&tex).with_pos(95.0, 10.0),
+				Quad::new(&tex).with_pos(120.0, 10.0),
+				Quad::new(&tex).with_pos(10.0, -20.0),
+			];
+
+		for q in quads()
+			{ rend.add_quad(q); }
+
+		let stats = rend.render(&proj());
+		assert_eq!(rend.cull_counts(), (2, 2));
+		assert_eq!(stats.culled, 2);
+
+		// With the camera moved right, the first one leaves and the third one comes in
+		let cam = proj() * Matrix4::new_translation(&Vector3::new(-50.0, 0.0, 0.0));
+
+		for q in quads()
+			{ rend.add_quad(q); }
+
+		rend.render(&cam);
+		assert_eq!(rend.cull_counts(), (2, 2));
+
+		// Nothing is culled when culling is off
+		rend.set_culling(false);
+
+		for q in quads()
+			{ rend.add_quad(q); }
+
+		rend.render(&proj());
+		assert_eq!(rend.cull_counts(), (4, 0));
+	}
+}
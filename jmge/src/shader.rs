@@ -1,98 +1,49 @@
 
-use std::ffi::{CString};
 use super::Error;
+use super::backend::{with_backend, Uniform};
 use nalgebra::base::{Matrix4};
 
 
-fn space_cstring(len: usize) -> CString
+fn source_from_bytes(source: &[u8]) -> Result<String, Error>
 {
-	// Create a C string filled with spaces
-	let mut buffer: Vec<u8> = Vec::with_capacity(len+1);
-	buffer.extend([b' '].iter().cycle().take(len));
-	unsafe { CString::from_vec_unchecked(buffer) }
-}
-
-struct Shader (u32);
-
-impl Shader
-{
-	fn from_bytes(source: &[u8], stype: u32) -> Result<Shader, Error>
-	{
-		let id = unsafe { gl::CreateShader(stype) };
-		let shader = Shader (id);
-
-		unsafe
-		{
-			// Compile the shader
-			let len = source.len() as i32;
-			gl::ShaderSource(id, 1, &(source.as_ptr() as *const i8), &len);
-			gl::CompileShader(id);
-		
-			// Check success
-			let mut success: i32 = 1;
-			gl::GetShaderiv(id, gl::COMPILE_STATUS, &mut success);
-
-			if success==0
-			{
-				// Failure, extract the error message
-				let mut len: i32 = 0;
-				gl::GetShaderiv(id, gl::INFO_LOG_LENGTH, &mut len);
-
-				let error: CString = space_cstring(len as usize);
-				gl::GetShaderInfoLog(id, len, std::ptr::null_mut(), error.as_ptr() as *mut i8);
-
-				return Err(Error::CompileShader(error.to_string_lossy().into_owned()));
-			}
-		}
-
-		Ok(shader)
-	}
-
-	pub fn id(&self) -> u32
+	// Shader sources have to be valid UTF-8
+	match std::str::from_utf8(source)
 	{
-		self.0
+		Ok (s) => Ok(s.to_string()),
+		Err (_) => Err(Error::CompileShader("The source isn't valid UTF-8".to_string())),
 	}
 }
 
-impl Drop for Shader
-{
-	fn drop(&mut self)
-	{
-		// Delete the shader
-		unsafe { gl::DeleteShader(self.0); }
-	}
-}
 
-pub struct VertexShader (Shader);
+// Shader sources, compiled by the backend when linked into a program
+pub struct VertexShader (String);
 
 impl VertexShader
 {
 	pub fn from_bytes(source: &[u8]) -> Result<VertexShader, Error>
 	{
-		let shader = Shader::from_bytes(source, gl::VERTEX_SHADER)?;
-		Ok(VertexShader (shader))
+		Ok(VertexShader (source_from_bytes(source)?))
 	}
 
-	pub fn id(&self) -> u32
+	pub fn source(&self) -> &str
 	{
-		self.0.id()
+		&self.0
 	}
 }
 
 
-pub struct FragmentShader (Shader);
+pub struct FragmentShader (String);
 
 impl FragmentShader
 {
 	pub fn from_bytes(source: &[u8]) -> Result<FragmentShader, Error>
 	{
-		let shader = Shader::from_bytes(source, gl::FRAGMENT_SHADER)?;
-		Ok(FragmentShader (shader))
+		Ok(FragmentShader (source_from_bytes(source)?))
 	}
 
-	pub fn id(&self) -> u32
+	pub fn source(&self) -> &str
 	{
-		self.0.id()
+		&self.0
 	}
 }
 
@@ -103,36 +54,10 @@ impl ShaderProgram
 {
 	pub fn new(vert: VertexShader, frag: FragmentShader) -> Result<ShaderProgram, Error>
 	{
-		// Create the program
-		let id = unsafe { gl::CreateProgram() };
-		let prg = ShaderProgram (id);
-
-		unsafe
-		{
-			// Link the program
-			gl::AttachShader(id, vert.id());
-			gl::AttachShader(id, frag.id());
-			gl::LinkProgram(id);
-
-			// Check success
-			let mut success: i32 = 1;
-			gl::GetProgramiv(id, gl::LINK_STATUS, &mut success);
-
-			if success==0
-			{
-				// Failure, extract the error message
-				let mut len: i32 = 0;
-				gl::GetProgramiv(id, gl::INFO_LOG_LENGTH, &mut len);
-
-				let error = space_cstring(len as usize);
-				gl::GetProgramInfoLog(id, len, std::ptr::null_mut(), error.as_ptr() as *mut i8);
-		
-				return Err(Error::CompileShader(error.to_string_lossy().into_owned()));
-			}
-
-		}
+		// Compile and link the program
+		let id = with_backend(|b| b.create_pipeline(vert.source(), frag.source()))?;
 
-		Ok(prg)
+		Ok(ShaderProgram (id))
 	}
 
 	pub fn from_str(vert: &str, frag: &str) -> Result<ShaderProgram, Error>
@@ -152,24 +77,46 @@ impl ShaderProgram
 	pub fn enable(&self)
 	{
 		// Enable the shader program
-		unsafe
-		{
-			gl::UseProgram(self.0);
-		}
+		with_backend(|b| b.bind_pipeline(self.0));
+	}
+
+	fn set_uniform(&self, name: &str, value: Uniform)
+	{
+		// The backend panics if the uniform doesn't exist
+		with_backend(|b| b.set_uniform(self.0, name, value));
 	}
 
 	pub fn set_uniform_matrix(&self, name: &str, mat: &Matrix4<f32>)
 	{
-		// Find the uniform and set it
-		unsafe
-		{
-			let uni = gl::GetUniformLocation(self.0, CString::new(name).unwrap().as_bytes_with_nul().as_ptr() as *const i8);
+		let mut m = [0.0; 16];
+		m.copy_from_slice(mat.as_slice());
 
-			if uni<0
-				{ panic!("set_uniform_matrix(): Uniform '{}' not found", name); }
+		self.set_uniform(name, Uniform::Matrix(m));
+	}
 
-			gl::UniformMatrix4fv(uni, 1, 0, mat.as_ptr());
-		}
+	pub fn set_uniform_float(&self, name: &str, v: f32)
+	{
+		self.set_uniform(name, Uniform::Float(v));
+	}
+
+	pub fn set_uniform_vec2(&self, name: &str, x: f32, y: f32)
+	{
+		self.set_uniform(name, Uniform::Vec2(x, y));
+	}
+
+	pub fn set_uniform_vec3(&self, name: &str, x: f32, y: f32, z: f32)
+	{
+		self.set_uniform(name, Uniform::Vec3(x, y, z));
+	}
+
+	pub fn set_uniform_vec4(&self, name: &str, x: f32, y: f32, z: f32, w: f32)
+	{
+		self.set_uniform(name, Uniform::Vec4(x, y, z, w));
+	}
+
+	pub fn set_uniform_int(&self, name: &str, v: i32)
+	{
+		self.set_uniform(name, Uniform::Int(v));
 	}
 }
 
@@ -179,13 +126,9 @@ impl Drop for ShaderProgram
 	{
 		if self.0!=0
 		{
-			unsafe
-			{
-				gl::DeleteProgram(self.0);
-			}
+			let id = self.0;
+			with_backend(|b| b.delete_pipeline(id));
 		}
 	}
 }
 
-
-
@@ -0,0 +1,210 @@
+
+use super::Color;
+use std::f32::consts::PI;
+
+
+fn segment_count(rx: f32, ry: f32) -> usize
+{
+	// Pick a number of segments that looks round enough for the given radius
+	((rx.max(ry) * 0.5) as usize).clamp(12, 128)
+}
+
+fn ellipse_point(x: f32, y: f32, rx: f32, ry: f32, i: usize, segs: usize) -> (f32, f32)
+{
+	// Get a point on the ellipse
+	let a = (i as f32) * 2.0 * PI / (segs as f32);
+
+	(x + a.cos()*rx, y + a.sin()*ry)
+}
+
+
+pub struct Shape
+{
+	// Triangle list, in world coordinates
+	pub points: Vec<(f32, f32)>,
+
+	// Color
+	pub col: u32,
+}
+
+impl Shape
+{
+	fn new() -> Shape
+	{
+		Shape
+		{
+			points: Vec::new(),
+			col: 0xFFFFFFFF,
+		}
+	}
+
+	pub fn with_color(mut self, col: Color) -> Shape
+	{
+		self.col = col.as_u32();
+		self
+	}
+
+	pub fn bounds(&self) -> (f32, f32, f32, f32)
+	{
+		// Get the bounding box of all the points, as min and max corners
+		let mut b = (f32::MAX, f32::MAX, f32::MIN, f32::MIN);
+
+		for &(x, y) in self.points.iter()
+		{
+			b = (b.0.min(x), b.1.min(y), b.2.max(x), b.3.max(y));
+		}
+
+		b
+	}
+
+	fn push_tri(&mut self, a: (f32, f32), b: (f32, f32), c: (f32, f32))
+	{
+		self.points.push(a);
+		self.points.push(b);
+		self.points.push(c);
+	}
+
+	fn push_quad(&mut self, a: (f32, f32), b: (f32, f32), c: (f32, f32), d: (f32, f32))
+	{
+		// Two triangles, corners given in order around the quad
+		self.push_tri(a, b, d);
+		self.push_tri(b, c, d);
+	}
+
+	fn push_segment(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, thickness: f32)
+	{
+		// Calc the segment normal
+		let dx = x2-x1;
+		let dy = y2-y1;
+		let len = (dx*dx + dy*dy).sqrt();
+
+		if len==0.0
+			{ return; }
+
+		let nx = -dy / len * thickness / 2.0;
+		let ny = dx / len * thickness / 2.0;
+
+		// Add a thin quad along the segment
+		self.push_quad((x1+nx, y1+ny), (x2+nx, y2+ny), (x2-nx, y2-ny), (x1-nx, y1-ny));
+	}
+
+	pub fn rect(x: f32, y: f32, w: f32, h: f32) -> Shape
+	{
+		// Filled rectangle
+		let mut shape = Shape::new();
+		shape.push_quad((x, y), (x+w, y), (x+w, y+h), (x, y+h));
+		shape
+	}
+
+	pub fn rect_outline(x: f32, y: f32, w: f32, h: f32, thickness: f32) -> Shape
+	{
+		// Rectangle border, drawn inside the rectangle
+		let t = thickness.min(w/2.0).min(h/2.0);
+		let mut shape = Shape::new();
+
+		shape.push_quad((x, y), (x+w, y), (x+w, y+t), (x, y+t));
+		shape.push_quad((x, y+h-t), (x+w, y+h-t), (x+w, y+h), (x, y+h));
+		shape.push_quad((x, y+t), (x+t, y+t), (x+t, y+h-t), (x, y+h-t));
+		shape.push_quad((x+w-t, y+t), (x+w, y+t), (x+w, y+h-t), (x+w-t, y+h-t));
+
+		shape
+	}
+
+	pub fn ellipse(x: f32, y: f32, rx: f32, ry: f32) -> Shape
+	{
+		// Filled ellipse, as a triangle fan around the center
+		let segs = segment_count(rx, ry);
+		let mut shape = Shape::new();
+
+		for i in 0..segs
+		{
+			let a = ellipse_point(x, y, rx, ry, i, segs);
+			let b = ellipse_point(x, y, rx, ry, i+1, segs);
+			shape.push_tri((x, y), a, b);
+		}
+
+		shape
+	}
+
+	pub fn ellipse_outline(x: f32, y: f32, rx: f32, ry: f32, thickness: f32) -> Shape
+	{
+		// Ellipse border, drawn inside the ellipse
+		let segs = segment_count(rx, ry);
+		let irx = (rx-thickness).max(0.0);
+		let iry = (ry-thickness).max(0.0);
+		let mut shape = Shape::new();
+
+		for i in 0..segs
+		{
+			let o1 = ellipse_point(x, y, rx, ry, i, segs);
+			let o2 = ellipse_point(x, y, rx, ry, i+1, segs);
+			let i1 = ellipse_point(x, y, irx, iry, i, segs);
+			let i2 = ellipse_point(x, y, irx, iry, i+1, segs);
+			shape.push_quad(o1, o2, i2, i1);
+		}
+
+		shape
+	}
+
+	pub fn circle(x: f32, y: f32, r: f32) -> Shape
+	{
+		Shape::ellipse(x, y, r, r)
+	}
+
+	pub fn circle_outline(x: f32, y: f32, r: f32, thickness: f32) -> Shape
+	{
+		Shape::ellipse_outline(x, y, r, r, thickness)
+	}
+
+	pub fn polygon(points: &[(f32, f32)]) -> Shape
+	{
+		// Filled convex polygon, as a triangle fan around the first point
+		let mut shape = Shape::new();
+
+		for i in 2..points.len()
+		{
+			shape.push_tri(points[0], points[i-1], points[i]);
+		}
+
+		shape
+	}
+
+	pub fn polygon_outline(points: &[(f32, f32)], thickness: f32) -> Shape
+	{
+		// Closed polygon border, centered on the edges
+		let mut shape = Shape::polyline(points, thickness);
+
+		if points.len()>2
+		{
+			let (x1, y1) = points[points.len()-1];
+			let (x2, y2) = points[0];
+			shape.push_segment(x1, y1, x2, y2, thickness);
+		}
+
+		shape
+	}
+
+	pub fn line(x1: f32, y1: f32, x2: f32, y2: f32, thickness: f32) -> Shape
+	{
+		// Single line segment
+		let mut shape = Shape::new();
+		shape.push_segment(x1, y1, x2, y2, thickness);
+		shape
+	}
+
+	pub fn polyline(points: &[(f32, f32)], thickness: f32) -> Shape
+	{
+		// Open chain of line segments
+		let mut shape = Shape::new();
+
+		for i in 1..points.len()
+		{
+			let (x1, y1) = points[i-1];
+			let (x2, y2) = points[i];
+			shape.push_segment(x1, y1, x2, y2, thickness);
+		}
+
+		shape
+	}
+}
+
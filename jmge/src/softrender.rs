@@ -0,0 +1,431 @@
+
+use super::{RenderStats, QuadRenderer, PixelSnap, Quad, Shape, Color, Canvas};
+use nalgebra::base::{Matrix4, Vector4};
+use std::time::Instant;
+
+
+#[derive(Clone, Copy)]
+struct SoftVertex
+{
+	// Position in canvas pixels, top-down
+	x: f32,
+	y: f32,
+
+	// Texture coordinates
+	u: f32,
+	v: f32,
+}
+
+
+struct Sampler<'a>
+{
+	cnv: &'a Canvas,
+	repeat: bool,
+}
+
+impl<'a> Sampler<'a>
+{
+	fn sample(&self, u: f32, v: f32) -> [f32; 4]
+	{
+		// Nearest texel, like a texture created without smoothing
+		let (w, h) = self.cnv.size();
+		let (w, h) = (w as i32, h as i32);
+		let mut x = (u * w as f32).floor() as i32;
+		let mut y = (v * h as f32).floor() as i32;
+
+		if self.repeat
+		{
+			x = x.rem_euclid(w);
+			y = y.rem_euclid(h);
+		}
+		else
+		{
+			x = x.max(0).min(w-1);
+			y = y.max(0).min(h-1);
+		}
+
+		unpack(self.cnv.get_pixel(x, y).0)
+	}
+}
+
+
+fn unpack(col: u32) -> [f32; 4]
+{
+	// Same layout as the UByteNorm color attribute
+	let c = Color(col);
+	[c.r(), c.g(), c.b(), c.a()]
+}
+
+fn pack(c: [f32; 4]) -> Color
+{
+	// Round to the nearest 8 bit value, like a normalized framebuffer
+	let f = |v: f32| (v.clamp(0.0, 1.0) * 255.0).round() as u8;
+	Color::rgba8(f(c[0]), f(c[1]), f(c[2]), f(c[3]))
+}
+
+fn edge(a: &SoftVertex, b: &SoftVertex, x: f32, y: f32) -> f32
+{
+	(b.x-a.x)*(y-a.y) - (b.y-a.y)*(x-a.x)
+}
+
+fn owns_edge(a: &SoftVertex, b: &SoftVertex) -> bool
+{
+	// Pixels exactly on an edge belong to only one of the two triangles sharing it
+	let dy = b.y-a.y;
+	dy>0.0 || (dy==0.0 && b.x>a.x)
+}
+
+
+fn raster_triangle(target: &mut Canvas, tri: [SoftVertex; 3], col: [f32; 4], tex: Option<&Sampler>)
+{
+	// Orient the triangle so the inside has positive edge values, there's no face culling
+	let mut t = tri;
+	let mut area = edge(&t[0], &t[1], t[2].x, t[2].y);
+
+	if area==0.0
+		{ return; }
+
+	if area<0.0
+	{
+		t.swap(1, 2);
+		area = -area;
+	}
+
+	// Bounding box, clipped to the canvas
+	let (cw, ch) = target.size();
+	let x1 = t.iter().fold(f32::MAX, |m, v| m.min(v.x)).floor().max(0.0) as i32;
+	let y1 = t.iter().fold(f32::MAX, |m, v| m.min(v.y)).floor().max(0.0) as i32;
+	let x2 = t.iter().fold(f32::MIN, |m, v| m.max(v.x)).ceil().min(cw as f32) as i32;
+	let y2 = t.iter().fold(f32::MIN, |m, v| m.max(v.y)).ceil().min(ch as f32) as i32;
+
+	let owns = [owns_edge(&t[1], &t[2]), owns_edge(&t[2], &t[0]), owns_edge(&t[0], &t[1])];
+
+	for y in y1..y2
+	{
+		for x in x1..x2
+		{
+			// Sample at the pixel center
+			let px = x as f32 + 0.5;
+			let py = y as f32 + 0.5;
+
+			let w = [edge(&t[1], &t[2], px, py), edge(&t[2], &t[0], px, py), edge(&t[0], &t[1], px, py)];
+
+			if (0..3).any(|i| w[i]<0.0 || (w[i]==0.0 && !owns[i]))
+				{ continue; }
+
+			// Fragment color, the texture modulated by the vertex color
+			let src = match tex
+				{
+					Some (smp) =>
+						{
+							let u = (w[0]*t[0].u + w[1]*t[1].u + w[2]*t[2].u) / area;
+							let v = (w[0]*t[0].v + w[1]*t[1].v + w[2]*t[2].v) / area;
+							let s = smp.sample(u, v);
+
+							[s[0]*col[0], s[1]*col[1], s[2]*col[2], s[3]*col[3]]
+						},
+
+					None => col,
+				};
+
+			// Blend with SRC_ALPHA, ONE_MINUS_SRC_ALPHA, the alpha channel included
+			let dst = unpack(target.get_pixel(x, y).0);
+			let a = src[3];
+			let out = [
+					src[0]*a + dst[0]*(1.0-a),
+					src[1]*a + dst[1]*(1.0-a),
+					src[2]*a + dst[2]*(1.0-a),
+					src[3]*a + dst[3]*(1.0-a),
+				];
+
+			target.set_pixel(x, y, pack(out));
+		}
+	}
+}
+
+//------------------------------------------------------------------------------------------------------------------------
+//------------------------------------------------------------------------------------------------------------------------
+//------------------------------------------------------------------------------------------------------------------------
+
+enum SoftPrim
+{
+	Quad (Quad),
+	Shape (Shape),
+}
+
+
+pub struct SoftRenderer
+{
+	target: Canvas,
+	prims: Vec<SoftPrim>,
+	snap: PixelSnap,
+}
+
+impl SoftRenderer
+{
+	pub fn new(w: u32, h: u32) -> SoftRenderer
+	{
+		// Create a renderer drawing into a transparent canvas
+		SoftRenderer
+		{
+			target: Canvas::new(w, h, Color(0)),
+			prims: Vec::new(),
+			snap: PixelSnap::None,
+		}
+	}
+
+	pub fn size(&self) -> (u32, u32)
+	{
+		self.target.size()
+	}
+
+	pub fn clear(&mut self, col: Color)
+	{
+		self.target.clear(col);
+	}
+
+	pub fn canvas(&self) -> &Canvas
+	{
+		// The rendered image
+		&self.target
+	}
+
+	pub fn into_canvas(self) -> Canvas
+	{
+		self.target
+	}
+
+	fn project(&self, proj_mat: &Matrix4<f32>, x: f32, y: f32) -> (f32, f32)
+	{
+		// World coordinates to canvas pixels, through normalized device coordinates
+		let p = proj_mat * Vector4::new(x, y, 0.0, 1.0);
+		let (w, h) = self.target.size();
+
+		((p.x/p.w + 1.0) * 0.5 * w as f32, (1.0 - p.y/p.w) * 0.5 * h as f32)
+	}
+
+	fn quad_vertex(&self, proj_mat: &Matrix4<f32>, quad: &Quad, x: f32, y: f32, u: f32, v: f32) -> SoftVertex
+	{
+		let (wx, wy) = quad.transform(x, y);
+		let (x, y) = self.project(proj_mat, wx, wy);
+
+		SoftVertex { x, y, u, v }
+	}
+
+	fn draw_quad(&mut self, proj_mat: &Matrix4<f32>, quad: &Quad)
+	{
+		// Same corners and UVs as the vertex buffer path, UVs within the texture rather than its atlas
+		let (u1, v1, u2, v2) = quad.calc_uv_in((0.0, 0.0, 1.0, 1.0));
+		let (w, h) = (quad.w, quad.h);
+
+		let a = self.quad_vertex(proj_mat, quad, 0.0, 0.0, u1, v1);
+		let b = self.quad_vertex(proj_mat, quad, w, 0.0, u2, v1);
+		let c = self.quad_vertex(proj_mat, quad, 0.0, h, u1, v2);
+		let d = self.quad_vertex(proj_mat, quad, w, h, u2, v2);
+
+		let col = unpack(quad.col);
+		let repeat = quad.tex.is_raw();
+		let target = &mut self.target;
+
+		let drawn = quad.tex.with_pixels(|cnv|
+			{
+				let smp = Sampler { cnv, repeat };
+
+				raster_triangle(target, [a, b, c], col, Some(&smp));
+				raster_triangle(target, [b, d, c], col, Some(&smp));
+			});
+
+		if drawn.is_none()
+			{ panic!("SoftRenderer.render(): OpenGL textures can't be drawn, use software or atlas textures"); }
+	}
+
+	fn draw_shape(&mut self, proj_mat: &Matrix4<f32>, shape: &Shape)
+	{
+		// Shapes are plain colored triangles
+		let col = unpack(shape.col);
+
+		for tri in shape.points.chunks(3)
+		{
+			if tri.len()<3
+				{ break; }
+
+			let mut v = [SoftVertex { x: 0.0, y: 0.0, u: 0.0, v: 0.0 }; 3];
+
+			for i in 0..3
+			{
+				let (x, y) = self.project(proj_mat, tri[i].0, tri[i].1);
+				v[i].x = x;
+				v[i].y = y;
+			}
+
+			raster_triangle(&mut self.target, v, col, None);
+		}
+	}
+}
+
+impl QuadRenderer for SoftRenderer
+{
+	fn add_quad(&mut self, quad: Quad)
+	{
+		// Add a quad to the queue
+		self.prims.push(SoftPrim::Quad(quad));
+	}
+
+	fn add_shape(&mut self, shape: Shape)
+	{
+		// Add a shape to the queue
+		if !shape.points.is_empty()
+			{ self.prims.push(SoftPrim::Shape(shape)); }
+	}
+
+	fn render(&mut self, proj_mat: &Matrix4<f32>) -> RenderStats
+	{
+		// Rasterise the queued primitives into the canvas, in order
+		let mut stats = RenderStats::default();
+		let start = Instant::now();
+		let prims = std::mem::take(&mut self.prims);
+
+		for prim in prims.iter()
+		{
+			match *prim
+			{
+				SoftPrim::Quad (ref quad) =>
+					{
+						self.draw_quad(proj_mat, quad);
+						stats.quads += 1;
+						stats.vertices += 6;
+					},
+
+				SoftPrim::Shape (ref shape) =>
+					{
+						self.draw_shape(proj_mat, shape);
+						stats.shapes += 1;
+						stats.vertices += shape.points.len();
+					},
+			}
+		}
+
+		stats.draw_time = start.elapsed();
+
+		stats
+	}
+
+	fn pixel_snap(&self) -> PixelSnap
+	{
+		self.snap
+	}
+
+	fn set_pixel_snap(&mut self, snap: PixelSnap)
+	{
+		self.snap = snap;
+	}
+}
+
+
+//------------------------------------------------------------------------------------------------------------------------
+//------------------------------------------------------------------------------------------------------------------------
+//------------------------------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+	use super::super::{Texture, NineSlice};
+	use std::rc::Rc;
+
+	const BG: Color = Color(0xFF000000);
+
+	fn render(rend: &mut SoftRenderer)
+	{
+		let (w, h) = rend.size();
+		rend.render(&Matrix4::new_orthographic(0.0, w as f32, h as f32, 0.0, -1.0, 1.0));
+	}
+
+	fn solid(w: u32, h: u32, col: Color) -> Rc<Texture>
+	{
+		Rc::new(Texture::soft(Canvas::new(w, h, col)))
+	}
+
+	#[test]
+	fn quad()
+	{
+		let red = Color::rgb(1.0, 0.0, 0.0);
+		let mut rend = SoftRenderer::new(8, 8);
+		rend.clear(BG);
+
+		rend.add_quad(Quad::new(&solid(4, 4, red)).with_pos(2.0, 3.0));
+		render(&mut rend);
+
+		let cnv = rend.canvas();
+		assert_eq!(cnv.get_pixel(2, 3).0, red.0);
+		assert_eq!(cnv.get_pixel(5, 6).0, red.0);
+		assert_eq!(cnv.get_pixel(1, 3).0, BG.0);
+		assert_eq!(cnv.get_pixel(6, 6).0, BG.0);
+		assert_eq!(cnv.get_pixel(2, 7).0, BG.0);
+	}
+
+	#[test]
+	fn tinted_quad()
+	{
+		let mut rend = SoftRenderer::new(4, 4);
+		rend.clear(BG);
+
+		rend.add_quad(Quad::new(&solid(4, 4, Color::rgb(1.0, 1.0, 1.0))).with_color(Color::rgb(0.0, 1.0, 0.0)));
+		render(&mut rend);
+
+		assert_eq!(rend.canvas().get_pixel(1, 1).0, Color::rgb(0.0, 1.0, 0.0).0);
+	}
+
+	#[test]
+	fn shape()
+	{
+		let blue = Color::rgb(0.0, 0.0, 1.0);
+		let mut rend = SoftRenderer::new(8, 8);
+		rend.clear(BG);
+
+		rend.add_shape(Shape::rect(1.0, 1.0, 3.0, 2.0).with_color(blue));
+		render(&mut rend);
+
+		let cnv = rend.canvas();
+		assert_eq!(cnv.get_pixel(1, 1).0, blue.0);
+		assert_eq!(cnv.get_pixel(3, 2).0, blue.0);
+		assert_eq!(cnv.get_pixel(4, 1).0, BG.0);
+		assert_eq!(cnv.get_pixel(1, 3).0, BG.0);
+		assert_eq!(cnv.get_pixel(0, 0).0, BG.0);
+	}
+
+	#[test]
+	fn nine_slice()
+	{
+		// A 6x6 texture with a 2 texel red border around a green center
+		let (red, green) = (Color::rgb(1.0, 0.0, 0.0), Color::rgb(0.0, 1.0, 0.0));
+		let mut cnv = Canvas::new(6, 6, red);
+
+		for y in 2..4
+		{
+			for x in 2..4
+				{ cnv.set_pixel(x, y, green); }
+		}
+
+		let tex = Rc::new(Texture::soft(cnv));
+
+		// Stretched to 12x10, the borders keep their width
+		let mut rend = SoftRenderer::new(16, 16);
+		rend.clear(BG);
+
+		rend.add_nine_slice(&NineSlice::new(&tex, 2.0, 3.0, 12, 10, (2, 2, 2, 2)));
+		render(&mut rend);
+
+		let cnv = rend.canvas();
+		assert_eq!(cnv.get_pixel(2, 3).0, red.0);
+		assert_eq!(cnv.get_pixel(3, 4).0, red.0);
+		assert_eq!(cnv.get_pixel(8, 3).0, red.0);
+		assert_eq!(cnv.get_pixel(13, 12).0, red.0);
+		assert_eq!(cnv.get_pixel(4, 5).0, green.0);
+		assert_eq!(cnv.get_pixel(8, 8).0, green.0);
+		assert_eq!(cnv.get_pixel(11, 10).0, green.0);
+		assert_eq!(cnv.get_pixel(1, 3).0, BG.0);
+		assert_eq!(cnv.get_pixel(14, 8).0, BG.0);
+		assert_eq!(cnv.get_pixel(8, 13).0, BG.0);
+	}
+}
@@ -48,11 +48,11 @@ impl SpriteSheet
 		for f in json["frames"].members()
 		{
 			//let file = f["filename"].as_str().unwrap();
-			let x = *&f["frame"]["x"].as_u32().unwrap();
-			let y = *&f["frame"]["y"].as_u32().unwrap();
-			let w = *&f["frame"]["w"].as_u32().unwrap();
-			let h = *&f["frame"]["h"].as_u32().unwrap();
-			let dur = *&f["duration"].as_u32().unwrap();
+			let x = f["frame"]["x"].as_u32().unwrap();
+			let y = f["frame"]["y"].as_u32().unwrap();
+			let w = f["frame"]["w"].as_u32().unwrap();
+			let h = f["frame"]["h"].as_u32().unwrap();
+			let dur = f["duration"].as_u32().unwrap();
 
 			// Create a sub-canvas and an atlas texture entry
 			let sub = cnv.sub(x, y, w, h);
@@ -251,11 +251,7 @@ impl Sprite
 	pub fn next_tag(&self) -> Option<String>
 	{
 		// Queued next tag
-		match &self.next_tag
-		{
-			Some (s) => Some(s.clone()),
-			None => None,
-		}
+		self.next_tag.clone()
 	}
 
 	pub fn rolled(&self) -> bool
@@ -282,6 +278,14 @@ impl SpriteSystem
 	}
 }
 
+impl Default for SpriteSystem
+{
+	fn default() -> SpriteSystem
+	{
+		SpriteSystem::new()
+	}
+}
+
 impl System for SpriteSystem
 {
 	fn run(&mut self, world: &World)
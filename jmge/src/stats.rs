@@ -0,0 +1,151 @@
+use super::backend::with_backend;
+
+use std::fmt;
+use std::time::Duration;
+
+
+#[derive(Clone, Copy, Default)]
+pub struct RenderStats
+{
+	// Primitives submitted, after culling
+	pub quads: usize,
+	pub shapes: usize,
+
+	// Primitives skipped by culling
+	pub culled: usize,
+
+	// Data uploaded
+	pub vertices: usize,
+	pub instances: usize,
+
+	// Draw calls and texture binds issued
+	pub draw_calls: usize,
+	pub texture_binds: usize,
+
+	// Batch breaks, by reason
+	pub texture_breaks: usize,
+	pub path_breaks: usize,
+
+	// CPU time spent building the buffers and issuing the draws
+	pub write_time: Duration,
+	pub draw_time: Duration,
+
+	// GPU time spent drawing, from a few frames ago, when timer queries are available
+	pub gpu_time: Option<Duration>,
+}
+
+impl fmt::Display for RenderStats
+{
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+	{
+		let gpu = match self.gpu_time
+			{
+				Some (t) => format!("{:.2}ms", t.as_secs_f64()*1000.0),
+				None => String::from("n/a"),
+			};
+
+		write!(f, "quads: {}, shapes: {}, culled: {}, vertices: {}, instances: {}, draws: {}, binds: {}, breaks: {} texture / {} path, write: {:.2}ms, draw: {:.2}ms, gpu: {}",
+			self.quads, self.shapes, self.culled, self.vertices, self.instances,
+			self.draw_calls, self.texture_binds, self.texture_breaks, self.path_breaks,
+			self.write_time.as_secs_f64()*1000.0, self.draw_time.as_secs_f64()*1000.0, gpu)
+	}
+}
+
+//------------------------------------------------------------------------------------------------------------------------
+//------------------------------------------------------------------------------------------------------------------------
+//------------------------------------------------------------------------------------------------------------------------
+
+const NUM_QUERIES: usize = 3;
+
+
+pub struct GpuTimer
+{
+	timers: [u32; NUM_QUERIES],
+	pending: [bool; NUM_QUERIES],
+	pos: usize,
+	active: bool,
+	last: Option<Duration>,
+}
+
+impl GpuTimer
+{
+	pub fn new() -> Option<GpuTimer>
+	{
+		// A few timers are used in turn, so their results can be collected frames later
+		let mut timers = [0; NUM_QUERIES];
+
+		for i in 0..NUM_QUERIES
+		{
+			match with_backend(|b| b.create_timer())
+			{
+				Some (timer) => timers[i] = timer,
+				None =>
+					{
+						with_backend(|b| timers[..i].iter().for_each(|t| b.delete_timer(*t)));
+						return None;
+					},
+			}
+		}
+
+		Some(GpuTimer
+		{
+			timers,
+			pending: [false; NUM_QUERIES],
+			pos: 0,
+			active: false,
+			last: None,
+		})
+	}
+
+	pub fn begin(&mut self)
+	{
+		// Collect the result of the oldest timer first, if it's ready
+		// Waiting on it would stall the pipeline, so it's just skipped otherwise
+		let timer = self.timers[self.pos];
+
+		if self.pending[self.pos]
+		{
+			match with_backend(|b| b.timer_result(timer))
+			{
+				Some (t) => self.last = Some(t),
+				None => return,
+			}
+
+			self.pending[self.pos] = false;
+		}
+
+		// Start timing
+		with_backend(|b| b.begin_timer(timer));
+		self.pending[self.pos] = true;
+		self.active = true;
+	}
+
+	pub fn end(&mut self)
+	{
+		// Stop timing, if begin() started a timer
+		if !self.active
+			{ return; }
+
+		let timer = self.timers[self.pos];
+		with_backend(|b| b.end_timer(timer));
+
+		self.pos = (self.pos+1) % NUM_QUERIES;
+		self.active = false;
+	}
+
+	pub fn last(&self) -> Option<Duration>
+	{
+		// Most recently collected GPU time
+		self.last
+	}
+}
+
+impl Drop for GpuTimer
+{
+	fn drop(&mut self)
+	{
+		let timers = self.timers;
+		with_backend(|b| timers.iter().for_each(|t| b.delete_timer(*t)));
+	}
+}
+
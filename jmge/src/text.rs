@@ -0,0 +1,63 @@
+
+use super::{Color, Font, Component};
+use std::rc::Rc;
+
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum TextAlign
+{
+	Left,
+	Center,
+	Right,
+}
+
+
+#[derive(Component)]
+pub struct Text
+{
+	// Font and string to render
+	pub font: Rc<Font>,
+	pub text: String,
+
+	// Position of the anchor point
+	pub x: f32,
+	pub y: f32,
+
+	// Color tint
+	pub color: Color,
+
+	// Visibility flag
+	pub visible: bool,
+
+	// Horizontal alignment around the anchor point
+	pub align: TextAlign,
+
+	// Scale
+	pub x_scale: f32,
+	pub y_scale: f32,
+
+	// Rotation angle, around the anchor point
+	pub angle: f32,
+}
+
+impl Text
+{
+	pub fn new(font: &Rc<Font>, text: &str, x: f32, y: f32) -> Text
+	{
+		// Create a new text with mostly default values
+		Text
+		{
+			font: Rc::clone(font),
+			text: String::from(text),
+			x,
+			y,
+			color: Color::rgb(1.0, 1.0, 1.0),
+			visible: true,
+			align: TextAlign::Left,
+			x_scale: 1.0,
+			y_scale: 1.0,
+			angle: 0.0,
+		}
+	}
+}
+
@@ -1,5 +1,6 @@
 
-use super::{Error, Canvas};
+use super::{Error, Canvas, Color};
+use super::backend::with_backend;
 use rect_packer::Packer;
 use std::rc::{Rc, Weak};
 use std::cell::RefCell;
@@ -9,6 +10,7 @@ pub enum Texture
 {
 	Raw (Rc<RefCell<RawTexture>>),
 	AtlasEntry (Rc<RefCell<AtlasEntry>>),
+	Soft (Rc<RefCell<Canvas>>),
 }
 
 impl Texture
@@ -19,11 +21,23 @@ impl Texture
 		Texture::Raw(Rc::new(RefCell::new(RawTexture::from_canvas(cnv, smooth))))
 	}
 
+	pub fn from_raw(raw: RawTexture) -> Texture
+	{
+		// Wrap an existing raw texture
+		Texture::Raw(Rc::new(RefCell::new(raw)))
+	}
+
 	pub fn from_file(fname: &str, smooth: bool) -> Result<Texture, Error>
 	{
 		Ok(Texture::Raw(Rc::new(RefCell::new(RawTexture::from_file(fname, smooth)?))))
 	}
 
+	pub fn soft(cnv: Canvas) -> Texture
+	{
+		// Texture living in memory only, for the software renderer
+		Texture::Soft(Rc::new(RefCell::new(cnv)))
+	}
+
 	pub fn size(&self) -> (u32, u32)
 	{
 		// Return the texture size
@@ -31,16 +45,31 @@ impl Texture
 		{
 			Texture::Raw (ref raw) => raw.borrow().size(),
 			Texture::AtlasEntry (ref entry) => entry.borrow().cnv.size(),
+			Texture::Soft (ref cnv) => cnv.borrow().size(),
 		}
 	}
 
 	fn base_raw_id(&self) -> u32
 	{
-		// Return the underlying raw texture
+		// Return the underlying raw texture, software textures don't have one
 		match *self
 		{
 			Texture::Raw (ref raw) => raw.borrow().id,
 			Texture::AtlasEntry (ref entry) => entry.borrow().raw_tex.id,
+			Texture::Soft (_) => 0,
+		}
+	}
+
+	pub fn with_pixels<R, F>(&self, f: F) -> Option<R>
+	where
+		F: FnOnce(&Canvas) -> R
+	{
+		// Access the pixels of the texture, if they're kept in memory
+		match *self
+		{
+			Texture::Raw (_) => None,
+			Texture::AtlasEntry (ref entry) => Some(f(&entry.borrow().cnv)),
+			Texture::Soft (ref cnv) => Some(f(&cnv.borrow())),
 		}
 	}
 	
@@ -49,24 +78,66 @@ impl Texture
 		// Return the UV values
 		match *self
 		{
-			Texture::Raw (_) => (0.0, 0.0, 1.0, 1.0),
+			Texture::Raw (_) | Texture::Soft (_) => (0.0, 0.0, 1.0, 1.0),
 			Texture::AtlasEntry (ref entry) => entry.borrow().uv,
 		}
 	}
 
+	pub fn is_raw(&self) -> bool
+	{
+		// Check if this is a standalone texture rather than an atlas entry
+		match *self
+		{
+			Texture::Raw (_) | Texture::Soft (_) => true,
+			Texture::AtlasEntry (_) => false,
+		}
+	}
+
+	pub fn is_soft(&self) -> bool
+	{
+		// Check if this is a software texture
+		matches!(*self, Texture::Soft (_))
+	}
+
 	pub fn is_same(&self, other: &Texture) -> bool
 	{
 		// Check if both textures have the same underlying raw texture
-		self.base_raw_id() == other.base_raw_id()
+		match (self, other)
+		{
+			(Texture::Soft (a), Texture::Soft (b)) => Rc::ptr_eq(a, b),
+			(Texture::Soft (_), _) | (_, Texture::Soft (_)) => false,
+			_ => self.base_raw_id() == other.base_raw_id(),
+		}
 	}
 
 	pub fn enable(&self)
 	{
-		// Enable the underyling raw texture
+		// Enable the underyling raw texture on the first unit
+		self.enable_on(0);
+	}
+
+	pub fn enable_on(&self, unit: u32)
+	{
+		// Enable the underyling raw texture on the given unit
 		match *self
 		{
-			Texture::Raw (ref raw) => raw.borrow().enable(),
-			Texture::AtlasEntry (ref entry) => entry.borrow().raw_tex.enable(),
+			Texture::Raw (ref raw) => raw.borrow().enable_on(unit),
+			Texture::AtlasEntry (ref entry) => entry.borrow().raw_tex.enable_on(unit),
+			Texture::Soft (_) => panic!("Texture.enable(): Software textures can't be used with OpenGL"),
+		}
+	}
+
+	pub fn enable_normals(&self, unit: u32) -> bool
+	{
+		// Enable the normal map paired with this texture, if there is one
+		match *self
+		{
+			Texture::Raw (_) | Texture::Soft (_) => false,
+			Texture::AtlasEntry (ref entry) => match entry.borrow().normal_tex
+				{
+					Some (ref tex) => { tex.enable_on(unit); true },
+					None => false,
+				},
 		}
 	}
 
@@ -77,6 +148,18 @@ impl Texture
 		{
 			Texture::Raw (ref raw) => raw.borrow_mut().update(cnv),
 			Texture::AtlasEntry (_) => panic!("Texture.update(): Entries in texture a texture atlas cannot be updated"),
+			Texture::Soft (ref soft) => *soft.borrow_mut() = Canvas::from_raw(cnv.width(), cnv.height(), cnv.data().clone()),
+		}
+	}
+
+	pub fn set_repeat(&self, repeat: bool)
+	{
+		// Change the wrapping mode
+		match *self
+		{
+			Texture::Raw (ref raw) => raw.borrow().set_repeat(repeat),
+			Texture::AtlasEntry (_) => panic!("Texture.set_repeat(): Entries in a texture atlas cannot repeat"),
+			Texture::Soft (_) => {},		// Software textures always repeat
 		}
 	}
 }
@@ -93,24 +176,8 @@ impl RawTexture
 {
 	pub fn new(w: u32, h: u32, smooth: bool) -> RawTexture
 	{
-		// Create an OpenGL texture
-		let mut id = 0;
-
-		unsafe
-		{
-			// Create the texture
-			gl::GenTextures(1, &mut id);
-
-			// Bind it and set its parameters
-			gl::BindTexture(gl::TEXTURE_2D, id);
-
-			let v = if smooth { gl::LINEAR } else { gl::NEAREST } as i32;
-			gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, v);
-			gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, v);
-			
-			// Create the store
-			gl::TexImage2D(gl::TEXTURE_2D, 0, gl::RGBA as i32, w as i32, h as i32, 0, gl::RGBA, gl::UNSIGNED_BYTE, std::ptr::null());
-		}
+		// Create a texture with undefined content
+		let id = with_backend(|b| b.create_texture(w, h, smooth, None));
 
 		RawTexture
 		{
@@ -122,20 +189,16 @@ impl RawTexture
 
 	pub fn from_canvas(cnv: &Canvas, smooth: bool) -> RawTexture
 	{
-		// Get the size
+		// Create the texture along with its data
 		let (w, h) = cnv.size();
+		let id = with_backend(|b| b.create_texture(w, h, smooth, Some(cnv.data())));
 
-		// Create the OpenGL texture
-		let tex = RawTexture::new(w, h, smooth);
-
-		// Upload the data to it
-		unsafe
+		RawTexture
 		{
-			let ptr = cnv.data().as_ptr();
-			gl::TexImage2D(gl::TEXTURE_2D, 0, gl::RGBA as i32, w as i32, h as i32, 0, gl::RGBA, gl::UNSIGNED_BYTE, ptr as *const std::os::raw::c_void);
+			id,
+			w,
+			h,
 		}
-
-		tex
 	}
 
 	pub fn from_file(fname: &str, smooth: bool) -> Result<RawTexture, Error>
@@ -151,28 +214,34 @@ impl RawTexture
 		(self.w, self.h)
 	}
 
+	pub fn id(&self) -> u32
+	{
+		self.id
+	}
+
 	pub fn enable(&self)
 	{
-		// Bind the texture
-		unsafe
-		{
-			gl::BindTexture(gl::TEXTURE_2D, self.id);
-		}
+		// Bind the texture on the first unit
+		self.enable_on(0);
 	}
 
-	pub fn update(&mut self, cnv: &Canvas)
+	pub fn enable_on(&self, unit: u32)
 	{
-		// Bind the texture
-		self.enable();
+		// Bind the texture on the given unit
+		with_backend(|b| b.bind_texture(unit, self.id));
+	}
+
+	pub fn set_repeat(&self, repeat: bool)
+	{
+		// Repeat or clamp the UV coordinates
+		with_backend(|b| b.set_texture_repeat(self.id, repeat));
+	}
 
+	pub fn update(&mut self, cnv: &Canvas)
+	{
 		// Update the data
 		let (w, h) = cnv.size();
-		
-		unsafe
-		{
-			let ptr = cnv.data().as_ptr();
-			gl::TexImage2D(gl::TEXTURE_2D, 0, gl::RGBA as i32, w as i32, h as i32, 0, gl::RGBA, gl::UNSIGNED_BYTE, ptr as *const std::os::raw::c_void);
-		}
+		with_backend(|b| b.replace_texture(self.id, w, h, cnv.data()));
 
 		self.w = w;
 		self.h = h;
@@ -184,10 +253,8 @@ impl Drop for RawTexture
 	fn drop(&mut self)
 	{
 		// Delete the texture
-		unsafe
-		{
-			gl::DeleteTextures(1, &self.id);
-		}
+		let id = self.id;
+		with_backend(|b| b.delete_texture(id));
 	}
 }
 
@@ -198,7 +265,9 @@ impl Drop for RawTexture
 pub struct AtlasEntry
 {
 	cnv: Canvas,
+	normal: Option<Canvas>,
 	raw_tex: Rc<RawTexture>,
+	normal_tex: Option<Rc<RawTexture>>,
 	x: i32,
 	y: i32,
 	uv: (f32, f32, f32, f32),
@@ -207,6 +276,7 @@ pub struct AtlasEntry
 pub struct TextureAtlas
 {
 	tex: Rc<RawTexture>,
+	normal_tex: Option<Rc<RawTexture>>,
 	size: u32,
 	packer: Packer,
 	entries: Vec<Weak<RefCell<AtlasEntry>>>,
@@ -226,6 +296,14 @@ fn create_packer(size: u32) -> Packer
 	Packer::new(config)
 }
 
+fn upload_canvas(tex: &RawTexture, x: i32, y: i32, cnv: &Canvas)
+{
+	// Upload a canvas into part of a texture
+	let (w, h) = cnv.size();
+
+	with_backend(|b| b.update_texture(tex.id, x, y, w, h, cnv.data()));
+}
+
 impl TextureAtlas
 {
 	pub fn new(size: u32, smooth: bool) -> TextureAtlas
@@ -237,20 +315,44 @@ impl TextureAtlas
 		TextureAtlas
 		{
 			tex: raw_tex,
+			normal_tex: None,
 			size,
 			packer : create_packer(size),
 			entries: Vec::new(),
 		}
 	}
 
+	pub fn new_with_normals(size: u32, smooth: bool) -> TextureAtlas
+	{
+		// Create an atlas with a companion texture holding the normal maps of its entries
+		let mut atlas = TextureAtlas::new(size, smooth);
+		atlas.normal_tex = Some(Rc::new(RawTexture::new(size, size, smooth)));
+
+		atlas
+	}
+
+	pub fn has_normals(&self) -> bool
+	{
+		self.normal_tex.is_some()
+	}
+
 	fn upload_data(&self, entry: &AtlasEntry)
 	{
 		// Upload the entry's canvas
-		let (w, h) = entry.cnv.size();
+		upload_canvas(&self.tex, entry.x, entry.y, &entry.cnv);
 
-		unsafe
+		// And its normal map, entries without one get a flat normal
+		if let Some(ref ntex) = self.normal_tex
 		{
-			gl::TexSubImage2D(gl::TEXTURE_2D, 0, entry.x, entry.y, w as i32, h as i32, gl::RGBA, gl::UNSIGNED_BYTE, entry.cnv.data().as_ptr() as *const std::os::raw::c_void);
+			match entry.normal
+			{
+				Some (ref normal) => upload_canvas(ntex, entry.x, entry.y, normal),
+				None =>
+					{
+						let (w, h) = entry.cnv.size();
+						upload_canvas(ntex, entry.x, entry.y, &Canvas::new(w, h, Color::rgb8(128, 128, 255)));
+					},
+			}
 		}
 	}
 
@@ -268,6 +370,23 @@ impl TextureAtlas
 	}
 
 	pub fn add(&mut self, cnv: Canvas) -> Result<Texture, Error>
+	{
+		self.add_entry(cnv, None)
+	}
+
+	pub fn add_with_normal(&mut self, cnv: Canvas, normal: Canvas) -> Result<Texture, Error>
+	{
+		// Add a canvas along with its normal map
+		if !self.has_normals()
+			{ panic!("TextureAtlas.add_with_normal(): the atlas wasn't created with normal maps"); }
+
+		if cnv.size()!=normal.size()
+			{ panic!("TextureAtlas.add_with_normal(): the normal map size doesn't match"); }
+
+		self.add_entry(cnv, Some(normal))
+	}
+
+	fn add_entry(&mut self, cnv: Canvas, normal: Option<Canvas>) -> Result<Texture, Error>
 	{
 		// Try to pack 
 		if let Some(rect) = self.packer.pack(cnv.width() as i32, cnv.height() as i32, false)
@@ -276,7 +395,9 @@ impl TextureAtlas
 			let mut entry = AtlasEntry
 				{
 					cnv,
+					normal,
 					raw_tex: Rc::clone(&self.tex),
+					normal_tex: self.normal_tex.as_ref().map(Rc::clone),
 					x: rect.x,
 					y: rect.y,
 					uv: (0.0, 0.0, 0.0, 0.0),
@@ -307,6 +428,9 @@ impl TextureAtlas
 		self.tex = Rc::new(RawTexture::new(size, size, smooth));
 		self.size = size;
 
+		if self.normal_tex.is_some()
+			{ self.normal_tex = Some(Rc::new(RawTexture::new(size, size, smooth))); }
+
 		// Re-create the packer
 		self.packer = create_packer(size);
 
@@ -325,6 +449,7 @@ impl TextureAtlas
 			{
 				// Adjust the entry
 				entry.raw_tex = Rc::clone(&self.tex);
+				entry.normal_tex = self.normal_tex.as_ref().map(Rc::clone);
 				entry.x = rect.x;
 				entry.y = rect.y;
 
@@ -1,8 +1,10 @@
 
-use super::{Texture, VertexBuffer, ShaderProgram, Error};
+use super::{Texture, VertexBuffer, VertexAttrib, AttribType, ShaderProgram, Error};
+use nalgebra::base::{Matrix4, Vector3};
 use std::rc::Rc;
 
 #[repr(C)]
+#[derive(Default)]
 struct Vertex
 {
 	pub x: f32,
@@ -12,6 +14,18 @@ struct Vertex
 	pub v: f32,
 }
 
+const VERTEX_ATTRIBS: [VertexAttrib; 3] =
+	[
+		// Position
+		VertexAttrib { count: 2, kind: AttribType::Float, offset: 0 },
+
+		// Color
+		VertexAttrib { count: 4, kind: AttribType::UByteNorm, offset: 8 },
+
+		// TexCoord
+		VertexAttrib { count: 2, kind: AttribType::Float, offset: 12 },
+	];
+
 
 pub struct TileMap
 {
@@ -20,6 +34,7 @@ pub struct TileMap
 	tw: u32,
 	th: u32,
 	tex: Rc<Texture>,
+	tiles: Vec<Option<u32>>,
 }
 
 impl TileMap
@@ -44,6 +59,7 @@ impl TileMap
 			tw,
 			th,
 			tex: Rc::clone(tex),
+			tiles: vec![None; (w*h) as usize],
 		}
 	}
 
@@ -61,6 +77,52 @@ impl TileMap
 	{
 		Rc::clone(&self.tex)
 	}
+
+	pub fn tile_count(&self) -> u32
+	{
+		// Number of tiles in the tileset
+		let (tsw, tsh) = self.tex.size();
+		(tsw/self.tw) * (tsh/self.th)
+	}
+
+	pub fn set_tile(&mut self, x: u32, y: u32, tile: Option<u32>)
+	{
+		// Tiles are numbered left to right, then top to bottom in the tileset
+		if x>=self.w || y>=self.h
+			{ panic!("TileMap.set_tile(): position outside the map"); }
+
+		if let Some(t) = tile
+		{
+			if t>=self.tile_count()
+				{ panic!("TileMap.set_tile(): tile not in the tileset"); }
+		}
+
+		self.tiles[(y*self.w+x) as usize] = tile;
+	}
+
+	pub fn tile(&self, x: u32, y: u32) -> Option<u32>
+	{
+		// Tile at a position, None for empty cells and outside the map
+		if x>=self.w || y>=self.h
+			{ return None; }
+
+		self.tiles[(y*self.w+x) as usize]
+	}
+
+	fn tile_uv(&self, tile: u32) -> (f32, f32, f32, f32)
+	{
+		// UV rectangle of a tile, within the texture's own UV rectangle
+		let (u1, v1, u2, v2) = self.tex.uv();
+		let (tsw, tsh) = self.tex.size();
+		let cols = tsw/self.tw;
+
+		let du = (u2-u1) * self.tw as f32 / tsw as f32;
+		let dv = (v2-v1) * self.th as f32 / tsh as f32;
+		let u = u1 + (tile%cols) as f32 * du;
+		let v = v1 + (tile/cols) as f32 * dv;
+
+		(u, v, u+du, v+dv)
+	}
 }
 
 //------------------------------------------------------------------------------------------------------------------------
@@ -83,7 +145,7 @@ impl TileMapRenderer
 			include_str!("../shaders/tilemap.frag")
 		)?;
 
-		let vb = VertexBuffer::new();
+		let vb = VertexBuffer::new(&VERTEX_ATTRIBS);
 
 		Ok(TileMapRenderer
 		{
@@ -91,8 +153,52 @@ impl TileMapRenderer
 			vb,
 		})
 	}
-}
 
+	pub fn render(&mut self, map: &TileMap, x: f32, y: f32, proj_mat: &Matrix4<f32>)
+	{
+		// Draw the map with its top left corner at the given position
+		let count = map.tiles.iter().filter(|t| t.is_some()).count();
 
+		if count==0
+			{ return; }
 
+		{
+			// Two triangles per tile, in map coordinates
+			let mut v = self.vb.map(count*6);
+			let (tw, th) = (map.tw as f32, map.th as f32);
+			let mut pos = 0;
+
+			for (i, tile) in map.tiles.iter().enumerate()
+			{
+				if let Some(t) = *tile
+				{
+					let x1 = (i as u32 % map.w) as f32 * tw;
+					let y1 = (i as u32 / map.w) as f32 * th;
+					let (x2, y2) = (x1+tw, y1+th);
+					let (u1, v1, u2, v2) = map.tile_uv(t);
+
+					let corners = [(x1, y1, u1, v1), (x2, y1, u2, v1), (x1, y2, u1, v2), (x2, y1, u2, v1), (x2, y2, u2, v2), (x1, y2, u1, v2)];
+
+					for (vtx, &(cx, cy, cu, cv)) in v[pos..pos+6].iter_mut().zip(corners.iter())
+					{
+						vtx.x = cx;
+						vtx.y = cy;
+						vtx.col = 0xFFFFFFFF;
+						vtx.u = cu;
+						vtx.v = cv;
+					}
+
+					pos += 6;
+				}
+			}
+		}
 
+		// Draw them all at once, the tiles share the tileset texture
+		self.shader.enable();
+		self.shader.set_uniform_matrix("Projection", proj_mat);
+		self.shader.set_uniform_matrix("Transform", &Matrix4::new_translation(&Vector3::new(x, y, 0.0)));
+		map.tex.enable();
+
+		self.vb.draw_triangles(0, (count*6) as u32);
+	}
+}
@@ -0,0 +1,269 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+
+pub struct FrameTimer
+{
+	start: Instant,
+	last: Instant,
+	delta: f32,
+	frames: u64,
+
+	// Exponential moving average of the frame time, in seconds
+	smooth: f32,
+	smoothing: f32,
+
+	// Recent frame times in ms, oldest first
+	times: VecDeque<f32>,
+	window: usize,
+
+	// Frame rate cap, only enforced by limit()
+	cap: Option<f32>,
+}
+
+impl FrameTimer
+{
+	pub fn new() -> FrameTimer
+	{
+		// Keep the last 2 seconds or so at 60 fps
+		let now = Instant::now();
+
+		FrameTimer
+		{
+			start: now,
+			last: now,
+			delta: 0.0,
+			frames: 0,
+			smooth: 0.0,
+			smoothing: 0.1,
+			times: VecDeque::with_capacity(120),
+			window: 120,
+			cap: None,
+		}
+	}
+
+	pub fn tick(&mut self) -> f32
+	{
+		// Call once per frame, returns the time since the previous call in seconds
+		let now = Instant::now();
+		let dt = now.duration_since(self.last).as_secs_f32();
+		self.last = now;
+
+		self.push_delta(dt);
+
+		self.delta
+	}
+
+	pub(crate) fn push_delta(&mut self, dt: f32)
+	{
+		// Account for a frame that took dt seconds
+		self.delta = dt;
+		self.frames += 1;
+
+		// The first delta only measures the setup time
+		if self.frames==1
+			{ return; }
+
+		self.smooth = if self.smooth==0.0 { self.delta } else { self.smooth + (self.delta-self.smooth)*self.smoothing };
+
+		if self.times.len()==self.window
+			{ self.times.pop_front(); }
+
+		self.times.push_back(self.delta * 1000.0);
+	}
+
+	pub fn limit(&self)
+	{
+		// Sleep until the capped frame time has passed since the last tick
+		let fps = match self.cap
+			{
+				Some (fps) if fps>0.0 => fps,
+				_ => return,
+			};
+
+		let target = Duration::from_secs_f32(1.0 / fps);
+		let elapsed = self.last.elapsed();
+
+		if elapsed<target
+			{ std::thread::sleep(target-elapsed); }
+	}
+
+	pub fn set_fps_cap(&mut self, cap: Option<f32>)
+	{
+		self.cap = cap;
+	}
+
+	pub fn fps_cap(&self) -> Option<f32>
+	{
+		self.cap
+	}
+
+	pub fn set_window(&mut self, frames: usize)
+	{
+		// Number of frames the statistics are taken over
+		self.window = frames.max(1);
+
+		while self.times.len()>self.window
+			{ self.times.pop_front(); }
+	}
+
+	pub fn set_smoothing(&mut self, factor: f32)
+	{
+		// Weight of the newest frame in the smoothed FPS, between 0 and 1
+		self.smoothing = factor.clamp(0.0, 1.0);
+	}
+
+	pub fn delta(&self) -> f32
+	{
+		self.delta
+	}
+
+	pub fn frames(&self) -> u64
+	{
+		self.frames
+	}
+
+	pub fn elapsed(&self) -> f32
+	{
+		// Seconds since the timer was created
+		self.start.elapsed().as_secs_f32()
+	}
+
+	pub fn fps(&self) -> f32
+	{
+		// Smoothed frames per second
+		if self.smooth>0.0 { 1.0 / self.smooth } else { 0.0 }
+	}
+
+	pub fn min_ms(&self) -> f32
+	{
+		self.times.iter().cloned().fold(None, |m: Option<f32>, t| Some(m.map_or(t, |m| m.min(t)))).unwrap_or(0.0)
+	}
+
+	pub fn max_ms(&self) -> f32
+	{
+		self.times.iter().cloned().fold(0.0, f32::max)
+	}
+
+	pub fn avg_ms(&self) -> f32
+	{
+		if self.times.is_empty()
+			{ return 0.0; }
+
+		self.times.iter().sum::<f32>() / self.times.len() as f32
+	}
+
+	pub fn percentile_ms(&self, p: f32) -> f32
+	{
+		// Frame time that p percent of the recent frames stay under, e.g. 99.0 for the 1% worst
+		if self.times.is_empty()
+			{ return 0.0; }
+
+		let mut sorted: Vec<f32> = self.times.iter().cloned().collect();
+		sorted.sort_by(f32::total_cmp);
+
+		let i = ((p / 100.0) * (sorted.len()-1) as f32).round() as usize;
+		sorted[i.min(sorted.len()-1)]
+	}
+}
+
+impl Default for FrameTimer
+{
+	fn default() -> FrameTimer
+	{
+		FrameTimer::new()
+	}
+}
+
+impl fmt::Display for FrameTimer
+{
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+	{
+		write!(f, "{:.1} fps, {:.2} ms avg, {:.2}/{:.2} ms min/max, {:.2} ms 99th", self.fps(), self.avg_ms(), self.min_ms(), self.max_ms(), self.percentile_ms(99.0))
+	}
+}
+
+//------------------------------------------------------------------------------------------------------------------------
+//------------------------------------------------------------------------------------------------------------------------
+//------------------------------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+
+	fn close(a: f32, b: f32) -> bool
+	{
+		(a-b).abs() < 0.001
+	}
+
+	fn timer_with(deltas: &[f32]) -> FrameTimer
+	{
+		let mut timer = FrameTimer::new();
+
+		for dt in deltas.iter()
+			{ timer.push_delta(*dt); }
+
+		timer
+	}
+
+	#[test]
+	fn first_frame_skipped()
+	{
+		let timer = timer_with(&[5.0, 0.010, 0.020]);
+
+		assert_eq!(timer.frames(), 3);
+		assert!(close(timer.delta(), 0.020));
+		assert!(close(timer.max_ms(), 20.0));
+		assert!(close(timer.avg_ms(), 15.0));
+	}
+
+	#[test]
+	fn statistics()
+	{
+		let timer = timer_with(&[0.0, 0.010, 0.030, 0.020, 0.040, 0.050]);
+
+		assert!(close(timer.min_ms(), 10.0));
+		assert!(close(timer.max_ms(), 50.0));
+		assert!(close(timer.avg_ms(), 30.0));
+		assert!(close(timer.percentile_ms(0.0), 10.0));
+		assert!(close(timer.percentile_ms(50.0), 30.0));
+		assert!(close(timer.percentile_ms(100.0), 50.0));
+	}
+
+	#[test]
+	fn window()
+	{
+		let mut timer = timer_with(&[0.0, 0.100, 0.010, 0.010]);
+		timer.set_window(2);
+		assert!(close(timer.max_ms(), 10.0));
+
+		timer.push_delta(0.030);
+		assert!(close(timer.avg_ms(), 20.0));
+	}
+
+	#[test]
+	fn smoothed_fps()
+	{
+		// Without smoothing the FPS follow the last frame
+		let mut timer = timer_with(&[0.0, 0.010]);
+		timer.set_smoothing(1.0);
+		timer.push_delta(0.020);
+		assert!(close(timer.fps(), 50.0));
+
+		// Constant frame times give their rate
+		let timer = timer_with(&[0.0, 0.025, 0.025, 0.025]);
+		assert!(close(timer.fps(), 40.0));
+	}
+
+	#[test]
+	fn empty()
+	{
+		let timer = FrameTimer::new();
+
+		assert_eq!(timer.fps(), 0.0);
+		assert_eq!(timer.avg_ms(), 0.0);
+		assert_eq!(timer.percentile_ms(99.0), 0.0);
+	}
+}
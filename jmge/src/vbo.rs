@@ -1,197 +1,130 @@
-
+use super::backend::with_backend;
+use super::Primitive;
 use std::ops::{Deref, DerefMut};
 
 
+#[derive(Clone, Copy)]
+pub enum AttribType
+{
+	Float,
+	UByteNorm,
+}
 
-pub struct MapGuard<T>
+#[derive(Clone, Copy)]
+pub struct VertexAttrib
 {
-	ptr: *mut T,
-	len: usize,
+	// Number of components
+	pub count: i32,
+
+	// Component type
+	pub kind: AttribType,
+
+	// Offset within the vertex structure
+	pub offset: usize,
 }
 
-impl<T> Drop for MapGuard<T>
+
+
+pub struct MapGuard<'a, T>
 {
-	fn drop(&mut self)
-	{
-		// Unmap and unbind
-		unsafe
-		{
-			gl::UnmapBuffer(gl::ARRAY_BUFFER);
-			gl::BindBuffer(gl::ARRAY_BUFFER, 0);
-		}
-	}
+	vb: &'a mut VertexBuffer<T>,
+	len: usize,
 }
 
-impl<T> MapGuard<T>
+impl<'a, T> Drop for MapGuard<'a, T>
 {
-	pub fn ptr(&self) -> *mut T
-	{
-		self.ptr
-	}
-/*
-	pub fn get(&self, index: usize) -> &mut T
+	fn drop(&mut self)
 	{
-		unsafe
-		{
-			let ptr = self.ptr.offset(index as isize);
-			let r: &mut T = &mut *ptr;
+		// Upload the written records
+		let bytes = unsafe
+			{
+				std::slice::from_raw_parts(self.vb.staging.as_ptr() as *const u8, self.len*std::mem::size_of::<T>())
+			};
 
-			r
-		}
+		let buf = self.vb.buf;
+		with_backend(|b| b.upload_buffer(buf, bytes));
 	}
-
-	pub fn slice(&self, start: usize, count: usize) -> &mut [T]
-	{
-		unsafe
-		{
-			let ptr = self.ptr.offset(start as isize);
-			std::slice::from_raw_parts_mut(ptr, count)
-		}
-	}*/
 }
 
-impl<T> Deref for MapGuard<T>
+impl<'a, T> Deref for MapGuard<'a, T>
 {
 	type Target = [T];
 
 	fn deref(&self) -> &[T]
 	{
-		unsafe
-		{
-			std::slice::from_raw_parts(self.ptr, self.len)
-		}
+		&self.vb.staging[..self.len]
 	}
 }
 
-impl<T> DerefMut for MapGuard<T>
+impl<'a, T> DerefMut for MapGuard<'a, T>
 {
 	fn deref_mut(&mut self) -> &mut [T]
 	{
-		unsafe
-		{
-			std::slice::from_raw_parts_mut(self.ptr, self.len)
-		}
+		&mut self.vb.staging[..self.len]
 	}
 }
 
 
 pub struct VertexBuffer<T>
 {
-	vbo: u32,
-	vao: u32,
-	vbo_size: usize,
-	phantom: std::marker::PhantomData<T>,
+	buf: u32,
+	staging: Vec<T>,
 }
 
-impl<T> VertexBuffer<T>
+impl<T: Default> VertexBuffer<T>
 {
 
-	pub fn new() -> VertexBuffer<T>
+	pub fn new(attribs: &[VertexAttrib]) -> VertexBuffer<T>
 	{
-		let mut vbo = 0;
-		let mut vao = 0;
-		let vbo_size = 6000;
-
-		let vsize = std::mem::size_of::<T>();
-
-		unsafe
-		{
-			// Create the VBO and VAO
-			gl::GenBuffers(1, &mut vbo);
-			gl::GenVertexArrays(1, &mut vao);
-
-			// Define the attributes
-			gl::BindVertexArray(vao);
-			gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
-
-			// Position
-			gl::EnableVertexAttribArray(0);
-			gl::VertexAttribPointer(0, 2, gl::FLOAT, gl::FALSE, vsize as i32, 0 as *const std::os::raw::c_void);
-
-			// Color
-			gl::EnableVertexAttribArray(1);
-			gl::VertexAttribPointer(1, 4, gl::UNSIGNED_BYTE, gl::TRUE, vsize as i32, 8 as *const std::os::raw::c_void);
-
-			// TexCoord
-			gl::EnableVertexAttribArray(2);
-			gl::VertexAttribPointer(2, 2, gl::FLOAT, gl::FALSE, vsize as i32, 12 as *const std::os::raw::c_void);
-
-			// Translation
-			gl::EnableVertexAttribArray(3);
-			gl::VertexAttribPointer(3, 2, gl::FLOAT, gl::FALSE, vsize as i32, 20 as *const std::os::raw::c_void);
-
-			// Scale
-			gl::EnableVertexAttribArray(4);
-			gl::VertexAttribPointer(4, 2, gl::FLOAT, gl::FALSE, vsize as i32, 28 as *const std::os::raw::c_void);
-
-			// Angle
-			gl::EnableVertexAttribArray(5);
-			gl::VertexAttribPointer(5, 1, gl::FLOAT, gl::FALSE, vsize as i32, 36 as *const std::os::raw::c_void);
-
-			// Origin
-			gl::EnableVertexAttribArray(6);
-			gl::VertexAttribPointer(6, 2, gl::FLOAT, gl::FALSE, vsize as i32, 40 as *const std::os::raw::c_void);
+		// One attribute record per vertex
+		VertexBuffer::create(attribs, false)
+	}
 
-			// Allocate a decent initial buffer
-			gl::BufferData(gl::ARRAY_BUFFER, (vbo_size*vsize) as isize, std::ptr::null(), gl::STREAM_DRAW);
+	pub fn new_instanced(attribs: &[VertexAttrib]) -> VertexBuffer<T>
+	{
+		// One attribute record per instance
+		VertexBuffer::create(attribs, true)
+	}
 
-			gl::BindBuffer(gl::ARRAY_BUFFER, 0);
-			gl::BindVertexArray(0);
-		}
+	fn create(attribs: &[VertexAttrib], instanced: bool) -> VertexBuffer<T>
+	{
+		// Create the buffer, and a decent initial staging area
+		let buf = with_backend(|b| b.create_buffer(attribs, std::mem::size_of::<T>(), instanced));
 
 		VertexBuffer
 			{
-				vbo,
-				vao,
-				vbo_size,
-				phantom: std::marker::PhantomData,
+				buf,
+				staging: Vec::with_capacity(6000),
 			}
 	}
 
-	pub fn map(&mut self, count: usize) -> MapGuard<T>
+	pub fn map(&mut self, count: usize) -> MapGuard<'_, T>
 	{
-		// Bind the buffer
-		unsafe
-		{
-			gl::BindBuffer(gl::ARRAY_BUFFER, self.vbo);
-		}
-			
-		// Make sure the buffer is big enough
-		if count > self.vbo_size
-		{
-			// Resize it
-			self.vbo_size = count;
-
-			unsafe
-			{
-				gl::BufferData(gl::ARRAY_BUFFER, (self.vbo_size*std::mem::size_of::<T>()) as isize, std::ptr::null(), gl::STREAM_DRAW);
-			}
-		}
-
-		// Map the region
-		let flags = gl::MAP_WRITE_BIT+gl::MAP_INVALIDATE_RANGE_BIT;
-		let ptr = unsafe { gl::MapBufferRange(gl::ARRAY_BUFFER, 0, (count*std::mem::size_of::<T>()) as isize, flags) };
+		// Make sure the staging area is big enough
+		if count > self.staging.len()
+			{ self.staging.resize_with(count, Default::default); }
 
 		MapGuard
 		{
-			ptr: ptr as *mut T,
+			vb: self,
 			len: count,
 		}
 	}
 
 	pub fn draw_triangles(&self, first: u32, count: u32)
 	{
-		unsafe
-		{
-			// Bind the VAO
-			gl::BindVertexArray(self.vao);
-
-			// Draw the triangles
-			gl::DrawArrays(gl::TRIANGLES, first as i32, count as i32);
-			
-			// Unbind
-			gl::BindVertexArray(0);
-		}
+		with_backend(|b| b.draw(self.buf, Primitive::Triangles, first, count));
+	}
+
+	pub fn draw_strip(&self, first: u32, count: u32)
+	{
+		with_backend(|b| b.draw(self.buf, Primitive::TriangleStrip, first, count));
+	}
+
+	pub fn draw_instanced_quads(&self, first: u32, count: u32)
+	{
+		// Draw a 4 vertex triangle strip per instance
+		with_backend(|b| b.draw_instanced(self.buf, Primitive::TriangleStrip, first, count, 4));
 	}
 
 }
@@ -200,17 +133,9 @@ impl<T> Drop for VertexBuffer<T>
 {
 	fn drop(&mut self)
 	{
-		// Drop the VAO and VBO
-		unsafe
-		{
-			gl::BindBuffer(gl::ARRAY_BUFFER, 0);
-			gl::BindVertexArray(0);
-
-			gl::DeleteBuffers(1, &mut self.vbo);
-			gl::DeleteVertexArrays(1, &mut self.vao);
-		}
+		let buf = self.buf;
+		with_backend(|b| b.delete_buffer(buf));
 	}
 
 }
 
-
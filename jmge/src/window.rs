@@ -1,5 +1,8 @@
 
-use super::{Color, Error, Input, Mouse, Keyboard};
+use super::{Color, Error, Input, Mouse, Keyboard, Modifiers, TextInput, TextEvent, TextEdit, Gamepads, Canvas, FrameTimer, Event};
+use super::framebuffer::read_pixels;
+use super::backend::{with_backend, Blend};
+use super::headless::HeadlessContext;
 use glfw::{Context, WindowEvent};
 use nalgebra::base::Matrix4;
 use std::time::{Instant};
@@ -10,15 +13,85 @@ pub struct Window
 
 	input: Input,
 
-	frame: u32,
-	frame_tot: u64,
-	last_time: Instant,
-	fps: f32,
+	timer: FrameTimer,
+	fps_in_title: bool,
+	title_time: Instant,
 	vsync: bool,
+	title: String,
+
+	virtual_res: Option<(u32, u32, ScaleMode)>,
+	viewport: (i32, i32, i32, i32),
+	cursor: (f64, f64),
+	resized: bool,
+	queue: Vec<Event>,
+	cursor_mode: CursorMode,
 	
-	window: glfw::Window,
-	events: std::sync::mpsc::Receiver<(f64, glfw::WindowEvent)>,
-	glfw: glfw::Glfw,
+	native: Native,
+}
+
+
+// What the window lives on, a bare context when GLFW can't start at all
+enum Native
+{
+	Glfw
+		{
+			glfw: glfw::Glfw,
+			window: glfw::Window,
+			events: std::sync::mpsc::Receiver<(f64, glfw::WindowEvent)>,
+		},
+	Headless
+		{
+			ctx: HeadlessContext,
+			close: bool,
+		},
+}
+
+impl Native
+{
+	fn size(&self) -> (i32, i32)
+	{
+		match self
+		{
+			Native::Glfw { window, .. } => window.get_size(),
+			Native::Headless { ctx, .. } => (ctx.size().0 as i32, ctx.size().1 as i32),
+		}
+	}
+
+	fn framebuffer_size(&self) -> (i32, i32)
+	{
+		match self
+		{
+			Native::Glfw { window, .. } => window.get_framebuffer_size(),
+			Native::Headless { .. } => self.size(),
+		}
+	}
+
+	fn window(&self) -> Option<&glfw::Window>
+	{
+		match self
+		{
+			Native::Glfw { window, .. } => Some(window),
+			Native::Headless { .. } => None,
+		}
+	}
+
+	fn window_mut(&mut self) -> Option<&mut glfw::Window>
+	{
+		match self
+		{
+			Native::Glfw { window, .. } => Some(window),
+			Native::Headless { .. } => None,
+		}
+	}
+
+	fn glfw_mut(&mut self) -> Option<&mut glfw::Glfw>
+	{
+		match self
+		{
+			Native::Glfw { glfw, .. } => Some(glfw),
+			Native::Headless { .. } => None,
+		}
+	}
 }
 
 
@@ -28,95 +101,537 @@ fn calc_proj(w: f32, h: f32) -> Matrix4<f32>
 	Matrix4::new_orthographic(0.0, w, h, 0.0, -1.0, 1.0)
 }
 
-impl Window
+fn calc_viewport(fb_w: i32, fb_h: i32, w: u32, h: u32, mode: ScaleMode) -> (i32, i32, i32, i32)
 {
+	// Area of the framebuffer showing a virtual resolution of w x h, centered
+	let (sx, sy) = (fb_w as f32 / w as f32, fb_h as f32 / h as f32);
 
-	pub fn new() -> Result<Window, Error>
+	let (vw, vh) = match mode
+		{
+			ScaleMode::Stretch => return (0, 0, fb_w, fb_h),
+			ScaleMode::Letterbox =>
+				{
+					let s = sx.min(sy);
+					((w as f32 * s).round() as i32, (h as f32 * s).round() as i32)
+				},
+			ScaleMode::IntegerScale =>
+				{
+					// Below 1x the image has to shrink anyway
+					let s = sx.min(sy);
+					let s = if s>=1.0 { s.floor() } else { s };
+					((w as f32 * s).round() as i32, (h as f32 * s).round() as i32)
+				},
+		};
+
+	((fb_w-vw)/2, (fb_h-vh)/2, vw, vh)
+}
+
+fn set_context_hints(glfw: &mut glfw::Glfw, major: u32, minor: u32)
+{
+	// Ask for an OpenGL context of the given version, core profiles exist from 3.2 on
+	glfw.window_hint(glfw::WindowHint::ContextVersion(major, minor));
+
+	if (major, minor)>=(3, 2)
+		{ glfw.window_hint(glfw::WindowHint::OpenGlProfile(glfw::OpenGlProfileHint::Core)); }
+}
+
+fn init_glfw() -> Result<glfw::Glfw, Error>
+{
+	// Only log errors, failures are reported through the return values
+	match glfw::init(glfw::LOG_ERRORS)
+	{
+		Ok (glfw) => Ok(glfw),
+		Err (e) => Err(Error::InitGlfw(format!("{:?}", e))),
+	}
+}
+
+//------------------------------------------------------------------------------------------------------------------------
+//------------------------------------------------------------------------------------------------------------------------
+//------------------------------------------------------------------------------------------------------------------------
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum ScaleMode
+{
+	// Largest scale keeping the aspect ratio, with bars on the sides or top and bottom
+	Letterbox,
+
+	// Largest whole number scale keeping the aspect ratio, for crisp pixel art
+	IntegerScale,
+
+	// Fill the whole window, distorting the aspect ratio
+	Stretch,
+}
+
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum CursorMode
+{
+	// Visible and free to leave the window
+	Normal,
+
+	// Invisible over the window
+	Hidden,
+
+	// Invisible and locked to the window, with unaccelerated motion where supported, for mouse-look
+	Captured,
+}
+
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum CursorShape
+{
+	Arrow,
+	IBeam,
+	Crosshair,
+	Hand,
+	HResize,
+	VResize,
+}
+
+
+fn to_pixel_image(cnv: &Canvas) -> glfw::PixelImage
+{
+	// Canvas pixels are already RGBA in memory
+	glfw::PixelImage
+	{
+		width: cnv.width(),
+		height: cnv.height(),
+		pixels: cnv.data().clone(),
+	}
+}
+
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum WindowMode
+{
+	// Regular window with decorations
+	Windowed,
+
+	// Exclusive fullscreen on the monitor with the given index, switching to the window size
+	Fullscreen { monitor: usize },
+
+	// Fullscreen at the monitor's current video mode, the window size is ignored
+	Borderless { monitor: usize },
+}
+
+
+pub struct WindowBuilder
+{
+	w: u32,
+	h: u32,
+	title: String,
+	resizable: bool,
+	mode: WindowMode,
+	samples: u32,
+	vsync: bool,
+	gl_version: (u32, u32),
+	pos: Option<(i32, i32)>,
+	hidden: bool,
+}
+
+impl WindowBuilder
+{
+	pub fn new() -> WindowBuilder
 	{
+		// Same settings as Window::new()
+		WindowBuilder
+		{
+			w: 1920,
+			h: 1080,
+			title: String::from("JMGE"),
+			resizable: false,
+			mode: WindowMode::Windowed,
+			samples: 0,
+			vsync: true,
+			gl_version: (3, 3),
+			pos: None,
+			hidden: false,
+		}
+	}
+
+	pub fn size(mut self, w: u32, h: u32) -> WindowBuilder
+	{
+		self.w = w;
+		self.h = h;
+		self
+	}
+
+	pub fn title(mut self, title: &str) -> WindowBuilder
+	{
+		self.title = title.to_string();
+		self
+	}
+
+	pub fn resizable(mut self, resizable: bool) -> WindowBuilder
+	{
+		self.resizable = resizable;
+		self
+	}
+
+	pub fn mode(mut self, mode: WindowMode) -> WindowBuilder
+	{
+		self.mode = mode;
+		self
+	}
+
+	pub fn samples(mut self, samples: u32) -> WindowBuilder
+	{
+		// MSAA samples, 0 disables multisampling
+		self.samples = samples;
+		self
+	}
+
+	pub fn vsync(mut self, vsync: bool) -> WindowBuilder
+	{
+		self.vsync = vsync;
+		self
+	}
+
+	pub fn gl_version(mut self, major: u32, minor: u32) -> WindowBuilder
+	{
+		// The renderer's shaders need at least 3.3, build() fails on older versions
+		self.gl_version = (major, minor);
+		self
+	}
+
+	pub fn position(mut self, x: i32, y: i32) -> WindowBuilder
+	{
+		// Initial position in screen coordinates, only used by windowed mode
+		self.pos = Some((x, y));
+		self
+	}
+
+	pub fn hidden(mut self, hidden: bool) -> WindowBuilder
+	{
+		// Hidden windows can be shown later with Window.show()
+		self.hidden = hidden;
+		self
+	}
+
+	pub fn build(self) -> Result<Window, Error>
+	{
+		// The renderer's shaders can't run on older versions
+		if self.gl_version<(3, 3)
+			{ return Err(Error::CreateWindow(format!("OpenGL {}.{} was requested, at least 3.3 is needed", self.gl_version.0, self.gl_version.1))); }
+
 		// Initialize GLFW
-		let mut glfw = glfw::init(glfw::FAIL_ON_ERRORS).unwrap();
+		let mut glfw = init_glfw()?;
+
+		// Set the window and context hints
+		set_context_hints(&mut glfw, self.gl_version.0, self.gl_version.1);
+		glfw.window_hint(glfw::WindowHint::Resizable(self.resizable));
+		glfw.window_hint(glfw::WindowHint::Visible(!self.hidden));
+		glfw.window_hint(glfw::WindowHint::Samples(if self.samples>0 { Some(self.samples) } else { None }));
+
+		// Create the window, on the chosen monitor for the fullscreen modes
+		let (w, h, title, mode) = (self.w, self.h, &self.title, self.mode);
+
+		let res = glfw.with_connected_monitors(|glfw, monitors|
+			{
+				let monitor = match mode
+					{
+						WindowMode::Windowed => return Ok(glfw.create_window(w, h, title, glfw::WindowMode::Windowed)),
+						WindowMode::Fullscreen { monitor } | WindowMode::Borderless { monitor } => monitor,
+					};
+
+				let monitor = match monitors.get(monitor)
+					{
+						Some (m) => m,
+						None => return Err(Error::CreateWindow(format!("there's no monitor {}, {} connected", monitor, monitors.len()))),
+					};
+
+				if let WindowMode::Borderless { .. } = mode
+				{
+					// Match the current video mode, so the monitor doesn't switch modes
+					let vm = match monitor.get_video_mode()
+						{
+							Some (vm) => vm,
+							None => return Err(Error::CreateWindow(String::from("could not get the monitor's video mode"))),
+						};
+
+					glfw.window_hint(glfw::WindowHint::RedBits(Some(vm.red_bits)));
+					glfw.window_hint(glfw::WindowHint::GreenBits(Some(vm.green_bits)));
+					glfw.window_hint(glfw::WindowHint::BlueBits(Some(vm.blue_bits)));
+					glfw.window_hint(glfw::WindowHint::RefreshRate(Some(vm.refresh_rate)));
+
+					Ok(glfw.create_window(vm.width, vm.height, title, glfw::WindowMode::FullScreen(monitor)))
+				}
+				else
+				{
+					Ok(glfw.create_window(w, h, title, glfw::WindowMode::FullScreen(monitor)))
+				}
+			})?;
+
+		let (mut window, events) = match res
+			{
+				Some (res) => res,
+				None => return Err(Error::CreateWindow(format!("could not create a {}x{} window with an OpenGL {}.{} context", w, h, self.gl_version.0, self.gl_version.1))),
+			};
+
+		if let (Some ((x, y)), WindowMode::Windowed) = (self.pos, self.mode)
+			{ window.set_pos(x, y); }
+
+		let mut wnd = Window::setup(Native::Glfw { glfw, window, events });
+		wnd.title = self.title;
+		wnd.set_vsync(self.vsync);
 
+		Ok(wnd)
+	}
+}
+
+impl Default for WindowBuilder
+{
+	fn default() -> WindowBuilder
+	{
+		WindowBuilder::new()
+	}
+}
+
+//------------------------------------------------------------------------------------------------------------------------
+//------------------------------------------------------------------------------------------------------------------------
+//------------------------------------------------------------------------------------------------------------------------
+
+impl Window
+{
+
+	pub fn new() -> Result<Window, Error>
+	{
 		// Create the default window
-		glfw.window_hint(glfw::WindowHint::ContextVersion(3, 2));
-		glfw.window_hint(glfw::WindowHint::OpenGlProfile(glfw::OpenGlProfileHint::Core));
+		WindowBuilder::new().build()
+	}
+
+	pub fn new_headless(w: u32, h: u32) -> Result<Window, Error>
+	{
+		// GLFW needs a display server, without one go straight to EGL
+		let mut glfw = match init_glfw()
+			{
+				Ok (glfw) => glfw,
+				Err (_) => return Ok(Window::setup(Native::Headless { ctx: HeadlessContext::new(w, h)?, close: false })),
+			};
+
+		// Create a hidden window, only used for its context
+		set_context_hints(&mut glfw, 3, 3);
+		glfw.window_hint(glfw::WindowHint::Visible(false));
 		glfw.window_hint(glfw::WindowHint::Resizable(false));
 
-		let (mut window, events) = glfw.create_window(1920, 1080, "JMGE", glfw::WindowMode::Windowed).unwrap();
+		let res = match glfw.create_window(w, h, "JMGE", glfw::WindowMode::Windowed)
+			{
+				Some (res) => Some(res),
+				None =>
+					{
+						// No usable native context, fall back to software rendering through OSMesa
+						glfw.window_hint(glfw::WindowHint::ContextCreationApi(glfw::ContextCreationApi::OsMesa));
+						glfw.create_window(w, h, "JMGE", glfw::WindowMode::Windowed)
+					},
+			};
 
-		window.make_current();
-		window.set_all_polling(true);
+		// Last, EGL without a window
+		match res
+		{
+			Some ((window, events)) => Ok(Window::setup(Native::Glfw { glfw, window, events })),
+			None => Ok(Window::setup(Native::Headless { ctx: HeadlessContext::new(w, h)?, close: false })),
+		}
+	}
 
+	fn setup(mut native: Native) -> Window
+	{
 		// Setup OpenGL
-		gl::load_with(|s| glfw.get_proc_address_raw(s));
+		match &mut native
+		{
+			Native::Glfw { glfw, window, .. } =>
+				{
+					window.make_current();
+					window.set_all_polling(true);
 
-		//glfw.set_swap_interval(glfw::SwapInterval::None);
+					// Report caps lock and num lock with the modifiers
+					window.set_store_lock_key_mods(true);
 
-		unsafe
-		{
-			gl::Disable(gl::DEPTH_TEST);
-			gl::Enable(gl::BLEND);
-			gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+					gl::load_with(|s| glfw.get_proc_address_raw(s));
+				},
+			Native::Headless { ctx, .. } => gl::load_with(|s| ctx.get_proc_address(s)),
 		}
 
-		// Set the viewport
-		let (w, h) = window.get_size();
-
-		unsafe { gl::Viewport(0, 0, w, h); }
+		//glfw.set_swap_interval(glfw::SwapInterval::None);
 
-		// Prepare the projection matrix
-		let proj_mat = calc_proj(w as f32, h as f32);
+		// Sprites are drawn in order with alpha blending, there is no depth test
+		with_backend(|b| b.set_blend(Blend::Alpha));
 
-		let wnd = Window
+		let mut wnd = Window
 		{
-			glfw,
-			window,
-			events,
-			proj_mat,
-			frame: 0,
-			frame_tot: 0,
-			last_time: Instant::now(),
-			fps: 0.0,
+			native,
+			proj_mat: Matrix4::identity(),
+			timer: FrameTimer::new(),
+			fps_in_title: false,
+			title_time: Instant::now(),
 			input: Input::new(),
 			vsync: true,
+			title: String::from("JMGE"),
+			virtual_res: None,
+			viewport: (0, 0, 0, 0),
+			cursor: (0.0, 0.0),
+			resized: false,
+			queue: Vec::new(),
+			cursor_mode: CursorMode::Normal,
 		};
 
-		Ok(wnd)
+		// Set the viewport and the projection matrix
+		wnd.update_view();
+
+		wnd
+	}
+
+	fn update_view(&mut self)
+	{
+		// The framebuffer is in pixels, the window in screen coordinates, they differ on high-DPI displays
+		let (fb_w, fb_h) = self.native.framebuffer_size();
+
+		let (w, h) = match self.virtual_res
+			{
+				Some ((vw, vh, mode)) =>
+					{
+						self.viewport = calc_viewport(fb_w, fb_h, vw, vh, mode);
+						(vw as f32, vh as f32)
+					},
+				None =>
+					{
+						// Logical units are screen coordinates, like the mouse position
+						let (w, h) = self.native.size();
+						self.viewport = (0, 0, fb_w, fb_h);
+						(w as f32, h as f32)
+					},
+			};
+
+		self.proj_mat = calc_proj(w, h);
+
+		let (x, y, w, h) = self.viewport;
+		with_backend(|b| b.set_viewport(x, y, w, h));
+
+		// The same cursor position can map to a different logical one now
+		self.update_mouse();
+	}
+
+	fn update_mouse(&mut self)
+	{
+		// Map the cursor from screen coordinates to logical units
+		let (x, y) = self.to_logical(self.cursor.0, self.cursor.1);
+		self.input.mouse_mut().set_pos(x.floor() as i32, y.floor() as i32);
+	}
+
+	pub fn to_logical(&self, x: f64, y: f64) -> (f32, f32)
+	{
+		// Convert a position in screen coordinates to the logical units of the projection
+		match self.virtual_res
+		{
+			Some ((vw, vh, _)) =>
+				{
+					let (ww, wh) = self.native.size();
+					let (fb_w, fb_h) = self.native.framebuffer_size();
+					let (vx, vy, vpw, vph) = self.viewport;
+
+					// Screen coordinates to framebuffer pixels, then into the viewport, whose y is bottom-up
+					let px = x as f32 * fb_w as f32 / ww.max(1) as f32;
+					let py = y as f32 * fb_h as f32 / wh.max(1) as f32;
+					let top = fb_h - vy - vph;
+
+					((px - vx as f32) * vw as f32 / vpw.max(1) as f32, (py - top as f32) * vh as f32 / vph.max(1) as f32)
+				},
+			None => (x as f32, y as f32),
+		}
+	}
+
+	pub fn set_virtual_resolution(&mut self, w: u32, h: u32, mode: ScaleMode)
+	{
+		// Render at w x h logical units, scaled to the window
+		self.virtual_res = Some((w.max(1), h.max(1), mode));
+		self.update_view();
+	}
+
+	pub fn clear_virtual_resolution(&mut self)
+	{
+		// Logical units are screen coordinates again
+		self.virtual_res = None;
+		self.update_view();
+	}
+
+	pub fn virtual_resolution(&self) -> Option<(u32, u32, ScaleMode)>
+	{
+		self.virtual_res
+	}
+
+	pub fn size(&self) -> (u32, u32)
+	{
+		// Size in logical units
+		match self.virtual_res
+		{
+			Some ((w, h, _)) => (w, h),
+			None =>
+				{
+					let (w, h) = self.native.size();
+					(w as u32, h as u32)
+				},
+		}
+	}
+
+	pub fn framebuffer_size(&self) -> (u32, u32)
+	{
+		// Size in pixels
+		let (w, h) = self.native.framebuffer_size();
+		(w as u32, h as u32)
+	}
+
+	pub fn viewport(&self) -> (i32, i32, i32, i32)
+	{
+		// Area of the framebuffer rendered to, in pixels, bottom-up like OpenGL
+		self.viewport
+	}
+
+	pub fn was_resized(&self) -> bool
+	{
+		// Whether the window or its framebuffer changed size during the last poll_events()
+		self.resized
 	}
 
 	pub fn swap(&mut self)
 	{
-		// Swap the display buffers
-		self.window.swap_buffers();
+		// Swap the display buffers, a headless context has nothing to show
+		if let Some(window) = self.native.window_mut()
+			{ window.swap_buffers(); }
 
-		// Perform FPS calculations
-		self.frame += 1;
-		self.frame_tot += 1;
+		// Vsync already paces the frames
+		if !self.vsync
+			{ self.timer.limit(); }
 
-		let now = Instant::now();
-		let ms = now.duration_since(self.last_time).as_millis();
+		self.timer.tick();
 
-		if ms>=1000
+		// Show the FPS in the title once a second, if asked to
+		if self.fps_in_title && self.title_time.elapsed().as_millis()>=1000
 		{
-			// Calc the new FPS
-			self.fps = (self.frame as f32) / (ms as f32 / 1000.0);
-			self.frame = 0;
-			self.last_time = now;
+			self.title_time = Instant::now();
 
-			// Update the title
-			self.window.set_title(&format!("JMGE - {} fps", self.fps));
-			println!("{} frames/second", self.fps);
+			if let Some(window) = self.native.window_mut()
+				{ window.set_title(&format!("{} - {:.0} fps", self.title, self.timer.fps())); }
 		}
 	}
 
 	pub fn poll_events(&mut self)
 	{
-		// Reset the input state
+		// Reset the input state and the event queue
 		self.input.reset();
+		self.resized = false;
+		self.queue.clear();
 
-		// Poll the queued events
-		self.glfw.poll_events();
+		// Poll the queued events, and take them out of the receiver first, handling them needs the whole window
+		let events: Vec<WindowEvent> = match &mut self.native
+			{
+				Native::Glfw { glfw, events, .. } =>
+					{
+						glfw.poll_events();
+						glfw::flush_messages(events).map(|(_, event)| event).collect()
+					},
+				Native::Headless { .. } => Vec::new(),
+			};
 
-		for (_, event) in glfw::flush_messages(&self.events)
+		for event in events
 		{
 			//println!("{:?}", event);
 
@@ -125,45 +640,111 @@ impl Window
 				WindowEvent::CursorPos (x, y) =>
 					{
 						// Update the mouse position
-						self.input.mouse_mut().set_pos(x as i32, y as i32);
+						self.cursor = (x, y);
+						self.update_mouse();
 					},
 
-				WindowEvent::MouseButton (but, glfw::Action::Press, _) =>
+				WindowEvent::FramebufferSize (_, _) | WindowEvent::Size (_, _) =>
 					{
-						self.input.mouse_mut().set_but(but as u8, true);
+						// Both can change alone, e.g. when moving to a monitor with another DPI
+						self.resized = true;
 					},
 
-				WindowEvent::MouseButton (but, glfw::Action::Release, _) =>
+				WindowEvent::MouseButton (but, action, mods) =>
 					{
-						self.input.mouse_mut().set_but(but as u8, false);
+						let mouse = self.input.mouse_mut();
+						mouse.set_modifiers(Modifiers::from_glfw(mods));
+						mouse.set_but(but as u8, action!=glfw::Action::Release);
 					},
 
-				WindowEvent::Key(key, _, glfw::Action::Press, _) =>
+				WindowEvent::CursorEnter (inside) =>
 					{
-						self.input.keyboard_mut().set_key(key, true);
+						self.input.mouse_mut().set_inside(inside);
+						self.queue.push(if inside { Event::CursorEntered } else { Event::CursorLeft });
 					},
 
-				WindowEvent::Key(key, _, glfw::Action::Release, _) =>
+				WindowEvent::Key(key, _, action, mods) =>
 					{
-						self.input.keyboard_mut().set_key(key, false);
+						let mods = Modifiers::from_glfw(mods);
+						self.input.keyboard_mut().set_modifiers(mods);
+
+						match action
+						{
+							glfw::Action::Press => self.input.keyboard_mut().set_key(key, true),
+							glfw::Action::Release => self.input.keyboard_mut().set_key(key, false),
+							glfw::Action::Repeat => (),
+						}
+
+						// Editing keys repeat while held
+						if action!=glfw::Action::Release
+						{
+							if let Some(edit) = TextEdit::from_key(key)
+								{ self.input.text_mut().push(TextEvent::Edit(edit, mods)); }
+						}
 					},
 
+				WindowEvent::Scroll (x, y) =>
+					{
+						self.input.mouse_mut().add_scroll(x as f32, y as f32);
+					},
+
+				WindowEvent::Char (c) =>
+					{
+						self.input.text_mut().push(TextEvent::Char(c));
+					},
+
+				WindowEvent::Focus (focused) =>
+					{
+						self.queue.push(if focused { Event::FocusGained } else { Event::FocusLost });
+					},
+
+				WindowEvent::Close =>
+					{
+						// GLFW already set the close flag, the application may clear it
+						self.queue.push(Event::CloseRequested);
+					},
+
+				WindowEvent::Iconify (true) => self.queue.push(Event::Minimized),
+				WindowEvent::Maximize (true) => self.queue.push(Event::Maximized),
+				WindowEvent::Iconify (false) | WindowEvent::Maximize (false) => self.queue.push(Event::Restored),
+
+				WindowEvent::ContentScale (sx, sy) => self.queue.push(Event::ContentScale(sx, sy)),
+
+				WindowEvent::FileDrop (paths) => self.queue.push(Event::FileDrop(paths)),
 
 				_ => (),
 			}
 		}
+
+		// Poll the gamepads, GLFW has no events for their state
+		if let Some(glfw) = self.native.glfw_mut()
+		{
+			for (i, connected) in self.input.gamepads_mut().update(glfw)
+				{ self.queue.push(if connected { Event::GamepadConnected(i) } else { Event::GamepadDisconnected(i) }); }
+		}
+
+		if self.resized
+		{
+			self.update_view();
+
+			let (w, h) = self.framebuffer_size();
+			self.queue.push(Event::Resized(w, h));
+		}
 	}
 
 	pub fn set_vsync(&mut self, vsync: bool)
 	{
 		// Set vsync
-		if vsync
+		if let Some(glfw) = self.native.glfw_mut()
 		{
-			self.glfw.set_swap_interval(glfw::SwapInterval::Sync(1));
-		}
-		else
-		{
-			self.glfw.set_swap_interval(glfw::SwapInterval::None);
+			if vsync
+			{
+				glfw.set_swap_interval(glfw::SwapInterval::Sync(1));
+			}
+			else
+			{
+				glfw.set_swap_interval(glfw::SwapInterval::None);
+			}
 		}
 
 		self.vsync = vsync;
@@ -175,29 +756,230 @@ impl Window
 		self.vsync
 	}
 
+	pub fn show(&mut self)
+	{
+		if let Some(window) = self.native.window_mut()
+			{ window.show(); }
+	}
+
+	pub fn hide(&mut self)
+	{
+		if let Some(window) = self.native.window_mut()
+			{ window.hide(); }
+	}
+
+	pub fn set_title(&mut self, title: &str)
+	{
+		// The FPS are appended to it once a second, if enabled
+		self.title = title.to_string();
+
+		if let Some(window) = self.native.window_mut()
+			{ window.set_title(title); }
+	}
+
+	pub fn title(&self) -> &str
+	{
+		&self.title
+	}
+
+	pub fn set_cursor_mode(&mut self, mode: CursorMode)
+	{
+		self.cursor_mode = mode;
+
+		if let Native::Glfw { glfw, window, .. } = &mut self.native
+		{
+			match mode
+			{
+				CursorMode::Normal => window.set_cursor_mode(glfw::CursorMode::Normal),
+				CursorMode::Hidden => window.set_cursor_mode(glfw::CursorMode::Hidden),
+				CursorMode::Captured => window.set_cursor_mode(glfw::CursorMode::Disabled),
+			}
+
+			window.set_raw_mouse_motion(mode==CursorMode::Captured && glfw.supports_raw_motion());
+		}
+	}
+
+	pub fn cursor_mode(&self) -> CursorMode
+	{
+		self.cursor_mode
+	}
+
+	pub fn set_cursor_shape(&mut self, shape: CursorShape)
+	{
+		// Use one of the system's cursors
+		let shape = match shape
+			{
+				CursorShape::Arrow => glfw::StandardCursor::Arrow,
+				CursorShape::IBeam => glfw::StandardCursor::IBeam,
+				CursorShape::Crosshair => glfw::StandardCursor::Crosshair,
+				CursorShape::Hand => glfw::StandardCursor::Hand,
+				CursorShape::HResize => glfw::StandardCursor::HResize,
+				CursorShape::VResize => glfw::StandardCursor::VResize,
+			};
+
+		if let Some(window) = self.native.window_mut()
+			{ window.set_cursor(Some(glfw::Cursor::standard(shape))); }
+	}
+
+	pub fn set_custom_cursor(&mut self, cnv: &Canvas, hot_x: u32, hot_y: u32)
+	{
+		// Use an image as the cursor, the hotspot is the pixel that points, from the top left
+		if let Some(window) = self.native.window_mut()
+			{ window.set_cursor(Some(glfw::Cursor::create_from_pixels(to_pixel_image(cnv), hot_x, hot_y))); }
+	}
+
+	pub fn reset_cursor(&mut self)
+	{
+		// Back to the default cursor
+		if let Some(window) = self.native.window_mut()
+			{ window.set_cursor(None); }
+	}
+
+	pub fn set_icon(&mut self, icons: &[&Canvas])
+	{
+		// Several sizes can be given, the system picks the closest ones
+		let images = icons.iter().map(|cnv| to_pixel_image(cnv)).collect();
+
+		if let Some(window) = self.native.window_mut()
+			{ window.set_icon_from_pixels(images); }
+	}
+
+	pub fn events(&self) -> &[Event]
+	{
+		// Events received by the last poll_events(), in order
+		&self.queue
+	}
+
 	pub fn should_close(&self) -> bool
 	{
-		self.window.should_close()
+		match &self.native
+		{
+			Native::Glfw { window, .. } => window.should_close(),
+			Native::Headless { close, .. } => *close,
+		}
 	}
 
-	pub fn clear(&self, col: Color)
+	fn set_should_close(&mut self, value: bool)
 	{
-		// Clear the window
-		unsafe
+		match &mut self.native
 		{
-			gl::ClearColor(col.r(), col.g(), col.b(), col.a());
-			gl::Clear(gl::COLOR_BUFFER_BIT);
+			Native::Glfw { window, .. } => window.set_should_close(value),
+			Native::Headless { close, .. } => *close = value,
 		}
 	}
 
+	pub fn close(&mut self)
+	{
+		// Ask the main loop to end, through should_close()
+		self.set_should_close(true);
+	}
+
+	pub fn cancel_close(&mut self)
+	{
+		// Veto a close request, e.g. to ask about unsaved changes first
+		self.set_should_close(false);
+	}
+
+	pub fn is_focused(&self) -> bool
+	{
+		self.native.window().is_some_and(|w| w.is_focused())
+	}
+
+	pub fn is_minimized(&self) -> bool
+	{
+		self.native.window().is_some_and(|w| w.is_iconified())
+	}
+
+	pub fn content_scale(&self) -> (f32, f32)
+	{
+		self.native.window().map_or((1.0, 1.0), |w| w.get_content_scale())
+	}
+
+	pub fn clear(&self, col: Color)
+	{
+		// Clear the window, the bars around a virtual resolution are black
+		let (x, y, w, h) = self.viewport;
+		let (fb_w, fb_h) = self.native.framebuffer_size();
+		let bars = (x, y, w, h)!=(0, 0, fb_w, fb_h);
+
+		with_backend(|b|
+			{
+				if bars
+				{
+					b.clear([0.0, 0.0, 0.0, 1.0], None);
+					b.clear([col.r(), col.g(), col.b(), col.a()], Some((x, y, w, h)));
+				}
+				else
+					{ b.clear([col.r(), col.g(), col.b(), col.a()], None); }
+			});
+	}
+
+	pub fn bind_default_framebuffer(&self)
+	{
+		// Render to the window again, after using an offscreen framebuffer
+		let (x, y, w, h) = self.viewport;
+
+		with_backend(|b|
+			{
+				b.bind_target(0);
+				b.set_viewport(x, y, w, h);
+			});
+	}
+
+	pub fn read_pixels(&self) -> Canvas
+	{
+		// Read back the frame rendered so far, before it is swapped
+		let (w, h) = self.native.framebuffer_size();
+
+		read_pixels(0, w as u32, h as u32)
+	}
+
+	pub fn screenshot(&self) -> Canvas
+	{
+		// Grab the window content, top row first
+		// Call it after rendering and before swapping
+		self.read_pixels()
+	}
+
+	pub fn set_fps_in_title(&mut self, show: bool)
+	{
+		// Append the FPS to the title once a second
+		self.fps_in_title = show;
+
+		if let (false, Some(window)) = (show, self.native.window_mut())
+			{ window.set_title(&self.title); }
+	}
+
+	pub fn set_fps_cap(&mut self, cap: Option<f32>)
+	{
+		// Sleep in swap() to stay under the given frame rate, only while vsync is off
+		self.timer.set_fps_cap(cap);
+	}
+
+	pub fn delta(&self) -> f32
+	{
+		// Seconds between the last two swaps
+		self.timer.delta()
+	}
+
 	pub fn fps(&self) -> f32
 	{
-		self.fps
+		self.timer.fps()
 	}
 
 	pub fn frames(&self) -> u64
 	{
-		self.frame_tot
+		self.timer.frames()
+	}
+
+	pub fn timer(&self) -> &FrameTimer
+	{
+		&self.timer
+	}
+
+	pub fn timer_mut(&mut self) -> &mut FrameTimer
+	{
+		&mut self.timer
 	}
 
 	pub fn input(&self) -> &Input
@@ -215,6 +997,32 @@ impl Window
 		self.input.keyboard()
 	}
 
+	pub fn gamepads(&self) -> &Gamepads
+	{
+		self.input.gamepads()
+	}
+
+	pub fn add_gamepad_mappings(&mut self, mappings: &str) -> bool
+	{
+		// SDL_GameControllerDB lines, added to the mappings GLFW ships with
+		self.native.glfw_mut().is_some_and(|glfw| glfw.update_gamepad_mappings(mappings))
+	}
+
+	pub fn text_input(&self) -> &TextInput
+	{
+		self.input.text()
+	}
+
+	pub fn start_text_input(&mut self)
+	{
+		self.input.text_mut().start();
+	}
+
+	pub fn stop_text_input(&mut self)
+	{
+		self.input.text_mut().stop();
+	}
+
 	pub fn projection_matrix(&self) -> &Matrix4<f32>
 	{
 		&self.proj_mat
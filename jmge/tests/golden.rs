@@ -0,0 +1,152 @@
+use jmge::*;
+use nalgebra::base::Matrix4;
+use std::rc::Rc;
+
+
+// Rendered images are compared against the PNGs in tests/golden
+// Run with JMGE_BLESS=1 to write them again after an intended change
+
+fn check_golden(cnv: &Canvas, name: &str)
+{
+	let fname = format!("{}/tests/golden/{}.png", env!("CARGO_MANIFEST_DIR"), name);
+
+	if std::env::var_os("JMGE_BLESS").is_some()
+		{ cnv.save_png(&fname).unwrap(); }
+
+	let golden = Canvas::from_file(&fname).unwrap_or_else(|e| panic!("{}: {}", fname, e));
+
+	// Allow rounding differences only
+	match cnv.max_difference(&golden)
+	{
+		Some (diff) => assert!(diff<=1, "{} differs by {}", name, diff),
+		None => panic!("{} has a different size than the golden image", name),
+	}
+}
+
+fn checker(w: u32, h: u32, a: Color, b: Color) -> Canvas
+{
+	let mut cnv = Canvas::new(w, h, a);
+
+	for y in 0..h as i32
+	{
+		for x in 0..w as i32
+		{
+			if (x/2 + y/2) % 2 == 1
+				{ cnv.set_pixel(x, y, b); }
+		}
+	}
+
+	cnv
+}
+
+
+#[test]
+fn soft_scene()
+{
+	let mut rend = SoftRenderer::new(48, 32);
+	rend.clear(Color::rgb(0.1, 0.1, 0.2));
+
+	let tex = Rc::new(Texture::soft(checker(8, 8, Color::rgb(1.0, 1.0, 1.0), Color::rgb(0.8, 0.2, 0.2))));
+
+	// A plain quad, a scaled and tinted one, and a rotated one around its center
+	rend.add_quad(Quad::new(&tex).with_pos(2.0, 2.0));
+
+	let mut q = Quad::new(&tex).with_pos(12.0, 2.0);
+	q.sx = 2.0;
+	q.sy = 1.5;
+	q.col = Color::rgb(0.5, 1.0, 0.5).0;
+	rend.add_quad(q);
+
+	let mut q = Quad::new(&tex).with_pos(36.0, 8.0);
+	q.ox = 4.0;
+	q.oy = 4.0;
+	q.angle = std::f32::consts::FRAC_PI_4;
+	rend.add_quad(q);
+
+	// Shapes
+	rend.add_shape(Shape::circle(10.0, 24.0, 6.0).with_color(Color::rgb(0.2, 0.6, 1.0)));
+	rend.add_shape(Shape::polygon(&[(20.0, 30.0), (30.0, 18.0), (40.0, 30.0)]).with_color(Color::rgb(1.0, 0.8, 0.0)));
+	rend.add_shape(Shape::rect_outline(1.0, 1.0, 46.0, 30.0, 1.0).with_color(Color::rgb(1.0, 1.0, 1.0)));
+
+	rend.render(&Matrix4::new_orthographic(0.0, 48.0, 32.0, 0.0, -1.0, 1.0));
+
+	check_golden(rend.canvas(), "soft_scene");
+}
+
+
+fn tileset() -> Canvas
+{
+	// Four tiles of 8x8, each with a mark in its top left corner
+	let cols = [Color::rgb(1.0, 0.0, 0.0), Color::rgb(0.0, 1.0, 0.0), Color::rgb(0.0, 0.0, 1.0), Color::rgb(1.0, 1.0, 1.0)];
+	let mut cnv = Canvas::new(16, 16, cols[0]);
+
+	for (i, col) in cols.iter().enumerate()
+	{
+		let (tx, ty) = ((i%2) as i32 * 8, (i/2) as i32 * 8);
+
+		for y in 0..8
+		{
+			for x in 0..8
+			{
+				let mark = x<3 && y<2;
+				cnv.set_pixel(tx+x, ty+y, if mark { Color::rgb(0.0, 0.0, 0.0) } else { *col });
+			}
+		}
+	}
+
+	cnv
+}
+
+fn headless(w: u32, h: u32) -> Option<Window>
+{
+	// Machines without any OpenGL skip the GPU tests
+	match Window::new_headless(w, h)
+	{
+		Ok (wnd) => Some(wnd),
+		Err (e) =>
+			{
+				eprintln!("skipped, no headless context: {}", e);
+				None
+			},
+	}
+}
+
+#[test]
+fn gl_scene()
+{
+	let wnd = match headless(64, 48)
+		{
+			Some (wnd) => wnd,
+			None => return,
+		};
+
+	let mut rend = Renderer::new().unwrap();
+	let tiles = Rc::new(Texture::from_canvas(&tileset(), false));
+	let mut tmr = TileMapRenderer::new().unwrap();
+
+	// Whole pixel positions only, so no pixel center lies on an edge
+	let tex = Rc::new(Texture::from_canvas(&checker(8, 8, Color::rgb(1.0, 1.0, 1.0), Color::rgb(0.8, 0.2, 0.2)), false));
+
+	let mut map = TileMap::new(4, 3, 8, 8, &tiles);
+
+	for y in 0..3
+	{
+		for x in 0..4
+		{
+			if (x+y) % 3 != 2
+				{ map.set_tile(x, y, Some((x+2*y) % 4)); }
+		}
+	}
+
+	wnd.clear(Color::rgb(0.1, 0.1, 0.2));
+	tmr.render(&map, 28.0, 20.0, wnd.projection_matrix());
+
+	// A plain quad, a scaled and tinted one, a mirrored one and a shape
+	rend.add_quad(Quad::new(&tex).with_pos(2.0, 2.0));
+	rend.add_quad(Quad::new(&tex).with_pos(2.0, 14.0).with_scale(2.0, 3.0).with_color(Color::rgb(0.5, 1.0, 0.5)));
+	rend.add_quad(Quad::new(&tex).with_pos(14.0, 2.0).with_flip(true, false).with_src(0.0, 0.0, 6.0, 8.0));
+	rend.add_shape(Shape::rect(40.0, 2.0, 20.0, 10.0).with_color(Color::rgb(1.0, 0.8, 0.0)));
+	rend.render(wnd.projection_matrix());
+
+	check_golden(&wnd.read_pixels(), "gl_scene");
+}
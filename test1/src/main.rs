@@ -6,7 +6,7 @@ use rand::Rng;
 
 fn oops(e: Error) -> !
 {
-	println!("Fatal error: {}", e.to_string());
+	println!("Fatal error: {}", e);
 	std::process::exit(1);
 }
 
@@ -39,8 +39,10 @@ impl System for RotSys
 
 fn run() -> Result<(), Error>
 {
-	let mut wnd = Window::new()?;
+	let mut wnd = WindowBuilder::new().title("JMGE test").resizable(true).samples(4).build()?;
+	wnd.set_fps_in_title(true);
 	let mut rend = Renderer::new()?;
+	rend.set_pixel_snap(PixelSnap::Pixel);
 
 	let _cnv1 = Canvas::from_memory_file(include_bytes!("../../kanade.png"))?;
 	let cnv2 = Canvas::from_memory_file(include_bytes!("../../kanade2.png"))?;
@@ -49,6 +51,14 @@ fn run() -> Result<(), Error>
 	let mut atlas = TextureAtlas::new(2048, false);
 	let tex = Rc::new(atlas.add(cnv2)?);
 
+	let white = Rc::new(atlas.add(Canvas::new(4, 4, Color::rgb(1.0, 1.0, 1.0)))?);
+	rend.set_white_texture(&white);
+
+
+	let mut font = Font::from_canvas(&Canvas::from_memory_file(include_bytes!("../../bmpfont8x16.png"))?)?;
+	font.pack(&mut atlas)?;
+	let font = Rc::new(font);
+
 
 	let ss = Rc::new(SpriteSheet::from_file("adventurer.json", &cnv3, &mut atlas)?);
 
@@ -59,11 +69,21 @@ fn run() -> Result<(), Error>
 	let music = Sound::from_file("Battleship.ogg")?;
 	let mut sink = None;
 
+	let mut name = String::new();
+
+	let mut actions = ActionMap::new();
+	actions.bind("game", "attack", Binding::Key(Key::Z));
+	actions.bind("game", "attack", Binding::PadButton(GamepadButton::ButtonX));
+	actions.push_context("game");
+
 
 	let mut world = World::new();
 	world.register::<Renderable>();
 	world.register::<RotSpeed>();
 	world.register::<Sprite>();
+	world.register::<Text>();
+	world.register::<Light>();
+	world.register::<ParticleEmitter>();
 
 
 	let (tw, th) = tex.size();
@@ -75,9 +95,9 @@ fn run() -> Result<(), Error>
 	{
 		let ent = world.new_entity();
 
-		let mut r = Renderable::new(&tex, rng.gen_range(0, 1920), rng.gen_range(0, 1080));
-		r.x_origin = tw as i32/2;
-		r.y_origin = th as i32/2;
+		let mut r = Renderable::new(&tex, rng.gen_range(0.0, 1920.0), rng.gen_range(0.0, 1080.0));
+		r.x_origin = tw as f32/2.0;
+		r.y_origin = th as f32/2.0;
 
 		world.set(&ent, r);
 
@@ -93,7 +113,7 @@ fn run() -> Result<(), Error>
 
 
 	let sp = Sprite::new(&ss, "idle");
-	let mut r = Renderable::new(&sp.get_texture(), 500, 400);
+	let mut r = Renderable::new(&sp.get_texture(), 500.0, 400.0);
 	r.x_scale = 4.0;
 	r.y_scale = 4.0;
 	
@@ -105,12 +125,57 @@ fn run() -> Result<(), Error>
 	world.add_system("sprite", SpriteSystem::new());
 
 
+	let title = world.new_entity();
+	let mut t = Text::new(&font, "JMGE test", 960.0, 100.0);
+	t.align = TextAlign::Center;
+	t.x_scale = 4.0;
+	t.y_scale = 4.0;
+	world.set(&title, t);
+
+
+
+	let sparks_def = Rc::new(EmitterDef::parse(include_str!("../../jmge/assets/sparks.json"))?);
+
+	let sparks = world.new_entity();
+	world.set(&sparks, ParticleEmitter::new(&sparks_def, &white, 960.0, 700.0));
+
+	world.add_system("particles", ParticleSystem::new());
+
+
+	let lamp = world.new_entity();
+	world.set(&lamp, Light::point(960.0, 540.0, 400.0, Color::rgb(1.0, 0.9, 0.7)));
+
+	let torch = world.new_entity();
+	world.set(&torch, Light::spot(500.0, 400.0, 600.0, Color::rgb(0.4, 0.6, 1.0), 0.0, 0.5));
+
+	let mut lights = LightRenderer::new(1920, 1080)?;
+	world.add_system("lights", lights.system());
+	let mut use_lights = false;
+
 
 	let ts = Rc::new(Texture::from_file("tileset.png", false)?);
-	let tm = TileMap::new(32, 32, 16, 16, &ts);
+	let mut tm = TileMap::new(32, 32, 16, 16, &ts);
+
+	for y in 0..32
+	{
+		for x in 0..32
+			{ tm.set_tile(x, y, Some((x+y) % tm.tile_count())); }
+	}
 
+	let mut tmr = TileMapRenderer::new()?;
+	let mut show_map = false;
 
-	let tmr = TileMapRenderer::new()?;
+	let mut post = PostProcess::new(1920, 1080)?;
+	post.add(Effect::Bloom { threshold: 0.7, intensity: 1.5 });
+	post.add(Effect::Scanlines { intensity: 0.2, count: 540.0 });
+	post.add(Effect::Vignette { radius: 0.75, softness: 0.45 });
+	let mut use_post = false;
+
+	let mut recorder: Option<FrameRecorder> = None;
+	let mut screenshot = false;
+
+	let mut stats = RenderStats::default();
+	let mut show_stats = false;
 
 
 	while !wnd.should_close()
@@ -121,7 +186,30 @@ fn run() -> Result<(), Error>
 		world.run_all();
 
 		wnd.poll_events();
+		actions.update(wnd.input());
+
+		for ev in wnd.events()
+		{
+			match ev
+			{
+				Event::FileDrop (paths) => for p in paths.iter() { println!("Dropped {}", p.display()); },
+				Event::FocusLost => sink = None,
+				_ => (),
+			}
+		}
 		
+		if wnd.text_input().is_active()
+		{
+			// Type a name, enter finishes it
+			if wnd.text_input().apply(&mut name)
+				{ wnd.stop_text_input(); }
+		}
+		else if wnd.keyboard().key_pressed(Key::T)
+		{
+			name.clear();
+			wnd.start_text_input();
+		}
+
 		let kbd = wnd.keyboard();
 		if kbd.key_pressed(Key::Escape)
 		{
@@ -161,7 +249,17 @@ fn run() -> Result<(), Error>
 			world.remove_system("rotater");
 		}
 
-		if kbd.key_pressed(Key::Z)
+		let (_, scroll) = wnd.mouse().scroll();
+
+		if scroll!=0.0
+		{
+			// Zoom the adventurer with the wheel
+			let mut r = world.get_mut::<Renderable>(&adv);
+			r.x_scale = (r.x_scale + scroll).clamp(1.0, 8.0);
+			r.y_scale = r.x_scale;
+		}
+
+		if actions.pressed("attack") || wnd.mouse().double_clicked(0)
 		{
 			let mut sp = world.get_mut::<Sprite>(&adv);
 			sp.set_tag("attack1");
@@ -169,17 +267,137 @@ fn run() -> Result<(), Error>
 			audio.play_detached(&sound);
 		}
 
+		if kbd.key_pressed(Key::Left)
+		{
+			world.get_mut::<Renderable>(&adv).flip_x = true;
+		}
+
+		if kbd.key_pressed(Key::Right)
+		{
+			world.get_mut::<Renderable>(&adv).flip_x = false;
+		}
+
+		if kbd.key_pressed(Key::P)
+		{
+			use_post = !use_post;
+		}
+
+		if wnd.mouse().but_pressed(1)
+		{
+			let mut em = world.get_mut::<ParticleEmitter>(&sparks);
+			em.x = wnd.mouse().pos().0 as f32;
+			em.y = wnd.mouse().pos().1 as f32;
+			em.burst(100);
+		}
+
+		if kbd.key_pressed(Key::L)
+		{
+			use_lights = !use_lights;
+		}
+
+		if kbd.key_pressed(Key::M)
+		{
+			show_map = !show_map;
+		}
+
+		if kbd.key_pressed(Key::F3)
+		{
+			show_stats = !show_stats;
+		}
+
+		if kbd.key_pressed(Key::F12)
+		{
+			screenshot = true;
+		}
+
+		if kbd.key_pressed(Key::F11)
+		{
+			// Start recording every other frame, or stop and wait for the files
+			match recorder.take()
+			{
+				Some (rec) => rec.finish()?,
+				None =>
+					{
+						let (w, h) = wnd.framebuffer_size();
+						recorder = Some(FrameRecorder::new(w, h, 2, RecordFormat::Png { prefix: String::from("frame") }));
+					},
+			}
+		}
+
 		if kbd.key_pressed(Key::V)
 		{
 			wnd.set_vsync(!wnd.vsync());
 		}
 
+		if wnd.keyboard().key_pressed(Key::C)
+		{
+			// The yellow circle stands in for the hidden cursor
+			let mode = if wnd.cursor_mode()==CursorMode::Normal { CursorMode::Hidden } else { CursorMode::Normal };
+			wnd.set_cursor_mode(mode);
+		}
+
+		if wnd.was_resized()
+		{
+			// Keep the offscreen buffers at the window's resolution
+			let (w, h) = wnd.framebuffer_size();
+			lights.resize(w, h)?;
+			post.resize(w, h)?;
+		}
+
+		let (mx, my) = wnd.mouse().pos();
+
+		{
+			let mut l = world.get_mut::<Light>(&lamp);
+			l.x = mx as f32;
+			l.y = my as f32;
+		}
+
 		
 		rend.add_world(&world);
 
+		rend.add_text(&font, 20.0, 40.0, Color::rgb(1.0, 1.0, 1.0), &format!("{} fps", wnd.fps()));
+		rend.add_text(&font, 20.0, 100.0, Color::rgb(1.0, 1.0, 1.0), &format!("Name: {}", name));
+
+		if show_stats
+		{
+			rend.add_text(&font, 20.0, 60.0, Color::rgb(1.0, 1.0, 1.0), &stats.to_string());
+			rend.add_text(&font, 20.0, 80.0, Color::rgb(1.0, 1.0, 1.0), &wnd.timer().to_string());
+		}
+
+		rend.add_shape(Shape::rect(20.0, 20.0, 200.0, 16.0).with_color(Color::rgb(0.8, 0.1, 0.1)));
+		rend.add_shape(Shape::rect_outline(20.0, 20.0, 200.0, 16.0, 2.0));
+		rend.add_shape(Shape::circle_outline(wnd.mouse().pos().0 as f32, wnd.mouse().pos().1 as f32, 24.0, 3.0).with_color(Color::rgba(1.0, 1.0, 0.0, 0.7)));
+
+		if use_lights
+		{
+			lights.prepare(&mut rend, wnd.projection_matrix());
+			wnd.bind_default_framebuffer();
+		}
+
+		if use_post
+			{ post.begin(); }
+
 		wnd.clear(Color::rgb(0.3, 0.5, 1.0));
 
-		rend.render(wnd.projection_matrix());
+		if show_map
+			{ tmr.render(&tm, 0.0, 0.0, wnd.projection_matrix()); }
+
+		stats = rend.render(wnd.projection_matrix());
+
+		if use_lights
+			{ lights.apply(); }
+
+		if use_post
+			{ post.present(&wnd); }
+
+		if screenshot
+		{
+			wnd.screenshot().save_png("screenshot.png")?;
+			screenshot = false;
+		}
+
+		if let Some(ref mut rec) = recorder
+			{ rec.capture(); }
 
 		wnd.swap();
 	}